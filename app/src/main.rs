@@ -1,4 +1,5 @@
 use anyhow::{Error, Result, anyhow, bail};
+use clap::Parser;
 use cpp::cpp;
 use directories::BaseDirs;
 use log::{debug, error, info, warn};
@@ -10,10 +11,11 @@ use std::io::{ErrorKind, Read, Write};
 use std::net::Shutdown;
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::PathBuf;
+use std::process::{Command, Stdio};
 use std::rc::Rc;
 use std::sync::mpsc;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{env, fs, thread};
 use tungstenite::http::{StatusCode, Uri};
 use tungstenite::protocol::frame::coding::CloseCode;
@@ -28,6 +30,11 @@ use crate::window_handler::{WindowHandler, WindowMessage};
 use window_properties::WindowProperties;
 
 const APP_NAME: &str = "pipeweaver-app";
+const DAEMON_NAME: &str = "pipeweaver-daemon";
+const DEFAULT_ADDRESS: &str = "localhost:14565";
+
+/// How long we wait for a daemon we've spawned to start accepting connections.
+const DAEMON_START_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// How long the IPC thread waits for Qt to pong back before declaring it hung.
 const QT_PING_TIMEOUT: Duration = Duration::from_millis(200);
@@ -52,6 +59,23 @@ qrc!(pipeweaver_resources,
     },
 );
 
+/// The app is solely responsible for its own window, the daemon never needs to be involved in
+/// opening it. If the daemon isn't running when the app starts, the app will start it with
+/// `--background` (so it doesn't try to open a second UI) unless told not to, which lets an
+/// autostart entry simply launch the app against whatever daemon is (or isn't) running.
+#[derive(Parser, Debug)]
+#[command(about, version, author)]
+struct Args {
+    /// Don't start the daemon if it isn't already running, exit instead
+    #[arg(long)]
+    no_spawn_daemon: bool,
+
+    /// Connect to an already running daemon at this address (host:port), implies
+    /// --no-spawn-daemon
+    #[arg(long, value_name = "ADDRESS")]
+    connect: Option<String>,
+}
+
 fn main() -> Result<()> {
     if let Err(e) = real_main() {
         display_error(format!("{e}"));
@@ -62,6 +86,10 @@ fn main() -> Result<()> {
 }
 
 fn real_main() -> Result<()> {
+    let args = Args::parse();
+    let spawn_daemon = !args.no_spawn_daemon && args.connect.is_none();
+    let address = args.connect.unwrap_or(DEFAULT_ADDRESS.to_string());
+
     let mut flags = vec![
         "--disable-software-rasterizer",
         "--disable-dev-shm-usage",
@@ -101,7 +129,7 @@ fn real_main() -> Result<()> {
     let (res_tx, res_rx) = mpsc::channel();
     let notify_websocket = notify_tx.clone();
     thread::spawn(move || {
-        websocket_main_thread(res_tx, notify_websocket);
+        websocket_main_thread(address, spawn_daemon, res_tx, notify_websocket);
     });
 
     if let Err(e) = res_rx.recv()? {
@@ -187,9 +215,14 @@ fn sanitize_quick_controls_style() {
     }
 }
 
-fn websocket_main_thread(res: mpsc::Sender<Result<()>>, tx: mpsc::Sender<WindowMessage>) {
+fn websocket_main_thread(
+    address: String,
+    spawn_daemon: bool,
+    res: mpsc::Sender<Result<()>>,
+    tx: mpsc::Sender<WindowMessage>,
+) {
     let uri = match Uri::builder()
-        .authority("localhost:14565")
+        .authority(address)
         .scheme("ws")
         .path_and_query("/api/websocket")
         .build()
@@ -202,6 +235,7 @@ fn websocket_main_thread(res: mpsc::Sender<Result<()>>, tx: mpsc::Sender<WindowM
     };
 
     let mut window_opened = false;
+    let mut daemon_spawned: Option<Instant> = None;
 
     'top: loop {
         let uri = uri.clone();
@@ -222,6 +256,25 @@ fn websocket_main_thread(res: mpsc::Sender<Result<()>>, tx: mpsc::Sender<WindowM
                     continue 'top;
                 }
 
+                // The daemon may simply not be running yet, if so start it and wait for it
+                if !window_opened && spawn_daemon {
+                    match daemon_spawned {
+                        None => match spawn_background_daemon() {
+                            Ok(()) => {
+                                daemon_spawned = Some(Instant::now());
+                                sleep(Duration::from_millis(500));
+                                continue 'top;
+                            }
+                            Err(spawn_error) => warn!("Unable to start daemon: {spawn_error}"),
+                        },
+                        Some(spawned) if spawned.elapsed() < DAEMON_START_TIMEOUT => {
+                            sleep(Duration::from_millis(500));
+                            continue 'top;
+                        }
+                        Some(_) => warn!("Daemon failed to start in time"),
+                    }
+                }
+
                 // Anything else on the initial connection is a real failure
                 error!("Connect failed with non-retryable error: {e}");
                 if !window_opened {
@@ -279,6 +332,25 @@ fn websocket_main_thread(res: mpsc::Sender<Result<()>>, tx: mpsc::Sender<WindowM
     let _ = tx.send(WindowMessage::Close);
 }
 
+fn spawn_background_daemon() -> Result<()> {
+    // Prefer a daemon alongside our own binary, otherwise leave it to $PATH
+    let daemon = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|parent| parent.join(DAEMON_NAME)))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(DAEMON_NAME));
+
+    info!("Daemon not running, starting {daemon:?}");
+    Command::new(daemon)
+        .arg("--background")
+        .current_dir(env::temp_dir())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
 fn ipc_thread_main(tx: mpsc::Sender<WindowMessage>) -> Result<()> {
     debug!("Spawning IPC Socket Handler");
 
@@ -466,7 +538,6 @@ fn get_runtime_path() -> Result<PathBuf> {
 }
 
 pub fn display_error(message: String) {
-    use std::process::Command;
     if let Err(e) = Command::new("kdialog")
         .arg("--title")
         .arg("Pipeweaver UI")
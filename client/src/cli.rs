@@ -1,8 +1,10 @@
 use clap::ArgAction;
 use clap::{Parser, Subcommand};
 use pipeweaver_shared::{
-    Colour, DeviceType, Mix, MuteState, MuteTarget, NodeType, OrderGroup, Quantum,
+    ChannelMode, Colour, DeviceType, Mix, MuteState, MuteTarget, NodeType, OrderGroup, Quantum,
+    VolumeCurve,
 };
+use std::path::PathBuf;
 
 /// PipeWeaver CLI
 #[derive(Parser, Debug)]
@@ -13,6 +15,35 @@ pub struct Cli {
     #[arg(long)]
     pub status: bool,
 
+    /// Display the version of the running daemon.
+    #[arg(long)]
+    pub daemon_version: bool,
+
+    /// Display recent xruns (audio dropouts) detected by the daemon.
+    #[arg(long)]
+    pub xruns: bool,
+
+    /// Display temporary states (previews, auto mutes, etc.) currently active in the daemon.
+    #[arg(long)]
+    pub overlays: bool,
+
+    /// Display every port on a physical device, by its Pipewire node id.
+    #[arg(long)]
+    pub device_ports: Option<u32>,
+
+    /// Display the requested and running audio quantum, and the latency it results in.
+    #[arg(long)]
+    pub quantum: bool,
+
+    /// Display a single reading from every meter, switching metering on briefly if needed.
+    #[arg(long)]
+    pub meters: bool,
+
+    /// Re-issue every request recorded in an audit log, for reproducing bugs. This is a file
+    /// name in the daemon's audit directory.
+    #[arg(long)]
+    pub replay_audit: Option<PathBuf>,
+
     /// Use HTTP Instead of IPC. Specify base path as the param (defaults to http://localhost:14565)
     #[arg(long, num_args=0..=1, default_missing_value="http://localhost:14565")]
     pub use_http: Option<String>,
@@ -54,6 +85,25 @@ pub enum NodeCommands {
         #[arg(value_enum)]
         node_type: NodeType,
         name: String,
+
+        /// Route the new source to the Monitor Target
+        #[arg(long)]
+        monitor: bool,
+    },
+    /// Set every source on a mix to the same volume
+    SetAllSourceVolumes {
+        #[arg(value_enum)]
+        mix: Mix,
+
+        /// Volume as a percentage (0-100)
+        #[arg(value_parser = percent_value)]
+        volume: u8,
+    },
+    /// Set every target to the same volume
+    SetAllTargetVolumes {
+        /// Volume as a percentage (0-100)
+        #[arg(value_parser = percent_value)]
+        volume: u8,
     },
     /// Operate on an existing node by ID
     Edit {
@@ -73,6 +123,8 @@ pub enum NodeIdCommands {
         colour: Colour,
     },
     Remove,
+    /// Return the node's volumes and mute states to default, keeping routes and devices
+    Reset,
     SetVolume {
         /// Volume as a percentage (0-100)
         #[arg(value_parser = percent_value)]
@@ -111,6 +163,11 @@ pub enum NodeIdCommands {
         #[arg(value_enum)]
         target: MuteTarget,
     },
+    SetMuteTargetLabel {
+        #[arg(value_enum)]
+        target: MuteTarget,
+        label: String,
+    },
     SetTargetMuteState {
         #[arg(value_enum)]
         state: MuteState,
@@ -121,12 +178,31 @@ pub enum NodeIdCommands {
     RemovePhysicalNode {
         index: usize,
     },
+    SetPhysicalNodeChannelMode {
+        index: usize,
+
+        /// The Channel Mode to force, omit to return to auto detection
+        #[arg(value_enum)]
+        mode: Option<ChannelMode>,
+    },
+    /// Split a stereo device into two mono sources, one for each channel
+    SplitPhysicalNode {
+        index: usize,
+    },
     SetOrderGroup {
         #[arg(value_enum)]
         group: OrderGroup,
     },
     SetOrder {
         order: u8,
+
+        /// Only apply the position if the node is still in this group
+        #[arg(long, value_enum)]
+        group: Option<OrderGroup>,
+    },
+    /// Set the number of channels on a Virtual Target (even, 2-16)
+    SetChannels {
+        channels: u8,
     },
 }
 
@@ -144,6 +220,19 @@ pub enum RouteCommands {
         source: String,
         target: String,
     },
+    /// Set which channel pair of a multichannel target a source is routed to
+    SetChannelPair {
+        source: String,
+        target: String,
+        pair: u8,
+    },
+    /// Arm or disarm a source for recording, armed sources are routed to the recorder target
+    RecordArm {
+        source: String,
+
+        #[arg(value_parser, action = ArgAction::Set)]
+        armed: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -193,12 +282,55 @@ pub enum DaemonCommands {
         quantum: Quantum,
     },
     ClearAudioQuantum,
+    /// Set how volumes are mapped to Pipewire, this should match your other volume controls
+    SetVolumeCurve {
+        #[arg(value_enum)]
+        curve: VolumeCurve,
+    },
     SetUseBrowser {
         #[arg(value_parser, action = ArgAction::Set)]
         enabled: bool,
     },
+    SetAutoCleanupOrphans {
+        #[arg(value_parser, action = ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Set the colours new nodes cycle through, leave empty for the defaults
+    SetDefaultPalette {
+        colours: Vec<Colour>,
+    },
+    /// Request a realtime priority (1-99) for the filter thread, leave empty to let Pipewire decide
+    SetRealtimePriority {
+        priority: Option<u8>,
+    },
+    /// Temporarily stop devices being automatically attached to nodes as they appear
+    SetAutoMatch {
+        #[arg(value_parser, action = ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Remove nodes left behind by a previous run of the daemon
+    CleanupOrphans,
     OpenInterface,
     ResetAudio,
+    /// Write meter events to a named pipe in the daemon's runtime directory, omit the file
+    /// name to stop
+    SetMeterPipe {
+        path: Option<PathBuf>,
+    },
+    /// Record every request the daemon receives to a file in the daemon's audit directory,
+    /// omit the file name to stop
+    SetAuditLog {
+        path: Option<PathBuf>,
+    },
+    /// Temporarily listen to a mix on the Monitor Target, set false to return to normal
+    PreviewMix {
+        #[arg(value_enum)]
+        mix: Mix,
+        #[arg(value_parser, action = ArgAction::Set)]
+        active: bool,
+    },
+    /// Check every route's links against Pipewire, fixing any which are missing or stale
+    ReconcileLinks,
 }
 
 // Example argument validation function (if needed)
@@ -12,7 +12,7 @@ use pipeweaver_ipc::clients::ipc::ipc_client::IPCClient;
 use pipeweaver_ipc::clients::ipc::ipc_socket::Socket;
 use pipeweaver_ipc::clients::web::web_client::WebClient;
 use pipeweaver_ipc::commands::{
-    APICommand, DaemonCommand, DaemonRequest, DaemonResponse, PWCommandResponse,
+    APICommand, DaemonCommand, DaemonRequest, DaemonResponse, PROTOCOL_VERSION, PWCommandResponse,
 };
 use pipeweaver_shared::AppDefinition;
 use std::path::PathBuf;
@@ -36,6 +36,21 @@ async fn main() -> Result<()> {
         Box::new(IPCClient::new(socket))
     };
 
+    // Make sure we're speaking the same language as the daemon
+    let hello = DaemonRequest::Hello {
+        client_protocol_version: PROTOCOL_VERSION,
+    };
+    match client.send(&hello).await? {
+        DaemonResponse::Hello(hello) if !hello.compatible => bail!(
+            "Client Protocol Version {} is not supported by the Daemon (Protocol {})",
+            PROTOCOL_VERSION,
+            hello.protocol_version
+        ),
+        DaemonResponse::Hello(_) => {}
+        DaemonResponse::Err(e) => bail!("{}", e),
+        _ => bail!("Unexpected Response"),
+    }
+
     // Poll the Status
     let status = client.get_status().await?;
 
@@ -59,6 +74,92 @@ async fn main() -> Result<()> {
                 }
                 PWCommandResponse::Err(e) => bail!("{}", e),
             },
+            DaemonResponse::LinkReport(report) => {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+            _ => bail!("Unexpected Response"),
+        }
+    }
+
+    if cli.daemon_version {
+        match client.send(&DaemonRequest::GetVersion).await? {
+            DaemonResponse::Version(version) => {
+                println!(
+                    "{} Daemon v{} - {} (Protocol {})",
+                    APP_NAME, version.version, version.git_hash, version.protocol_version
+                );
+                if version.protocol_version != PROTOCOL_VERSION {
+                    println!(
+                        "Warning: Client Protocol Version {} does not match the Daemon",
+                        PROTOCOL_VERSION
+                    );
+                }
+            }
+            DaemonResponse::Err(e) => bail!("{}", e),
+            _ => bail!("Unexpected Response"),
+        }
+    }
+
+    if cli.xruns {
+        match client.send(&DaemonRequest::GetXrunHistory).await? {
+            DaemonResponse::XrunHistory(history) => {
+                for xrun in history {
+                    let driver = xrun.driver_name.unwrap_or(xrun.driver_id.to_string());
+                    println!(
+                        "{}: {:?} {}us (Driver: {})",
+                        xrun.timestamp, xrun.severity, xrun.duration, driver
+                    );
+                }
+            }
+            DaemonResponse::Err(e) => bail!("{}", e),
+            _ => bail!("Unexpected Response"),
+        }
+    }
+
+    if cli.overlays {
+        match client.send(&DaemonRequest::GetActiveOverlays).await? {
+            DaemonResponse::ActiveOverlays(overlays) => {
+                println!("{}", serde_json::to_string_pretty(&overlays)?);
+            }
+            DaemonResponse::Err(e) => bail!("{}", e),
+            _ => bail!("Unexpected Response"),
+        }
+    }
+
+    if let Some(node_id) = cli.device_ports {
+        match client.send(&DaemonRequest::GetDevicePorts(node_id)).await? {
+            DaemonResponse::DevicePorts(ports) => {
+                println!("{}", serde_json::to_string_pretty(&ports)?);
+            }
+            DaemonResponse::Err(e) => bail!("{}", e),
+            _ => bail!("Unexpected Response"),
+        }
+    }
+
+    if cli.quantum {
+        match client.send(&DaemonRequest::GetAudioQuantum).await? {
+            DaemonResponse::AudioQuantum(quantum) => {
+                println!("{}", serde_json::to_string_pretty(&quantum)?);
+            }
+            DaemonResponse::Err(e) => bail!("{}", e),
+            _ => bail!("Unexpected Response"),
+        }
+    }
+
+    if cli.meters {
+        match client.send(&DaemonRequest::GetMeters).await? {
+            DaemonResponse::Meters(meters) => {
+                println!("{}", serde_json::to_string_pretty(&meters)?);
+            }
+            DaemonResponse::Err(e) => bail!("{}", e),
+            _ => bail!("Unexpected Response"),
+        }
+    }
+
+    if let Some(path) = cli.replay_audit {
+        match client.send(&DaemonRequest::ReplayAudit(path)).await? {
+            DaemonResponse::Ok => {}
+            DaemonResponse::Err(e) => bail!("{}", e),
             _ => bail!("Unexpected Response"),
         }
     }
@@ -76,7 +177,13 @@ fn handle_node_command(cmd: cli::NodeCommands) -> DaemonRequest {
     use cli::NodeCommands::*;
     use cli::NodeIdCommands as IdCmd;
     let api_cmd = match cmd {
-        Create { node_type, name } => APICommand::CreateNode(node_type, name),
+        Create {
+            node_type,
+            name,
+            monitor,
+        } => APICommand::CreateNode(node_type, name, monitor),
+        SetAllSourceVolumes { mix, volume } => APICommand::SetAllSourceVolumes(mix, volume),
+        SetAllTargetVolumes { volume } => APICommand::SetAllTargetVolumes(volume),
         Edit {
             name: src_name,
             command,
@@ -84,6 +191,7 @@ fn handle_node_command(cmd: cli::NodeCommands) -> DaemonRequest {
             IdCmd::Rename { name } => APICommand::RenameNodeByName(src_name, name),
             IdCmd::SetColour { colour } => APICommand::SetNodeColourByName(src_name, colour),
             IdCmd::Remove => APICommand::RemoveNodeByName(src_name),
+            IdCmd::Reset => APICommand::ResetNodeByName(src_name),
             IdCmd::SetVolume { mix, volume } => APICommand::SetVolumeByName(src_name, mix, volume),
             IdCmd::SetSourceVolumeLinked { linked } => {
                 APICommand::SetSourceVolumeLinkedByName(src_name, linked)
@@ -104,6 +212,9 @@ fn handle_node_command(cmd: cli::NodeCommands) -> DaemonRequest {
             IdCmd::ClearMuteTargetNodes { target } => {
                 APICommand::ClearMuteTargetNodesByName(src_name, target)
             }
+            IdCmd::SetMuteTargetLabel { target, label } => {
+                APICommand::SetMuteTargetLabelByName(src_name, target, label)
+            }
             IdCmd::SetTargetMuteState { state } => {
                 APICommand::SetTargetMuteStatesByName(src_name, state)
             }
@@ -113,8 +224,20 @@ fn handle_node_command(cmd: cli::NodeCommands) -> DaemonRequest {
             IdCmd::RemovePhysicalNode { index } => {
                 APICommand::RemovePhysicalNodeByName(src_name, index)
             }
+            IdCmd::SetPhysicalNodeChannelMode { index, mode } => {
+                APICommand::SetPhysicalNodeChannelModeByName(src_name, index, mode)
+            }
+            IdCmd::SplitPhysicalNode { index } => {
+                APICommand::SplitPhysicalNodeByName(src_name, index)
+            }
             IdCmd::SetOrderGroup { group } => APICommand::SetOrderGroupByName(src_name, group),
-            IdCmd::SetOrder { order } => APICommand::SetOrderByName(src_name, order),
+            IdCmd::SetOrder { order, group } => match group {
+                Some(group) => APICommand::SetOrderInGroupByName(src_name, group, order),
+                None => APICommand::SetOrderByName(src_name, order),
+            },
+            IdCmd::SetChannels { channels } => {
+                APICommand::SetTargetChannelsByName(src_name, channels)
+            }
         },
     };
     DaemonRequest::Pipewire(api_cmd)
@@ -166,6 +289,12 @@ fn handle_route_command(cmd: cli::RouteCommands) -> DaemonRequest {
             enabled,
         } => APICommand::SetRouteByNames(source, target, enabled),
         Toggle { source, target } => APICommand::ToggleRouteByNames(source, target),
+        SetChannelPair {
+            source,
+            target,
+            pair,
+        } => APICommand::SetRouteChannelPairByNames(source, target, pair),
+        RecordArm { source, armed } => APICommand::SetRecordArmByName(source, armed),
     };
     DaemonRequest::Pipewire(api_cmd)
 }
@@ -175,10 +304,20 @@ fn handle_daemon_command(cmd: cli::DaemonCommands) -> DaemonRequest {
     let daemon_cmd = match cmd {
         SetAutoStart { enabled } => DaemonCommand::SetAutoStart(enabled),
         SetUseBrowser { enabled } => DaemonCommand::SetUseBrowser(enabled),
+        SetAutoCleanupOrphans { enabled } => DaemonCommand::SetAutoCleanupOrphans(enabled),
+        SetDefaultPalette { colours } => DaemonCommand::SetDefaultPalette(colours),
+        SetRealtimePriority { priority } => DaemonCommand::SetRealtimePriority(priority),
+        SetAutoMatch { enabled } => DaemonCommand::SetAutoMatch(enabled),
+        CleanupOrphans => DaemonCommand::CleanupOrphans,
         SetAudioQuantum { quantum } => DaemonCommand::SetAudioQuantum(Some(quantum)),
         ClearAudioQuantum => DaemonCommand::SetAudioQuantum(None),
+        SetVolumeCurve { curve } => DaemonCommand::SetVolumeCurve(curve),
         OpenInterface => DaemonCommand::OpenInterface,
         ResetAudio => DaemonCommand::ResetAudio,
+        SetMeterPipe { path } => DaemonCommand::SetMeterPipe(path),
+        SetAuditLog { path } => DaemonCommand::SetAuditLog(path),
+        PreviewMix { mix, active } => DaemonCommand::PreviewMix(mix, active),
+        ReconcileLinks => DaemonCommand::ReconcileLinks,
     };
     DaemonRequest::Daemon(daemon_cmd)
 }
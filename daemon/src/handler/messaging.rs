@@ -1,11 +1,22 @@
+use anyhow::Result;
+use std::collections::HashMap;
 use tokio::sync::oneshot;
+use ulid::Ulid;
 
 use pipeweaver_ipc::commands::{
-    APICommand, DaemonCommand, DaemonResponse, DaemonStatus, PWCommandResponse,
+    APICommand, ActiveOverlays, AudioQuantum, DaemonCommand, DaemonRequest, DaemonResponse,
+    DaemonStatus, DevicePorts, PWCommandResponse, RoutingMatrix, XrunEvent,
 };
 
 pub enum DaemonMessage {
     GetStatus(oneshot::Sender<DaemonStatus>),
     RunDaemon(DaemonCommand, oneshot::Sender<DaemonResponse>),
     RunPipewire(APICommand, oneshot::Sender<PWCommandResponse>),
+    RunTransaction(Vec<DaemonRequest>, oneshot::Sender<Vec<DaemonResponse>>),
+    GetXrunHistory(oneshot::Sender<Vec<XrunEvent>>),
+    GetActiveOverlays(oneshot::Sender<ActiveOverlays>),
+    GetRoutingMatrix(oneshot::Sender<RoutingMatrix>),
+    GetDevicePorts(u32, oneshot::Sender<Result<DevicePorts>>),
+    GetAudioQuantum(oneshot::Sender<AudioQuantum>),
+    GetMeters(oneshot::Sender<HashMap<Ulid, u8>>),
 }
@@ -1,19 +1,113 @@
-use anyhow::{Context, Result, anyhow};
-use log::error;
+use anyhow::{Context, Result, anyhow, bail};
+use log::{debug, error, info, warn};
+use std::path::PathBuf;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::oneshot;
 
 use crate::handler::messaging::DaemonMessage;
-use pipeweaver_ipc::commands::{DaemonRequest, DaemonResponse, PWCommandResponse};
+use crate::servers::audit_log::read_audit_log;
+use crate::{HASH, VERSION};
+use pipeweaver_ipc::commands::{
+    DaemonCommand, DaemonRequest, DaemonResponse, HelloResponse, MIN_PROTOCOL_VERSION,
+    PROTOCOL_VERSION, PWCommandResponse, VersionInfo,
+};
 
 pub type Messenger = Sender<DaemonMessage>;
 type Response = Result<DaemonResponse>;
 
+/// Tracks the protocol handshake for a single client connection. Clients which never send a
+/// `Hello` are assumed to be speaking the current protocol.
+#[derive(Debug, Default)]
+pub struct ConnectionState {
+    received_request: bool,
+}
+
+impl ConnectionState {
+    /// Checks whether this request is valid at this point in the connection
+    pub fn check_request(&mut self, request: &DaemonRequest) -> Result<()> {
+        let first = !self.received_request;
+        self.received_request = true;
+
+        match request {
+            DaemonRequest::Hello { .. } if !first => {
+                bail!("Hello must be the first message sent on a connection")
+            }
+            DaemonRequest::Hello { .. } => {}
+            _ if first => debug!("Client connected without a Hello, assuming current protocol"),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns true if the response means the connection should now be closed
+    pub fn is_rejected(response: &DaemonResponse) -> bool {
+        matches!(
+            response,
+            DaemonResponse::Hello(HelloResponse {
+                compatible: false,
+                ..
+            })
+        )
+    }
+}
+
+/// Re-issues the requests from an Audit Log one at a time, failures are logged but don't stop
+/// the replay, as a later request may depend on the state an earlier one was expected to fail in.
+async fn replay_audit(path: PathBuf, sender: &Messenger) -> Response {
+    let entries = read_audit_log(&path)?;
+    info!(
+        "Replaying {} requests from {}",
+        entries.len(),
+        path.display()
+    );
+
+    for entry in entries {
+        // Anything controlling the connection or the log itself isn't part of the trace
+        if matches!(
+            entry.request,
+            DaemonRequest::Hello { .. }
+                | DaemonRequest::ReplayAudit(_)
+                | DaemonRequest::Daemon(DaemonCommand::SetAuditLog(_))
+        ) {
+            continue;
+        }
+
+        debug!("Replaying {:?} from {}", entry.request, entry.source);
+        match Box::pin(handle_packet(entry.request, sender)).await {
+            Ok(DaemonResponse::Err(e)) => warn!("Replayed Request Failed: {}", e),
+            Err(e) => warn!("Replayed Request Failed: {}", e),
+            _ => {}
+        }
+    }
+    Ok(DaemonResponse::Ok)
+}
+
 /// This is pretty similar to the GoXLR Utility, as very little really needs to change here.
 pub async fn handle_packet(request: DaemonRequest, sender: &Messenger) -> Response {
     // Ok, we just match the request, and send it off where it needs to go..
     let response = match request {
         DaemonRequest::Ping => Ok(DaemonResponse::Ok),
+        DaemonRequest::Hello {
+            client_protocol_version,
+        } => {
+            let compatible =
+                (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&client_protocol_version);
+            if !compatible {
+                warn!(
+                    "Rejecting client with Protocol Version {}, supported: {} - {}",
+                    client_protocol_version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+                );
+            }
+            Ok(DaemonResponse::Hello(HelloResponse {
+                protocol_version: PROTOCOL_VERSION,
+                compatible,
+            }))
+        }
+        DaemonRequest::GetVersion => Ok(DaemonResponse::Version(VersionInfo {
+            version: VERSION.to_string(),
+            git_hash: HASH.to_string(),
+            protocol_version: PROTOCOL_VERSION,
+        })),
         DaemonRequest::GetStatus => {
             let (tx, rx) = oneshot::channel();
 
@@ -34,8 +128,8 @@ pub async fn handle_packet(request: DaemonRequest, sender: &Messenger) -> Respon
                 .map_err(|e| anyhow!(e.to_string()))
                 .context("Failed to send message to device manager")?;
 
-            rx.await.context("Error from device manager")?;
-            Ok(DaemonResponse::Ok)
+            let result = rx.await.context("Error from device manager")?;
+            Ok(result)
         }
         DaemonRequest::Pipewire(command) => {
             let (tx, rx) = oneshot::channel();
@@ -48,6 +142,84 @@ pub async fn handle_packet(request: DaemonRequest, sender: &Messenger) -> Respon
             let result = rx.await.context("Error from Device Manager")?;
             Ok(DaemonResponse::Pipewire(result))
         }
+        DaemonRequest::Transaction(requests) => {
+            let (tx, rx) = oneshot::channel();
+            sender
+                .send(DaemonMessage::RunTransaction(requests, tx))
+                .await
+                .map_err(|e| anyhow!(e.to_string()))
+                .context("Failed to send message to device manager")?;
+
+            let result = rx.await.context("Error from Device Manager")?;
+            Ok(DaemonResponse::Transaction(result))
+        }
+        DaemonRequest::GetXrunHistory => {
+            let (tx, rx) = oneshot::channel();
+            sender
+                .send(DaemonMessage::GetXrunHistory(tx))
+                .await
+                .map_err(|e| anyhow!(e.to_string()))
+                .context("Failed to send message to device manager")?;
+
+            let result = rx.await.context("Error from device manager")?;
+            Ok(DaemonResponse::XrunHistory(result))
+        }
+        DaemonRequest::GetActiveOverlays => {
+            let (tx, rx) = oneshot::channel();
+            sender
+                .send(DaemonMessage::GetActiveOverlays(tx))
+                .await
+                .map_err(|e| anyhow!(e.to_string()))
+                .context("Failed to send message to device manager")?;
+
+            let result = rx.await.context("Error from device manager")?;
+            Ok(DaemonResponse::ActiveOverlays(result))
+        }
+        DaemonRequest::GetRoutingMatrix => {
+            let (tx, rx) = oneshot::channel();
+            sender
+                .send(DaemonMessage::GetRoutingMatrix(tx))
+                .await
+                .map_err(|e| anyhow!(e.to_string()))
+                .context("Failed to send message to device manager")?;
+
+            let result = rx.await.context("Error from device manager")?;
+            Ok(DaemonResponse::RoutingMatrix(result))
+        }
+        DaemonRequest::GetDevicePorts(node_id) => {
+            let (tx, rx) = oneshot::channel();
+            sender
+                .send(DaemonMessage::GetDevicePorts(node_id, tx))
+                .await
+                .map_err(|e| anyhow!(e.to_string()))
+                .context("Failed to send message to device manager")?;
+
+            let result = rx.await.context("Error from device manager")?;
+            Ok(DaemonResponse::DevicePorts(result?))
+        }
+        DaemonRequest::GetAudioQuantum => {
+            let (tx, rx) = oneshot::channel();
+            sender
+                .send(DaemonMessage::GetAudioQuantum(tx))
+                .await
+                .map_err(|e| anyhow!(e.to_string()))
+                .context("Failed to send message to device manager")?;
+
+            let result = rx.await.context("Error from device manager")?;
+            Ok(DaemonResponse::AudioQuantum(result))
+        }
+        DaemonRequest::GetMeters => {
+            let (tx, rx) = oneshot::channel();
+            sender
+                .send(DaemonMessage::GetMeters(tx))
+                .await
+                .map_err(|e| anyhow!(e.to_string()))
+                .context("Failed to send message to device manager")?;
+
+            let result = rx.await.context("Error from device manager")?;
+            Ok(DaemonResponse::Meters(result))
+        }
+        DaemonRequest::ReplayAudit(path) => replay_audit(path, sender).await,
     };
 
     match &response {
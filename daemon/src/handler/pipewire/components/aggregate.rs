@@ -0,0 +1,169 @@
+use crate::handler::pipewire::components::load_profile::LoadProfile;
+use crate::handler::pipewire::components::node::NodeManagement;
+use crate::handler::pipewire::manager::PipewireManager;
+use anyhow::{Result, anyhow, bail};
+use log::debug;
+use pipeweaver_pipewire::PipewireMessage::SetApplicationTarget;
+use pipeweaver_pipewire::{MediaClass, NodeTarget, PipewireMessage, oneshot};
+use pipeweaver_profile::AggregateSink;
+use pipeweaver_shared::NodeType;
+use ulid::Ulid;
+
+/// The aggregate sink is a single output device which users can point everything at. It's not
+/// connected to anything itself, instead, when an application lands on it, it's moved to the
+/// source it's mapped to in the profile's `application_mapping`, or to the fallback source if it
+/// has no mapping. If neither exist, the application stays on the aggregate sink, and is silent.
+///
+/// Applications which have been manually moved elsewhere are left alone, the same as normal
+/// application routing, but moving one back onto the aggregate will dispatch it again.
+pub(crate) trait AggregateSinkManagement {
+    async fn aggregate_create(&mut self, name: String, fallback: Option<Ulid>) -> Result<Ulid>;
+    async fn aggregate_set_fallback(&mut self, fallback: Option<Ulid>) -> Result<()>;
+    async fn aggregate_remove(&mut self) -> Result<()>;
+    async fn aggregate_load(&mut self) -> Result<()>;
+
+    fn is_aggregate_sink(&self, id: Ulid) -> bool;
+    fn is_aggregate_target(&self, target: Option<Option<NodeTarget>>) -> bool;
+    fn aggregate_fallback(&self, target: Option<Option<NodeTarget>>) -> Option<Ulid>;
+}
+
+impl AggregateSinkManagement for PipewireManager {
+    async fn aggregate_create(&mut self, name: String, fallback: Option<Ulid>) -> Result<Ulid> {
+        if self.profile.aggregate_sink.is_some() {
+            bail!("An Aggregate Sink already exists");
+        }
+        if self.get_node_id_by_name(&name).is_some() {
+            bail!("Node with name {} already exists", name);
+        }
+        if !Self::is_valid_name(&name) {
+            bail!("Aggregate Sink name {} is invalid", name);
+        }
+        self.aggregate_validate_fallback(fallback)?;
+
+        let id = Ulid::new();
+        let aggregate = AggregateSink { id, name, fallback };
+        self.aggregate_pw_create(&aggregate).await?;
+        self.profile.aggregate_sink = Some(aggregate);
+
+        Ok(id)
+    }
+
+    async fn aggregate_set_fallback(&mut self, fallback: Option<Ulid>) -> Result<()> {
+        self.aggregate_validate_fallback(fallback)?;
+
+        let Some(aggregate) = &mut self.profile.aggregate_sink else {
+            bail!("No Aggregate Sink has been created");
+        };
+        aggregate.fallback = fallback;
+        let aggregate_id = aggregate.id;
+
+        // Anything unmapped that's been sitting on the aggregate can now be sent somewhere
+        if let Some(fallback) = fallback {
+            let on_aggregate = Some(Some(NodeTarget::Node(aggregate_id)));
+            let waiting: Vec<u32> = self
+                .application_nodes
+                .iter()
+                .filter(|(_, node)| node.media_target == on_aggregate)
+                .map(|(id, _)| *id)
+                .collect();
+
+            for id in waiting {
+                debug!("[Aggregate] Dispatching {} to {}", id, fallback);
+                self.pipewire()
+                    .send_message(SetApplicationTarget(id, fallback))?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn aggregate_remove(&mut self) -> Result<()> {
+        let Some(aggregate) = self.profile.aggregate_sink.take() else {
+            bail!("No Aggregate Sink has been created");
+        };
+
+        // Anything still on the sink will be moved by the session manager
+        let message = PipewireMessage::RemoveDeviceNode(aggregate.id);
+        self.pipewire().send_message(message)
+    }
+
+    async fn aggregate_load(&mut self) -> Result<()> {
+        if let Some(aggregate) = self.profile.aggregate_sink.clone() {
+            // Make sure the fallback still exists
+            if self
+                .aggregate_validate_fallback(aggregate.fallback)
+                .is_err()
+                && let Some(aggregate) = &mut self.profile.aggregate_sink
+            {
+                aggregate.fallback = None;
+            }
+            self.aggregate_pw_create(&aggregate).await?;
+        }
+        Ok(())
+    }
+
+    fn is_aggregate_sink(&self, id: Ulid) -> bool {
+        self.profile
+            .aggregate_sink
+            .as_ref()
+            .is_some_and(|aggregate| aggregate.id == id)
+    }
+
+    fn is_aggregate_target(&self, target: Option<Option<NodeTarget>>) -> bool {
+        let Some(aggregate) = &self.profile.aggregate_sink else {
+            return false;
+        };
+
+        // An application is headed for the aggregate if it's been explicitly sent there, or if
+        // it's going to the default device while the aggregate is the default
+        match target {
+            Some(Some(NodeTarget::Node(id))) => id == aggregate.id,
+            Some(Some(NodeTarget::UnmanagedNode(_))) => false,
+            Some(None) | None => self.default_target == Some(NodeTarget::Node(aggregate.id)),
+        }
+    }
+
+    fn aggregate_fallback(&self, target: Option<Option<NodeTarget>>) -> Option<Ulid> {
+        if !self.is_aggregate_target(target) {
+            return None;
+        }
+        self.profile.aggregate_sink.as_ref()?.fallback
+    }
+}
+
+trait AggregateSinkManagementLocal {
+    async fn aggregate_pw_create(&mut self, aggregate: &AggregateSink) -> Result<()>;
+    fn aggregate_validate_fallback(&self, fallback: Option<Ulid>) -> Result<()>;
+}
+
+impl AggregateSinkManagementLocal for PipewireManager {
+    async fn aggregate_pw_create(&mut self, aggregate: &AggregateSink) -> Result<()> {
+        let (send, recv) = oneshot::channel();
+        let mut properties =
+            self.create_node_props(MediaClass::Sink, aggregate.id, &aggregate.name, 100);
+
+        // Applications are moved off the aggregate, nothing upstream manages its volume
+        properties.managed_volume = false;
+        properties.ready_sender = Some(send);
+
+        let message = PipewireMessage::CreateDeviceNode(properties);
+        self.pipewire().send_message(message)?;
+        recv.await.map_err(|_| {
+            anyhow!(
+                "Node {} creation aborted before it became ready",
+                aggregate.id
+            )
+        })?;
+
+        Ok(())
+    }
+
+    fn aggregate_validate_fallback(&self, fallback: Option<Ulid>) -> Result<()> {
+        if let Some(fallback) = fallback {
+            match self.get_node_type(fallback) {
+                Some(NodeType::PhysicalSource) | Some(NodeType::VirtualSource) => {}
+                _ => bail!("Aggregate fallback must be a Source"),
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::handler::pipewire::components::aggregate::AggregateSinkManagement;
 use crate::handler::pipewire::components::node::NodeManagement;
 use crate::handler::pipewire::manager::PipewireManager;
 use anyhow::{Result, bail};
@@ -21,6 +22,11 @@ pub(crate) trait ApplicationManagement {
 
     async fn refresh_applications(&mut self, target: Ulid) -> Result<()>;
 
+    /// Sends every managed application which isn't where its mapping says to its target. After
+    /// Pipewire restarts, streams can come back pointing at node ids which no longer exist, so
+    /// this is run once the graph has been rebuilt and settled.
+    fn reapply_application_targets(&mut self) -> Result<()>;
+
     fn application_appeared(&mut self, node: ApplicationNode) -> Result<()>;
     fn application_target_changed(&mut self, id: u32, target: Target) -> Result<()>;
     fn application_volume_changed(&mut self, id: u32, volume: u8) -> Result<()>;
@@ -86,29 +92,41 @@ impl ApplicationManagement for PipewireManager {
     }
 
     async fn set_application_transient_target(&mut self, id: u32, target: Ulid) -> Result<()> {
-        if let Some(node) = self.application_nodes.get(&id) {
-            if let Some(target) = self.get_application_type_from_node(target) {
-                if target != get_application_type(node.node_class) {
-                    bail!("Target Type mismatch");
-                }
-            } else {
-                bail!("Invalid Target");
-            }
+        if !self.session_manager_present {
+            bail!("No Session Manager running, unable to route applications");
+        }
 
-            // Send this node to its new target
-            let message = SetApplicationTarget(id, target);
-            self.pipewire().send_message(message)?;
+        let Some(node) = self.application_nodes.get(&id) else {
+            bail!("Application {} not Found", id);
+        };
+
+        if let Some(target) = self.get_application_type_from_node(target) {
+            if target != get_application_type(node.node_class) {
+                bail!("Target Type mismatch");
+            }
+        } else {
+            bail!("Invalid Target");
         }
 
+        // Send this node to its new target
+        let message = SetApplicationTarget(id, target);
+        self.pipewire().send_message(message)?;
+
         Ok(())
     }
 
     async fn clear_application_transient_target(&mut self, id: u32) -> Result<()> {
-        // We need to force this transient target back to the default output
-        if self.application_nodes.contains_key(&id) {
-            let message = ClearApplicationTarget(id);
-            self.pipewire().send_message(message)?;
+        if !self.session_manager_present {
+            bail!("No Session Manager running, unable to route applications");
+        }
+
+        if !self.application_nodes.contains_key(&id) {
+            bail!("Application {} not Found", id);
         }
+
+        // We need to force this transient target back to the default output
+        let message = ClearApplicationTarget(id);
+        self.pipewire().send_message(message)?;
         Ok(())
     }
 
@@ -160,6 +178,26 @@ impl ApplicationManagement for PipewireManager {
         Ok(())
     }
 
+    fn reapply_application_targets(&mut self) -> Result<()> {
+        let keys: Vec<u32> = self.application_nodes.keys().copied().collect();
+        for id in keys {
+            if self.application_target_ignore.contains_key(&id) {
+                continue;
+            }
+            let Some(target) = self.get_application_assignment(id) else {
+                continue;
+            };
+
+            let current = self.application_nodes.get(&id).and_then(|n| n.media_target);
+            if current != Some(Some(NodeTarget::Node(target))) {
+                debug!("Reapplying Target {} to {} ({:?})", target, id, current);
+                let message = SetApplicationTarget(id, target);
+                self.pipewire().send_message(message)?;
+            }
+        }
+        Ok(())
+    }
+
     fn application_appeared(&mut self, node: ApplicationNode) -> Result<()> {
         debug!("Node Appeared: {:?}", node);
 
@@ -236,6 +274,13 @@ impl ApplicationManagement for PipewireManager {
                     }
                     Some(route) => {
                         match route {
+                            Some(NodeTarget::Node(target)) if self.is_aggregate_sink(target) => {
+                                // Anything landing on the aggregate gets sent to its source
+                                debug!("Node {} arrived on Aggregate, sending to {}", id, desired);
+                                self.application_target_ignore.remove(&id);
+                                let message = SetApplicationTarget(id, desired);
+                                self.pipewire().send_message(message)?;
+                            }
                             Some(NodeTarget::Node(target)) => {
                                 if target != desired {
                                     debug!("Target is not the desired output, adding to ignore..");
@@ -257,7 +302,7 @@ impl ApplicationManagement for PipewireManager {
                                 });
                             }
                             None => {
-                                if original.is_none() {
+                                if original.is_none() || self.is_aggregate_target(target) {
                                     debug!("Setting Initial Target for {}", id);
                                     // This is the first Pipewire message for this node.
                                     let message = SetApplicationTarget(id, desired);
@@ -329,6 +374,11 @@ impl ApplicationManagementLocal for PipewireManager {
             } else {
                 debug!("Process {} has No entry", node.process_name);
             }
+
+            // Unmapped applications on the aggregate sink are sent to its fallback
+            if node_type == DeviceType::Source {
+                return self.aggregate_fallback(node.media_target);
+            }
         } else {
             warn!("Node Not Present Application Node List: {}", id);
         }
@@ -0,0 +1,108 @@
+use anyhow::{Result, bail};
+use pipeweaver_pipewire::{FilterHandler, FilterProperty, FilterValue};
+use tokio::sync::mpsc;
+use ulid::Ulid;
+
+const PROP_ARMED: u32 = 0;
+
+/// Records a fixed number of frames from its inputs once armed, and sends them upstream as
+/// interleaved samples, tagged with its own id, when done. The buffer is allocated up front, so nothing is allocated
+/// while running inside the audio thread.
+pub struct CaptureFilter {
+    armed: bool,
+
+    channels: usize,
+    frames: usize,
+    buffer: Vec<f32>,
+
+    id: Ulid,
+    callback: mpsc::Sender<(Ulid, Vec<f32>)>,
+}
+
+impl CaptureFilter {
+    pub(crate) fn new(
+        id: Ulid,
+        callback: mpsc::Sender<(Ulid, Vec<f32>)>,
+        channels: usize,
+        duration_ms: u32,
+        rate: u32,
+    ) -> Self {
+        let frames = ((rate as u64 * duration_ms as u64) / 1000) as usize;
+
+        Self {
+            armed: false,
+
+            channels,
+            frames,
+            buffer: Vec::with_capacity(frames * channels),
+
+            id,
+            callback,
+        }
+    }
+}
+
+impl FilterHandler for CaptureFilter {
+    fn get_properties(&self) -> Vec<FilterProperty> {
+        vec![self.get_property(PROP_ARMED)]
+    }
+
+    fn get_property(&self, id: u32) -> FilterProperty {
+        match id {
+            PROP_ARMED => FilterProperty {
+                id: PROP_ARMED,
+                name: "Armed".into(),
+                symbol: "armed".into(),
+                value: FilterValue::Bool(self.armed),
+
+                min: 0.0,
+                max: 1.0,
+
+                enum_def: None,
+            },
+            _ => panic!("Attempted to lookup non-existent property!"),
+        }
+    }
+
+    fn set_property(&mut self, id: u32, value: FilterValue) -> Result<String> {
+        match id {
+            PROP_ARMED => {
+                if let FilterValue::Bool(value) = value {
+                    self.armed = value;
+                    Ok("armed".into())
+                } else {
+                    bail!("Attempted to Arm Capture without Bool type");
+                }
+            }
+            _ => bail!("Attempted to set non-existent property!"),
+        }
+    }
+
+    fn process_samples(&mut self, inputs: Vec<&mut [f32]>, _outputs: Vec<&mut [f32]>) {
+        if !self.armed {
+            return;
+        }
+
+        let length = inputs.first().map(|i| i.len()).unwrap_or(0);
+        let captured = self.buffer.len() / self.channels;
+        let remaining = self.frames - captured;
+
+        for frame in 0..length.min(remaining) {
+            for channel in 0..self.channels {
+                let sample = inputs
+                    .get(channel)
+                    .and_then(|input| input.get(frame))
+                    .copied()
+                    .unwrap_or(0.0);
+                self.buffer.push(sample);
+            }
+        }
+
+        if self.buffer.len() / self.channels >= self.frames {
+            // Only capture once per arming
+            self.armed = false;
+            let buffer = std::mem::take(&mut self.buffer);
+            let _ = self.callback.try_send((self.id, buffer));
+        }
+    }
+}
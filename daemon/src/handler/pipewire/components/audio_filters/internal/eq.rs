@@ -0,0 +1,322 @@
+use anyhow::{Result, bail};
+use pipeweaver_pipewire::{FilterHandler, FilterProperty, FilterValue};
+use pipeweaver_profile::{EqBand, EqBandType, EqSettings};
+use std::f64::consts::PI;
+
+const PROP_EQ: u32 = 0;
+
+const EQ_CHANNELS: usize = 2;
+
+// Keeps bands clear of Nyquist, where the coefficients stop making sense
+const MAX_FREQUENCY_RATIO: f64 = 0.49;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Coefficients {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+pub struct EqFilter {
+    rate: u32,
+    settings: EqSettings,
+
+    // Only bands which actually change the signal, when this is empty we're a pass through
+    coefficients: Vec<Coefficients>,
+
+    // Per channel, per band, Transposed Direct Form II state
+    state: Vec<Vec<[f32; 2]>>,
+}
+
+impl EqFilter {
+    pub(crate) fn new(settings: EqSettings, rate: u32) -> Self {
+        let mut filter = Self {
+            rate,
+            settings: EqSettings::default(),
+            coefficients: vec![],
+
+            // Filters are always stereo, so the state can be sized now, rather than on the
+            // audio thread
+            state: vec![vec![]; EQ_CHANNELS],
+        };
+        filter.set_settings(settings);
+        filter
+    }
+
+    fn set_settings(&mut self, settings: EqSettings) {
+        let rate = self.rate as f64;
+        let coefficients: Vec<Coefficients> = Self::active_bands(&settings)
+            .map(|band| Self::calculate(band, rate))
+            .collect();
+
+        // Carrying state over to a different set of bands would just produce a click
+        if coefficients.len() != self.coefficients.len() {
+            for channel in self.state.iter_mut() {
+                *channel = vec![[0.0; 2]; coefficients.len()];
+            }
+        }
+
+        self.coefficients = coefficients;
+        self.settings = settings;
+    }
+
+    fn active_bands(settings: &EqSettings) -> impl Iterator<Item = &EqBand> {
+        settings
+            .bands
+            .iter()
+            .filter(|band| settings.enabled && !Self::is_flat(band))
+    }
+
+    fn is_flat(band: &EqBand) -> bool {
+        match band.band_type {
+            EqBandType::Peak | EqBandType::LowShelf | EqBandType::HighShelf => band.gain == 0.0,
+            EqBandType::HighPass | EqBandType::LowPass => false,
+        }
+    }
+
+    // From the RBJ Audio EQ Cookbook
+    fn calculate(band: &EqBand, rate: f64) -> Coefficients {
+        let frequency = (band.frequency as f64).min(rate * MAX_FREQUENCY_RATIO);
+        let a = 10.0_f64.powf(band.gain as f64 / 40.0);
+        let w0 = 2.0 * PI * frequency / rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * band.q as f64);
+
+        let (b0, b1, b2, a0, a1, a2) = match band.band_type {
+            EqBandType::Peak => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            EqBandType::LowShelf => {
+                let shelf = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) - (a - 1.0) * cos + shelf),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - shelf),
+                    (a + 1.0) + (a - 1.0) * cos + shelf,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - shelf,
+                )
+            }
+            EqBandType::HighShelf => {
+                let shelf = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) + (a - 1.0) * cos + shelf),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - shelf),
+                    (a + 1.0) - (a - 1.0) * cos + shelf,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - shelf,
+                )
+            }
+            EqBandType::HighPass => (
+                (1.0 + cos) / 2.0,
+                -(1.0 + cos),
+                (1.0 + cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+            EqBandType::LowPass => (
+                (1.0 - cos) / 2.0,
+                1.0 - cos,
+                (1.0 - cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+        };
+
+        Coefficients {
+            b0: (b0 / a0) as f32,
+            b1: (b1 / a0) as f32,
+            b2: (b2 / a0) as f32,
+            a1: (a1 / a0) as f32,
+            a2: (a2 / a0) as f32,
+        }
+    }
+
+    #[inline]
+    fn apply_bands(coefficients: &[Coefficients], state: &mut [[f32; 2]], output: &mut [f32]) {
+        for (c, s) in coefficients.iter().zip(state.iter_mut()) {
+            for sample in output.iter_mut() {
+                let input = *sample;
+                let out = c.b0 * input + s[0];
+                s[0] = c.b1 * input - c.a1 * out + s[1];
+                s[1] = c.b2 * input - c.a2 * out;
+                *sample = out;
+            }
+        }
+    }
+}
+
+impl FilterHandler for EqFilter {
+    fn get_properties(&self) -> Vec<FilterProperty> {
+        vec![self.get_property(PROP_EQ)]
+    }
+
+    fn get_property(&self, id: u32) -> FilterProperty {
+        match id {
+            PROP_EQ => FilterProperty {
+                id: PROP_EQ,
+                name: "EQ".into(),
+                symbol: "eq".into(),
+                value: FilterValue::String(
+                    serde_json::to_string(&self.settings).unwrap_or_default(),
+                ),
+
+                min: 0.0,
+                max: 0.0,
+
+                enum_def: None,
+            },
+            _ => panic!("Attempted to lookup non-existent property!"),
+        }
+    }
+
+    fn set_property(&mut self, id: u32, value: FilterValue) -> Result<String> {
+        match id {
+            PROP_EQ => {
+                if let FilterValue::String(value) = value {
+                    self.set_settings(serde_json::from_str(&value)?);
+                    Ok("eq".into())
+                } else {
+                    bail!("Attempted to Set EQ as non-string");
+                }
+            }
+            _ => bail!("Attempted to set non-existent property!"),
+        }
+    }
+
+    fn process_samples(&mut self, inputs: Vec<&mut [f32]>, mut outputs: Vec<&mut [f32]>) {
+        for (index, (input, output)) in inputs.iter().zip(outputs.iter_mut()).enumerate() {
+            if input.is_empty() || input.len() != output.len() {
+                continue;
+            }
+            output.copy_from_slice(input);
+            if let Some(state) = self.state.get_mut(index) {
+                Self::apply_bands(&self.coefficients, state, output);
+            }
+        }
+    }
+
+    fn set_rate(&mut self, rate: u32) {
+        if rate == self.rate {
+            return;
+        }
+        self.rate = rate;
+
+        // The bands haven't changed, so the coefficients can be replaced without allocating
+        let rate = rate as f64;
+        for (coefficients, band) in self
+            .coefficients
+            .iter_mut()
+            .zip(Self::active_bands(&self.settings))
+        {
+            *coefficients = Self::calculate(band, rate);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn band(band_type: EqBandType, frequency: f32, gain: f32) -> EqBand {
+        EqBand {
+            frequency,
+            gain,
+            q: 0.707,
+            band_type,
+        }
+    }
+
+    // The magnitude of the band's response at a frequency
+    fn response(c: &Coefficients, frequency: f64, rate: f64) -> f64 {
+        let w = 2.0 * PI * frequency / rate;
+        let (sin, cos) = w.sin_cos();
+        let (sin2, cos2) = (2.0 * w).sin_cos();
+        let (b0, b1, b2) = (c.b0 as f64, c.b1 as f64, c.b2 as f64);
+        let (a1, a2) = (c.a1 as f64, c.a2 as f64);
+
+        let num = (b0 + b1 * cos + b2 * cos2, -(b1 * sin + b2 * sin2));
+        let den = (1.0 + a1 * cos + a2 * cos2, -(a1 * sin + a2 * sin2));
+        (num.0.hypot(num.1)) / (den.0.hypot(den.1))
+    }
+
+    #[test]
+    fn only_zero_gain_shaping_bands_are_flat() {
+        assert!(EqFilter::is_flat(&band(EqBandType::Peak, 1000.0, 0.0)));
+        assert!(EqFilter::is_flat(&band(EqBandType::LowShelf, 100.0, 0.0)));
+        assert!(EqFilter::is_flat(&band(EqBandType::HighShelf, 8000.0, 0.0)));
+        assert!(!EqFilter::is_flat(&band(EqBandType::Peak, 1000.0, -3.0)));
+
+        // Pass filters ignore gain, so always shape the signal
+        assert!(!EqFilter::is_flat(&band(EqBandType::HighPass, 80.0, 0.0)));
+        assert!(!EqFilter::is_flat(&band(EqBandType::LowPass, 12000.0, 0.0)));
+    }
+
+    #[test]
+    fn peak_boosts_its_centre_frequency() {
+        let c = EqFilter::calculate(&band(EqBandType::Peak, 1000.0, 6.0), 48000.0);
+        let gain = 20.0 * response(&c, 1000.0, 48000.0).log10();
+        assert!((gain - 6.0).abs() < 0.01, "{}", gain);
+
+        // And leaves frequencies well away from it alone
+        assert!((response(&c, 20.0, 48000.0) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn pass_filters_keep_their_side() {
+        let rate = 48000.0;
+        let high = EqFilter::calculate(&band(EqBandType::HighPass, 100.0, 0.0), rate);
+        assert!(response(&high, 1.0, rate) < 0.01);
+        assert!((response(&high, 5000.0, rate) - 1.0).abs() < 0.01);
+
+        let low = EqFilter::calculate(&band(EqBandType::LowPass, 5000.0, 0.0), rate);
+        assert!((response(&low, 1.0, rate) - 1.0).abs() < 0.01);
+        assert!(response(&low, 23000.0, rate) < 0.01);
+    }
+
+    #[test]
+    fn frequencies_are_kept_below_nyquist() {
+        let rate = 48000.0;
+        let clamped = EqFilter::calculate(&band(EqBandType::Peak, 30000.0, 3.0), rate);
+        let limit = band(EqBandType::Peak, (rate * MAX_FREQUENCY_RATIO) as f32, 3.0);
+        assert_eq!(clamped, EqFilter::calculate(&limit, rate));
+    }
+
+    #[test]
+    fn coefficients_follow_the_rate() {
+        let settings = EqSettings {
+            enabled: true,
+            bands: vec![
+                band(EqBandType::Peak, 1000.0, 0.0),
+                band(EqBandType::LowShelf, 200.0, 4.0),
+            ],
+        };
+        let mut filter = EqFilter::new(settings.clone(), 48000);
+        assert_eq!(filter.coefficients.len(), 1);
+
+        filter.set_rate(96000);
+        let expected = EqFilter::calculate(&settings.bands[1], 96000.0);
+        assert_eq!(filter.coefficients, vec![expected]);
+    }
+
+    #[test]
+    fn state_is_allocated_up_front() {
+        let settings = EqSettings {
+            enabled: true,
+            bands: vec![band(EqBandType::Peak, 1000.0, 6.0)],
+        };
+        let filter = EqFilter::new(settings, 48000);
+        assert_eq!(filter.state.len(), EQ_CHANNELS);
+        assert!(filter.state.iter().all(|channel| channel.len() == 1));
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::{Result, bail};
+use pipeweaver_pipewire::{FilterHandler, FilterProperty, FilterValue};
+use tokio::sync::mpsc;
+use ulid::Ulid;
+
+// Long enough to stand out from whatever else is playing, short enough to be barely audible
+const PROBE_LENGTH: usize = 1024;
+const PROBE_LEVEL: f32 = 0.25;
+
+const PROP_ARMED: u32 = 0;
+
+/// The burst the probe plays, white noise from a fixed seed, so whoever is listening for it
+/// can build the same signal to correlate against.
+pub(crate) fn probe_signal() -> Vec<f32> {
+    let mut seed: u32 = 0x2545_f491;
+    (0..PROBE_LENGTH)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            ((seed as f32 / u32::MAX as f32) * 2.0 - 1.0) * PROBE_LEVEL
+        })
+        .collect()
+}
+
+/// Plays the probe signal on its outputs when armed, and records its inputs (mixed down to
+/// mono) for a window starting at the same moment. The recording is sent upstream tagged with
+/// the filter's id, where it can be searched for the probe. Both buffers are allocated up
+/// front, so nothing is allocated while running inside the audio thread.
+pub struct LatencyFilter {
+    armed: bool,
+
+    probe: Vec<f32>,
+    emitted: usize,
+
+    window: usize,
+    recording: Vec<f32>,
+
+    id: Ulid,
+    callback: mpsc::Sender<(Ulid, Vec<f32>)>,
+}
+
+impl LatencyFilter {
+    pub(crate) fn new(
+        id: Ulid,
+        callback: mpsc::Sender<(Ulid, Vec<f32>)>,
+        window_ms: u32,
+        rate: u32,
+    ) -> Self {
+        let window = ((rate as u64 * window_ms as u64) / 1000) as usize;
+
+        Self {
+            armed: false,
+
+            probe: probe_signal(),
+            emitted: 0,
+
+            window,
+            recording: Vec::with_capacity(window),
+
+            id,
+            callback,
+        }
+    }
+}
+
+impl FilterHandler for LatencyFilter {
+    fn get_properties(&self) -> Vec<FilterProperty> {
+        vec![self.get_property(PROP_ARMED)]
+    }
+
+    fn get_property(&self, id: u32) -> FilterProperty {
+        match id {
+            PROP_ARMED => FilterProperty {
+                id: PROP_ARMED,
+                name: "Armed".into(),
+                symbol: "armed".into(),
+                value: FilterValue::Bool(self.armed),
+
+                min: 0.0,
+                max: 1.0,
+
+                enum_def: None,
+            },
+            _ => panic!("Attempted to lookup non-existent property!"),
+        }
+    }
+
+    fn set_property(&mut self, id: u32, value: FilterValue) -> Result<String> {
+        match id {
+            PROP_ARMED => {
+                if let FilterValue::Bool(value) = value {
+                    self.armed = value;
+                    Ok("armed".into())
+                } else {
+                    bail!("Attempted to Arm Latency Probe without Bool type");
+                }
+            }
+            _ => bail!("Attempted to set non-existent property!"),
+        }
+    }
+
+    fn process_samples(&mut self, inputs: Vec<&mut [f32]>, mut outputs: Vec<&mut [f32]>) {
+        for output in outputs.iter_mut() {
+            output.fill(0.0);
+        }
+
+        if !self.armed {
+            return;
+        }
+
+        // Carry on playing the probe from wherever the last buffer left off
+        let length = inputs.first().map(|i| i.len()).unwrap_or(0);
+        let remaining = &self.probe[self.emitted..];
+        let count = remaining.len().min(length);
+        for output in outputs.iter_mut() {
+            let count = count.min(output.len());
+            output[..count].copy_from_slice(&remaining[..count]);
+        }
+        self.emitted += count;
+
+        let count = (self.window - self.recording.len()).min(length);
+        let scale = 1.0 / inputs.len().max(1) as f32;
+        for frame in 0..count {
+            let sample: f32 = inputs.iter().filter_map(|input| input.get(frame)).sum();
+            self.recording.push(sample * scale);
+        }
+
+        if self.recording.len() >= self.window {
+            // Only measure once per arming
+            self.armed = false;
+            self.emitted = 0;
+            let recording = std::mem::take(&mut self.recording);
+            let _ = self.callback.try_send((self.id, recording));
+        }
+    }
+}
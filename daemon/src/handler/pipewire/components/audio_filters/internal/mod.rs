@@ -1,3 +1,7 @@
+pub(crate) mod capture;
+pub(crate) mod eq;
+pub(crate) mod latency;
 pub(crate) mod meter;
 pub(crate) mod pass_through;
+pub(crate) mod tone;
 pub(crate) mod volume;
@@ -1,11 +1,18 @@
 use anyhow::{Result, bail};
 use pipeweaver_pipewire::{FilterHandler, FilterProperty, FilterValue};
 
-pub struct PassThroughFilter {}
+pub struct PassThroughFilter {
+    gain: f32,
+}
 
 impl PassThroughFilter {
     pub(crate) fn new() -> Self {
-        Self {}
+        Self { gain: 1.0 }
+    }
+
+    /// A pass-through which scales everything going through it by a fixed amount
+    pub(crate) fn with_gain(gain: f32) -> Self {
+        Self { gain }
     }
 }
 
@@ -23,11 +30,43 @@ impl FilterHandler for PassThroughFilter {
     }
 
     fn process_samples(&mut self, inputs: Vec<&mut [f32]>, mut outputs: Vec<&mut [f32]>) {
-        for (i, input) in inputs.iter().enumerate() {
-            if input.is_empty() || outputs[i].is_empty() {
+        for (input, output) in inputs.iter().zip(outputs.iter_mut()) {
+            if input.is_empty() || input.len() != output.len() {
                 continue;
             }
-            outputs[i].copy_from_slice(input);
+            output.copy_from_slice(input);
+            if self.gain != 1.0 {
+                output.iter_mut().for_each(|sample| *sample *= self.gain);
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_passed_through() {
+        let mut filter = PassThroughFilter::new();
+        let mut input = [0.25, -0.5, 1.0];
+        let mut output = [0.0; 3];
+        filter.process_samples(vec![&mut input], vec![&mut output]);
+        assert_eq!(output, [0.25, -0.5, 1.0]);
+    }
+
+    #[test]
+    fn gain_is_applied() {
+        let mut filter = PassThroughFilter::with_gain(0.5);
+        let mut left = [1.0, 0.5];
+        let mut right = [-1.0, 0.0];
+        let mut out_left = [0.0; 2];
+        let mut out_right = [0.0; 2];
+        filter.process_samples(
+            vec![&mut left, &mut right],
+            vec![&mut out_left, &mut out_right],
+        );
+        assert_eq!(out_left, [0.5, 0.25]);
+        assert_eq!(out_right, [-0.5, 0.0]);
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{Result, bail};
+use pipeweaver_ipc::commands::TestSignal;
+use pipeweaver_pipewire::{FilterHandler, FilterProperty, FilterValue};
+use std::f32::consts::TAU;
+
+// Pink noise comes out of the filter bank rather hot, this brings it back to roughly unity
+const PINK_GAIN: f32 = 0.11;
+
+/// Generates a continuous test signal on all of its outputs, used for calibrating speakers
+/// and headphones. Everything needed is held in the struct, so nothing allocates while running.
+pub struct ToneFilter {
+    signal: TestSignal,
+    level: f32,
+    rate: f32,
+
+    phase: f32,
+    seed: u32,
+    pink: [f32; 7],
+}
+
+impl ToneFilter {
+    pub(crate) fn new(signal: TestSignal, level: u8, rate: u32) -> Self {
+        Self {
+            signal,
+            level: level as f32 / 100.,
+            rate: rate as f32,
+
+            phase: 0.,
+            seed: 0x9E37_79B9,
+            pink: [0.; 7],
+        }
+    }
+
+    fn white(&mut self) -> f32 {
+        // xorshift32, plenty random enough for noise
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed as f32 / u32::MAX as f32) * 2. - 1.
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        match self.signal {
+            TestSignal::Sine(frequency) => {
+                let sample = (self.phase * TAU).sin();
+                self.phase = (self.phase + frequency as f32 / self.rate).fract();
+                sample
+            }
+            TestSignal::WhiteNoise => self.white(),
+            TestSignal::PinkNoise => {
+                // Paul Kellet's refined pink noise filter
+                let white = self.white();
+                let b = &mut self.pink;
+                b[0] = 0.99886 * b[0] + white * 0.0555179;
+                b[1] = 0.99332 * b[1] + white * 0.0750759;
+                b[2] = 0.96900 * b[2] + white * 0.1538520;
+                b[3] = 0.86650 * b[3] + white * 0.3104856;
+                b[4] = 0.55000 * b[4] + white * 0.5329522;
+                b[5] = -0.7616 * b[5] - white * 0.0168980;
+                let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+                b[6] = white * 0.115926;
+                pink * PINK_GAIN
+            }
+        }
+    }
+}
+
+impl FilterHandler for ToneFilter {
+    fn get_properties(&self) -> Vec<FilterProperty> {
+        vec![]
+    }
+
+    fn get_property(&self, _: u32) -> FilterProperty {
+        panic!("Attempted to get non-existent property");
+    }
+
+    fn set_property(&mut self, _: u32, _: FilterValue) -> Result<String> {
+        bail!("Attempted to set non-existent property");
+    }
+
+    fn process_samples(&mut self, _inputs: Vec<&mut [f32]>, mut outputs: Vec<&mut [f32]>) {
+        let length = outputs.first().map(|o| o.len()).unwrap_or(0);
+
+        for frame in 0..length {
+            // Every channel gets the same signal, so the calibration is centred
+            let sample = self.next_sample() * self.level;
+            for output in outputs.iter_mut() {
+                if let Some(value) = output.get_mut(frame) {
+                    *value = sample;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use crate::handler::pipewire::components::filters::FilterManagement;
+use crate::handler::pipewire::components::links::LinkManagement;
+use crate::handler::pipewire::components::node::NodeManagement;
+use crate::handler::pipewire::manager::PipewireManager;
+use anyhow::{Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use log::{debug, warn};
+use pipeweaver_ipc::commands::CapturedSample;
+use pipeweaver_shared::NodeType;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+use ulid::Ulid;
+
+// Long enough to hear what's going on, short enough to not flood the IPC
+const MAX_CAPTURE_MS: u32 = 10000;
+
+// How long to wait beyond the capture length before giving up
+const CAPTURE_GRACE_MS: u64 = 2000;
+
+const CAPTURE_CHANNELS: u16 = 2;
+
+/// A capture which is recording, waiting for the filter to send back its samples
+pub(crate) struct PendingCapture {
+    node: Ulid,
+    node_type: NodeType,
+    duration_ms: u32,
+    deadline: Instant,
+    callback: oneshot::Sender<Result<CapturedSample>>,
+}
+
+/// Records a short sample from a node, at the same point the meter listens, and returns it as
+/// a base64 encoded WAV file. The capture runs in the background, the callback is answered from
+/// the manager loop once the filter has filled its buffer, or the capture has timed out.
+pub(crate) trait CaptureManagement {
+    async fn capture_start(
+        &mut self,
+        node: Ulid,
+        duration_ms: u32,
+        callback: oneshot::Sender<Result<CapturedSample>>,
+    );
+    async fn capture_complete(&mut self, id: Ulid, samples: Vec<f32>);
+
+    fn capture_deadline(&self) -> Option<Instant>;
+    async fn capture_tick(&mut self);
+}
+
+impl CaptureManagement for PipewireManager {
+    async fn capture_start(
+        &mut self,
+        node: Ulid,
+        duration_ms: u32,
+        callback: oneshot::Sender<Result<CapturedSample>>,
+    ) {
+        match self.capture_begin(node, duration_ms).await {
+            Ok((id, node_type)) => {
+                let wait = Duration::from_millis(duration_ms as u64 + CAPTURE_GRACE_MS);
+                let capture = PendingCapture {
+                    node,
+                    node_type,
+                    duration_ms,
+                    deadline: Instant::now() + wait,
+                    callback,
+                };
+                self.captures.insert(id, capture);
+            }
+            Err(e) => {
+                let _ = callback.send(Err(e));
+            }
+        }
+    }
+
+    async fn capture_complete(&mut self, id: Ulid, samples: Vec<f32>) {
+        let Some(capture) = self.captures.remove(&id) else {
+            // Already timed out and cleaned up
+            return;
+        };
+        self.capture_remove(id, &capture).await;
+
+        let rate = self.clock_rate.unwrap_or(48000);
+        debug!(
+            "[Capture] Captured {} samples from {}",
+            samples.len(),
+            capture.node
+        );
+
+        let _ = capture.callback.send(Ok(CapturedSample {
+            rate,
+            channels: CAPTURE_CHANNELS,
+            milliseconds: capture.duration_ms,
+            wav: STANDARD.encode(encode_wav(&samples, rate)),
+        }));
+    }
+
+    fn capture_deadline(&self) -> Option<Instant> {
+        self.captures.values().map(|capture| capture.deadline).min()
+    }
+
+    async fn capture_tick(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<Ulid> = self
+            .captures
+            .iter()
+            .filter(|(_, capture)| capture.deadline <= now)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in expired {
+            if let Some(capture) = self.captures.remove(&id) {
+                self.capture_remove(id, &capture).await;
+                let _ = capture
+                    .callback
+                    .send(Err(anyhow!("Timed out waiting for Capture")));
+            }
+        }
+    }
+}
+
+trait CaptureManagementLocal {
+    async fn capture_begin(&mut self, node: Ulid, duration_ms: u32) -> Result<(Ulid, NodeType)>;
+    async fn capture_link(&mut self, capture: Ulid, node: Ulid, node_type: NodeType) -> Result<()>;
+    async fn capture_unlink(
+        &mut self,
+        capture: Ulid,
+        node: Ulid,
+        node_type: NodeType,
+    ) -> Result<()>;
+    async fn capture_remove(&mut self, id: Ulid, capture: &PendingCapture);
+}
+
+impl CaptureManagementLocal for PipewireManager {
+    async fn capture_begin(&mut self, node: Ulid, duration_ms: u32) -> Result<(Ulid, NodeType)> {
+        if duration_ms == 0 || duration_ms > MAX_CAPTURE_MS {
+            bail!("Capture length must be between 1 and {}ms", MAX_CAPTURE_MS);
+        }
+
+        let node_type = self
+            .get_node_type(node)
+            .ok_or(anyhow!("Unable to Locate Node"))?;
+
+        let capture = self.filter_capture_create(duration_ms).await?;
+        if let Err(e) = self.capture_link(capture, node, node_type).await {
+            // Don't leave a half linked filter behind
+            let _ = self.capture_unlink(capture, node, node_type).await;
+            if let Err(e) = self.filter_remove(capture).await {
+                warn!("[Capture] Unable to clean up Capture Filter: {}", e);
+            }
+            return Err(e);
+        }
+        Ok((capture, node_type))
+    }
+
+    async fn capture_link(&mut self, capture: Ulid, node: Ulid, node_type: NodeType) -> Result<()> {
+        // Tap the node the same way the meter does
+        if matches!(
+            node_type,
+            NodeType::PhysicalSource | NodeType::PhysicalTarget
+        ) {
+            self.link_create_filter_to_filter(node, capture).await?;
+        } else {
+            self.link_create_node_to_filter(node, capture).await?;
+        }
+
+        // Only start recording once linked, otherwise we'd capture silence
+        self.filter_arm(capture).await
+    }
+
+    async fn capture_unlink(
+        &mut self,
+        capture: Ulid,
+        node: Ulid,
+        node_type: NodeType,
+    ) -> Result<()> {
+        if matches!(
+            node_type,
+            NodeType::PhysicalSource | NodeType::PhysicalTarget
+        ) {
+            self.link_remove_filter_to_filter(node, capture).await
+        } else {
+            self.link_remove_node_to_filter(node, capture).await
+        }
+    }
+
+    async fn capture_remove(&mut self, id: Ulid, capture: &PendingCapture) {
+        if let Err(e) = self
+            .capture_unlink(id, capture.node, capture.node_type)
+            .await
+        {
+            warn!("[Capture] Unable to unlink Capture Filter: {}", e);
+        }
+        if let Err(e) = self.filter_remove(id).await {
+            warn!("[Capture] Unable to clean up Capture Filter: {}", e);
+        }
+    }
+}
+
+/// Builds a 16bit PCM WAV file from interleaved float samples
+fn encode_wav(samples: &[f32], rate: u32) -> Vec<u8> {
+    let bits: u16 = 16;
+    let block_align = CAPTURE_CHANNELS * (bits / 8);
+    let byte_rate = rate * block_align as u32;
+    let data_length = (samples.len() * 2) as u32;
+
+    let mut wav = Vec::with_capacity(44 + data_length as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_length).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&CAPTURE_CHANNELS.to_le_bytes());
+    wav.extend_from_slice(&rate.to_le_bytes());
+    wav.extend_from_slice(&byte_rate.to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&bits.to_le_bytes());
+
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_length.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        wav.extend_from_slice(&value.to_le_bytes());
+    }
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(wav: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([wav[offset], wav[offset + 1]])
+    }
+
+    fn u32_at(wav: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(wav[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn wav_header_describes_the_samples() {
+        let samples = [0.0; 8];
+        let wav = encode_wav(&samples, 48000);
+
+        assert_eq!(wav.len(), 44 + 16);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32_at(&wav, 4), 36 + 16);
+        assert_eq!(&wav[8..12], b"WAVE");
+
+        assert_eq!(&wav[12..16], b"fmt ");
+        assert_eq!(u32_at(&wav, 16), 16);
+        assert_eq!(u16_at(&wav, 20), 1);
+        assert_eq!(u16_at(&wav, 22), CAPTURE_CHANNELS);
+        assert_eq!(u32_at(&wav, 24), 48000);
+        assert_eq!(u32_at(&wav, 28), 48000 * 4);
+        assert_eq!(u16_at(&wav, 32), 4);
+        assert_eq!(u16_at(&wav, 34), 16);
+
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(u32_at(&wav, 40), 16);
+    }
+
+    #[test]
+    fn wav_samples_are_scaled_and_clamped() {
+        let samples = [0.0, 1.0, -1.0, 0.5, 2.0, -2.0];
+        let wav = encode_wav(&samples, 44100);
+
+        let values: Vec<i16> = wav[44..]
+            .chunks(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        assert_eq!(
+            values,
+            vec![0, i16::MAX, -i16::MAX, i16::MAX / 2, i16::MAX, -i16::MAX]
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use crate::handler::pipewire::components::node::NodeManagement;
+use crate::handler::pipewire::components::physical::PhysicalDevices;
 use crate::handler::pipewire::manager::PipewireManager;
 use anyhow::{Result, bail};
+use log::{debug, info};
 use pipeweaver_pipewire::PipewireMessage::SetDefaultDevice;
 use pipeweaver_pipewire::{MediaClass, NodeTarget};
 use pipeweaver_shared::{DeviceType, NodeType};
@@ -11,6 +13,8 @@ pub(crate) trait DefaultHandlers {
     async fn set_default_input(&self, id: Ulid) -> Result<()>;
     async fn set_default_output(&self, id: Ulid) -> Result<()>;
     fn find_ulid_for_pw_id(&self, id: u32) -> Option<Ulid>;
+
+    async fn defaults_attach_first_run(&mut self) -> Result<bool>;
 }
 
 impl DefaultHandlers for PipewireManager {
@@ -33,6 +37,41 @@ impl DefaultHandlers for PipewireManager {
 
         None
     }
+
+    async fn defaults_attach_first_run(&mut self) -> Result<bool> {
+        // This only runs for a brand new profile, but never replace something already attached
+        let devices = &self.profile.devices;
+        let sources = &devices.sources.physical_devices;
+        let targets = &devices.targets.physical_devices;
+        if sources.iter().any(|d| !d.attached_devices.is_empty())
+            || targets.iter().any(|d| !d.attached_devices.is_empty())
+        {
+            return Ok(false);
+        }
+
+        // Attach the system's current defaults to the first physical source and target
+        let source = sources.first().map(|d| d.description.id);
+        let target = targets.first().map(|d| d.description.id);
+
+        let mut attached = false;
+        let defaults = [(source, self.default_source), (target, self.default_target)];
+        for (node, default) in defaults {
+            if let Some(id) = node
+                && let Some(NodeTarget::UnmanagedNode(node_id)) = default
+            {
+                info!(
+                    "[Defaults] First Run, attaching Device {} to {}",
+                    node_id, id
+                );
+                match self.add_device_to_node(id, node_id).await {
+                    Ok(()) => attached = true,
+                    Err(e) => debug!("[Defaults] Unable to attach Device {}: {}", node_id, e),
+                }
+            }
+        }
+
+        Ok(attached)
+    }
 }
 
 trait DefaultHandlersInternal {
@@ -0,0 +1,103 @@
+use crate::handler::pipewire::components::filters::FilterManagement;
+use crate::handler::pipewire::components::profile::ProfileManagement;
+use crate::handler::pipewire::manager::PipewireManager;
+use anyhow::{Result, anyhow, bail};
+use pipeweaver_profile::{EqBand, EqSettings};
+use ulid::Ulid;
+
+const EQ_MAX_BANDS: usize = 10;
+const EQ_FREQUENCY_RANGE: (f32, f32) = (20., 20000.);
+const EQ_GAIN_RANGE: (f32, f32) = (-24., 24.);
+const EQ_Q_RANGE: (f32, f32) = (0.1, 10.);
+
+pub(crate) trait EqManagement {
+    async fn save_eq_preset(&mut self, name: String, id: Ulid) -> Result<()>;
+    async fn apply_eq_preset(&mut self, name: String, id: Ulid) -> Result<()>;
+    async fn set_eq_bands(&mut self, id: Ulid, bands: Vec<EqBand>) -> Result<()>;
+}
+
+impl EqManagement for PipewireManager {
+    async fn save_eq_preset(&mut self, name: String, id: Ulid) -> Result<()> {
+        if name.trim().is_empty() {
+            bail!("EQ Preset name cannot be empty");
+        }
+
+        let description = self.get_device_description(id)?;
+        let Some(eq) = description.eq.clone() else {
+            bail!("Node {} has no EQ configured", id);
+        };
+
+        self.profile.eq_presets.insert(name, eq);
+        Ok(())
+    }
+
+    async fn apply_eq_preset(&mut self, name: String, id: Ulid) -> Result<()> {
+        // Presets can come in from an imported profile, so don't trust them blindly
+        let preset = self
+            .profile
+            .eq_presets
+            .get(&name)
+            .cloned()
+            .ok_or(anyhow!("EQ Preset {} not Found", name))?;
+        Self::validate_eq(&preset)?;
+
+        let description = self.get_device_description(id)?;
+        description.eq = Some(preset.clone());
+        self.eq_update_filter(id, &preset).await
+    }
+
+    async fn set_eq_bands(&mut self, id: Ulid, bands: Vec<EqBand>) -> Result<()> {
+        if !self.eq_map.contains_key(&id) {
+            bail!("EQ is only available on Sources");
+        }
+
+        // Setting bands on a node without an EQ is taken as wanting it on
+        let description = self.get_device_description(id)?;
+        let enabled = description.eq.as_ref().is_none_or(|eq| eq.enabled);
+        let eq = EqSettings { enabled, bands };
+        Self::validate_eq(&eq)?;
+
+        self.eq_update_filter(id, &eq).await?;
+        self.get_device_description(id)?.eq = Some(eq);
+        Ok(())
+    }
+}
+
+trait EqManagementLocal {
+    fn validate_eq(eq: &EqSettings) -> Result<()>;
+    async fn eq_update_filter(&self, id: Ulid, eq: &EqSettings) -> Result<()>;
+}
+
+impl EqManagementLocal for PipewireManager {
+    fn validate_eq(eq: &EqSettings) -> Result<()> {
+        if eq.bands.len() > EQ_MAX_BANDS {
+            bail!(
+                "EQ has too many bands ({}, max {})",
+                eq.bands.len(),
+                EQ_MAX_BANDS
+            );
+        }
+
+        let in_range = |value: f32, (min, max): (f32, f32)| value >= min && value <= max;
+        for (index, band) in eq.bands.iter().enumerate() {
+            if !in_range(band.frequency, EQ_FREQUENCY_RANGE) {
+                bail!("EQ Band {} has invalid frequency {}", index, band.frequency);
+            }
+            if !in_range(band.gain, EQ_GAIN_RANGE) {
+                bail!("EQ Band {} has invalid gain {}", index, band.gain);
+            }
+            if !in_range(band.q, EQ_Q_RANGE) {
+                bail!("EQ Band {} has invalid Q {}", index, band.q);
+            }
+        }
+        Ok(())
+    }
+
+    async fn eq_update_filter(&self, id: Ulid, eq: &EqSettings) -> Result<()> {
+        // Only Sources carry an EQ filter, anything else just keeps the settings
+        if let Some(&filter) = self.eq_map.get(&id) {
+            self.filter_eq_set(filter, eq).await?;
+        }
+        Ok(())
+    }
+}
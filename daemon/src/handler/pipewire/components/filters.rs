@@ -1,17 +1,23 @@
+use crate::handler::pipewire::components::audio_filters::internal::capture::CaptureFilter;
+use crate::handler::pipewire::components::audio_filters::internal::eq::EqFilter;
+use crate::handler::pipewire::components::audio_filters::internal::latency::LatencyFilter;
 use crate::handler::pipewire::components::audio_filters::internal::meter::MeterFilter;
 use crate::handler::pipewire::components::audio_filters::internal::pass_through::PassThroughFilter;
+use crate::handler::pipewire::components::audio_filters::internal::tone::ToneFilter;
 use crate::handler::pipewire::components::audio_filters::internal::volume::VolumeFilter;
 use crate::handler::pipewire::manager::PipewireManager;
-use crate::{APP_ID, APP_NAME, APP_NAME_ID};
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
+use pipeweaver_ipc::commands::TestSignal;
 use pipeweaver_pipewire::oneshot;
 use pipeweaver_pipewire::{FilterProperties, FilterValue, MediaClass, PipewireMessage};
+use pipeweaver_profile::EqSettings;
 use ulid::Ulid;
 
 #[allow(unused)]
 pub(crate) trait FilterManagement {
     async fn filter_pass_create(&mut self, name: String) -> Result<Ulid>;
     async fn filter_pass_create_id(&mut self, name: String, id: Ulid) -> Result<()>;
+    async fn filter_mono_sum_create(&mut self, name: String) -> Result<Ulid>;
 
     async fn filter_volume_create(&mut self, name: String) -> Result<Ulid>;
     async fn filter_volume_create_id(&mut self, name: String, id: Ulid) -> Result<()>;
@@ -19,7 +25,19 @@ pub(crate) trait FilterManagement {
     async fn filter_meter_create(&mut self, node: Ulid, name: String) -> Result<Ulid>;
     async fn filter_meter_create_id(&mut self, node: Ulid, name: String, id: Ulid) -> Result<()>;
 
+    async fn filter_latency_create(&mut self, window_ms: u32) -> Result<Ulid>;
+
+    async fn filter_capture_create(&mut self, duration_ms: u32) -> Result<Ulid>;
+
+    async fn filter_tone_create(&mut self, signal: TestSignal, level: u8) -> Result<Ulid>;
+    async fn filter_eq_create(&mut self, name: String, eq: EqSettings) -> Result<Ulid>;
+
     async fn filter_volume_set(&self, id: Ulid, volume: u8) -> Result<()>;
+    async fn filter_eq_set(&self, id: Ulid, eq: &EqSettings) -> Result<()>;
+
+    /// Starts a one shot filter (a latency probe or a capture) once it's been linked up, both
+    /// take their 'armed' flag as property 0
+    async fn filter_arm(&self, id: Ulid) -> Result<()>;
 
     async fn filter_remove(&mut self, id: Ulid) -> Result<()>;
     async fn filter_debug_create(&mut self, props: FilterProperties) -> Result<()>;
@@ -37,6 +55,14 @@ impl FilterManagement for PipewireManager {
         self.filter_pw_create(props).await
     }
 
+    async fn filter_mono_sum_create(&mut self, name: String) -> Result<Ulid> {
+        let id = Ulid::new();
+        let props = self.filter_mono_sum_get_props(name, id);
+        self.filter_pw_create(props).await?;
+
+        Ok(id)
+    }
+
     async fn filter_volume_create(&mut self, name: String) -> Result<Ulid> {
         let id = Ulid::new();
         self.filter_volume_create_id(name, id).await?;
@@ -60,6 +86,38 @@ impl FilterManagement for PipewireManager {
         self.filter_pw_create(props).await
     }
 
+    async fn filter_latency_create(&mut self, window_ms: u32) -> Result<Ulid> {
+        let id = Ulid::new();
+        let props = self.filter_latency_get_props(id, window_ms);
+        self.filter_pw_create(props).await?;
+
+        Ok(id)
+    }
+
+    async fn filter_capture_create(&mut self, duration_ms: u32) -> Result<Ulid> {
+        let id = Ulid::new();
+        let props = self.filter_capture_get_props(id, duration_ms);
+        self.filter_pw_create(props).await?;
+
+        Ok(id)
+    }
+
+    async fn filter_tone_create(&mut self, signal: TestSignal, level: u8) -> Result<Ulid> {
+        let id = Ulid::new();
+        let props = self.filter_tone_get_props(id, signal, level);
+        self.filter_pw_create(props).await?;
+
+        Ok(id)
+    }
+
+    async fn filter_eq_create(&mut self, name: String, eq: EqSettings) -> Result<Ulid> {
+        let id = Ulid::new();
+        let props = self.filter_eq_get_props(name, id, eq);
+        self.filter_pw_create(props).await?;
+
+        Ok(id)
+    }
+
     async fn filter_volume_set(&self, id: Ulid, volume: u8) -> Result<()> {
         if !(0..=100).contains(&volume) {
             bail!("Volume must be between 0 and 100");
@@ -81,7 +139,27 @@ impl FilterManagement for PipewireManager {
         Ok(())
     }
 
+    async fn filter_eq_set(&self, id: Ulid, eq: &EqSettings) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        let value = FilterValue::String(serde_json::to_string(eq)?);
+        let message = PipewireMessage::SetFilterValue(id, 0, value, tx);
+        self.pipewire().send_message(message)?;
+        rx.recv()??;
+
+        Ok(())
+    }
+
+    async fn filter_arm(&self, id: Ulid) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        let message = PipewireMessage::SetFilterValue(id, 0, FilterValue::Bool(true), tx);
+        self.pipewire().send_message(message)?;
+        rx.recv()??;
+
+        Ok(())
+    }
+
     async fn filter_remove(&mut self, id: Ulid) -> Result<()> {
+        self.bypassed_filters.remove(&id);
         self.filter_pw_remove(id).await
     }
 
@@ -95,8 +173,13 @@ trait FilterManagementLocal {
     async fn filter_pw_remove(&self, id: Ulid) -> Result<()>;
 
     fn filter_pass_get_props(&self, name: String, id: Ulid) -> FilterProperties;
+    fn filter_mono_sum_get_props(&self, name: String, id: Ulid) -> FilterProperties;
     fn filter_volume_get_props(&self, name: String, id: Ulid) -> FilterProperties;
     fn filter_meter_get_props(&self, node: Ulid, name: String, id: Ulid) -> FilterProperties;
+    fn filter_latency_get_props(&self, id: Ulid, window_ms: u32) -> FilterProperties;
+    fn filter_capture_get_props(&self, id: Ulid, duration_ms: u32) -> FilterProperties;
+    fn filter_tone_get_props(&self, id: Ulid, signal: TestSignal, level: u8) -> FilterProperties;
+    fn filter_eq_get_props(&self, name: String, id: Ulid, eq: EqSettings) -> FilterProperties;
 }
 
 impl FilterManagementLocal for PipewireManager {
@@ -104,9 +187,11 @@ impl FilterManagementLocal for PipewireManager {
         let (send, recv) = oneshot::channel();
 
         props.ready_sender = Some(send);
+        let id = props.filter_id;
         self.pipewire()
             .send_message(PipewireMessage::CreateFilterNode(props))?;
-        recv.await?;
+        recv.await
+            .map_err(|_| anyhow!("Filter {} creation aborted before it became ready", id))?;
 
         Ok(())
     }
@@ -123,11 +208,10 @@ impl FilterManagementLocal for PipewireManager {
             filter_id: id,
             filter_name: "Pass-Through".into(),
             filter_nick: name.to_string(),
-            filter_description: format!("{}/{}", APP_NAME_ID, description),
+            filter_description: format!("{}/{}", self.naming.group_prefix, description),
 
             class: MediaClass::Duplex,
-            app_id: APP_ID.to_string(),
-            app_name: APP_NAME.to_string(),
+            naming: self.naming.clone(),
             linger: false,
             callback: Box::new(PassThroughFilter::new()),
 
@@ -135,6 +219,25 @@ impl FilterManagementLocal for PipewireManager {
         }
     }
 
+    fn filter_mono_sum_get_props(&self, name: String, id: Ulid) -> FilterProperties {
+        let description = name.to_lowercase().replace(" ", "-");
+
+        // Both channels are linked into each side, halve them so the sum doesn't clip
+        FilterProperties {
+            filter_id: id,
+            filter_name: "Mono Sum".into(),
+            filter_nick: name.to_string(),
+            filter_description: format!("{}/{}", self.naming.group_prefix, description),
+
+            class: MediaClass::Duplex,
+            naming: self.naming.clone(),
+            linger: false,
+            callback: Box::new(PassThroughFilter::with_gain(0.5)),
+
+            ready_sender: None,
+        }
+    }
+
     fn filter_volume_get_props(&self, name: String, id: Ulid) -> FilterProperties {
         let description = name.to_lowercase().replace(" ", "-");
 
@@ -142,11 +245,10 @@ impl FilterManagementLocal for PipewireManager {
             filter_id: id,
             filter_name: "Volume".into(),
             filter_nick: name.to_string(),
-            filter_description: format!("{}/{}", APP_NAME_ID, description),
+            filter_description: format!("{}/{}", self.naming.group_prefix, description),
 
             class: MediaClass::Duplex,
-            app_id: APP_ID.to_string(),
-            app_name: APP_NAME.to_string(),
+            naming: self.naming.clone(),
             linger: false,
             callback: Box::new(VolumeFilter::new(0)),
 
@@ -162,11 +264,10 @@ impl FilterManagementLocal for PipewireManager {
             filter_id: id,
             filter_name: "Meter".into(),
             filter_nick: name.to_string(),
-            filter_description: format!("{}/{}", APP_NAME_ID, description),
+            filter_description: format!("{}/{}", self.naming.group_prefix, description),
 
             class: MediaClass::Source,
-            app_id: APP_ID.to_string(),
-            app_name: APP_NAME.to_string(),
+            naming: self.naming.clone(),
             linger: false,
             callback: Box::new(MeterFilter::new(
                 node,
@@ -178,4 +279,79 @@ impl FilterManagementLocal for PipewireManager {
             ready_sender: None,
         }
     }
+
+    fn filter_latency_get_props(&self, id: Ulid, window_ms: u32) -> FilterProperties {
+        let rate = self.clock_rate.unwrap_or(48000);
+        let callback = self.latency_callback.clone();
+
+        FilterProperties {
+            filter_id: id,
+            filter_name: "Latency Probe".into(),
+            filter_nick: "latency-probe".into(),
+            filter_description: format!("{}/latency-probe", self.naming.group_prefix),
+
+            class: MediaClass::Duplex,
+            naming: self.naming.clone(),
+            linger: false,
+            callback: Box::new(LatencyFilter::new(id, callback, window_ms, rate)),
+
+            ready_sender: None,
+        }
+    }
+
+    fn filter_capture_get_props(&self, id: Ulid, duration_ms: u32) -> FilterProperties {
+        let rate = self.clock_rate.unwrap_or(48000);
+        let callback = self.capture_callback.clone();
+
+        FilterProperties {
+            filter_id: id,
+            filter_name: "Capture".into(),
+            filter_nick: "capture".into(),
+            filter_description: format!("{}/capture", self.naming.group_prefix),
+
+            class: MediaClass::Source,
+            naming: self.naming.clone(),
+            linger: false,
+            callback: Box::new(CaptureFilter::new(id, callback, 2, duration_ms, rate)),
+
+            ready_sender: None,
+        }
+    }
+
+    fn filter_tone_get_props(&self, id: Ulid, signal: TestSignal, level: u8) -> FilterProperties {
+        let rate = self.clock_rate.unwrap_or(48000);
+
+        FilterProperties {
+            filter_id: id,
+            filter_name: "Test Tone".into(),
+            filter_nick: "test-tone".into(),
+            filter_description: format!("{}/test-tone", self.naming.group_prefix),
+
+            class: MediaClass::Sink,
+            naming: self.naming.clone(),
+            linger: false,
+            callback: Box::new(ToneFilter::new(signal, level, rate)),
+
+            ready_sender: None,
+        }
+    }
+
+    fn filter_eq_get_props(&self, name: String, id: Ulid, eq: EqSettings) -> FilterProperties {
+        let description = name.to_lowercase().replace(" ", "-");
+        let rate = self.clock_rate.unwrap_or(48000);
+
+        FilterProperties {
+            filter_id: id,
+            filter_name: "EQ".into(),
+            filter_nick: name.to_string(),
+            filter_description: format!("{}/{}", self.naming.group_prefix, description),
+
+            class: MediaClass::Duplex,
+            naming: self.naming.clone(),
+            linger: false,
+            callback: Box::new(EqFilter::new(eq, rate)),
+
+            ready_sender: None,
+        }
+    }
 }
@@ -0,0 +1,309 @@
+use crate::handler::pipewire::components::audio_filters::internal::latency::probe_signal;
+use crate::handler::pipewire::components::filters::FilterManagement;
+use crate::handler::pipewire::components::links::LinkManagement;
+use crate::handler::pipewire::components::node::NodeManagement;
+use crate::handler::pipewire::components::routing::RoutingManagement;
+use crate::handler::pipewire::manager::PipewireManager;
+use anyhow::{Result, anyhow, bail};
+use log::{debug, warn};
+use pipeweaver_ipc::commands::LatencyMeasurement;
+use pipeweaver_shared::NodeType;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::task;
+use tokio::time::Instant;
+use ulid::Ulid;
+
+// How long after the probe starts we listen for it to come back
+const LATENCY_WINDOW_MS: u32 = 500;
+
+// How long to wait beyond the window before giving up
+const LATENCY_GRACE_MS: u64 = 1500;
+
+// How closely the recording has to match the probe for it to count as having arrived
+const MINIMUM_CORRELATION: f32 = 0.3;
+
+/// The Source a probe is played into, and the Target it's listened for at
+#[derive(Debug, Copy, Clone)]
+struct LatencyPath {
+    source: Ulid,
+    source_type: NodeType,
+    target: Ulid,
+    target_type: NodeType,
+}
+
+/// A probe which is running, waiting for the filter to send back its recording
+pub(crate) struct PendingLatency {
+    path: LatencyPath,
+    deadline: Instant,
+    callback: oneshot::Sender<Result<LatencyMeasurement>>,
+}
+
+/// Measures how long audio takes to get from a source to a target. A probe plays a known noise
+/// burst into the Source's input, alongside whatever the Source is already receiving, so it
+/// passes through the Source's filters, mix and route, and listens for it at the Target. The
+/// measurement runs in the background, the callback is answered once the recording has been
+/// correlated against the burst.
+pub(crate) trait LatencyManagement {
+    async fn latency_start(
+        &mut self,
+        source: Ulid,
+        target: Ulid,
+        callback: oneshot::Sender<Result<LatencyMeasurement>>,
+    );
+    async fn latency_complete(&mut self, id: Ulid, recording: Vec<f32>);
+
+    fn latency_deadline(&self) -> Option<Instant>;
+    async fn latency_tick(&mut self);
+}
+
+impl LatencyManagement for PipewireManager {
+    async fn latency_start(
+        &mut self,
+        source: Ulid,
+        target: Ulid,
+        callback: oneshot::Sender<Result<LatencyMeasurement>>,
+    ) {
+        match self.latency_begin(source, target).await {
+            Ok((id, path)) => {
+                let wait = Duration::from_millis(LATENCY_WINDOW_MS as u64 + LATENCY_GRACE_MS);
+                let probe = PendingLatency {
+                    path,
+                    deadline: Instant::now() + wait,
+                    callback,
+                };
+                self.latency_probes.insert(id, probe);
+            }
+            Err(e) => {
+                let _ = callback.send(Err(e));
+            }
+        }
+    }
+
+    async fn latency_complete(&mut self, id: Ulid, recording: Vec<f32>) {
+        let Some(probe) = self.latency_probes.remove(&id) else {
+            // Already timed out and cleaned up
+            return;
+        };
+        self.latency_remove_probe(id, probe.path).await;
+
+        // Searching the recording is too slow to do on the manager loop
+        let rate = self.clock_rate.unwrap_or(48000);
+        task::spawn_blocking(move || {
+            let Some(samples) = probe_offset(&recording, &probe_signal()) else {
+                let error = anyhow!("Probe not detected at Target, check the route isn't muted");
+                let _ = probe.callback.send(Err(error));
+                return;
+            };
+            let milliseconds = (samples as f32 * 1000.) / rate as f32;
+
+            debug!(
+                "[Latency] {} -> {}: {} samples ({:.2}ms)",
+                probe.path.source, probe.path.target, samples, milliseconds
+            );
+            let _ = probe.callback.send(Ok(LatencyMeasurement {
+                samples,
+                milliseconds,
+            }));
+        });
+    }
+
+    fn latency_deadline(&self) -> Option<Instant> {
+        self.latency_probes
+            .values()
+            .map(|probe| probe.deadline)
+            .min()
+    }
+
+    async fn latency_tick(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<Ulid> = self
+            .latency_probes
+            .iter()
+            .filter(|(_, probe)| probe.deadline <= now)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in expired {
+            if let Some(probe) = self.latency_probes.remove(&id) {
+                self.latency_remove_probe(id, probe.path).await;
+                let error = anyhow!("Timed out waiting for Latency Probe");
+                let _ = probe.callback.send(Err(error));
+            }
+        }
+    }
+}
+
+trait LatencyManagementLocal {
+    async fn latency_begin(&mut self, source: Ulid, target: Ulid) -> Result<(Ulid, LatencyPath)>;
+    async fn latency_link_probe(&mut self, probe: Ulid, path: LatencyPath) -> Result<()>;
+    async fn latency_unlink_probe(&mut self, probe: Ulid, path: LatencyPath) -> Result<()>;
+    async fn latency_remove_probe(&mut self, probe: Ulid, path: LatencyPath);
+}
+
+impl LatencyManagementLocal for PipewireManager {
+    async fn latency_begin(&mut self, source: Ulid, target: Ulid) -> Result<(Ulid, LatencyPath)> {
+        let err = anyhow!("Unable to Locate Source");
+        let source_type = self.get_node_type(source).ok_or(err)?;
+        if !matches!(
+            source_type,
+            NodeType::PhysicalSource | NodeType::VirtualSource
+        ) {
+            bail!("Provided Source is a Target Node");
+        }
+
+        let err = anyhow!("Unable to Locate Target");
+        let target_type = self.get_node_type(target).ok_or(err)?;
+        if !matches!(
+            target_type,
+            NodeType::PhysicalTarget | NodeType::VirtualTarget
+        ) {
+            bail!("Provided Target is a Source Node");
+        }
+
+        if !self.routing_route_exists(source, target).await? {
+            bail!("Source is not Routed to the Target");
+        }
+
+        let path = LatencyPath {
+            source,
+            source_type,
+            target,
+            target_type,
+        };
+
+        let probe = self.filter_latency_create(LATENCY_WINDOW_MS).await?;
+        if let Err(e) = self.latency_link_probe(probe, path).await {
+            // Don't leave a half linked probe behind
+            self.latency_remove_probe(probe, path).await;
+            return Err(e);
+        }
+        Ok((probe, path))
+    }
+
+    async fn latency_link_probe(&mut self, probe: Ulid, path: LatencyPath) -> Result<()> {
+        // Feed the probe into the source, the same way its devices or applications are
+        if path.source_type == NodeType::VirtualSource {
+            self.link_create_filter_to_node(probe, path.source).await?;
+        } else {
+            self.link_create_filter_to_filter(probe, path.source)
+                .await?;
+        }
+
+        // And listen to the target, the same way the meter does
+        if path.target_type == NodeType::PhysicalTarget {
+            self.link_create_filter_to_filter(path.target, probe)
+                .await?;
+        } else {
+            self.link_create_node_to_filter(path.target, probe).await?;
+        }
+
+        // Only fire once everything's linked up, otherwise the probe could go nowhere
+        self.filter_arm(probe).await
+    }
+
+    async fn latency_unlink_probe(&mut self, probe: Ulid, path: LatencyPath) -> Result<()> {
+        if path.source_type == NodeType::VirtualSource {
+            self.link_remove_filter_to_node(probe, path.source).await?;
+        } else {
+            self.link_remove_filter_to_filter(probe, path.source)
+                .await?;
+        }
+
+        if path.target_type == NodeType::PhysicalTarget {
+            self.link_remove_filter_to_filter(path.target, probe).await
+        } else {
+            self.link_remove_node_to_filter(path.target, probe).await
+        }
+    }
+
+    async fn latency_remove_probe(&mut self, probe: Ulid, path: LatencyPath) {
+        if let Err(e) = self.latency_unlink_probe(probe, path).await {
+            warn!("[Latency] Unable to unlink Latency Probe: {}", e);
+        }
+        if let Err(e) = self.filter_remove(probe).await {
+            warn!("[Latency] Unable to clean up Latency Probe: {}", e);
+        }
+    }
+}
+
+/// Finds where the probe starts in the recording by cross-correlating the two, returning the
+/// offset in samples, or None if nothing in the recording resembles the probe closely enough.
+fn probe_offset(recording: &[f32], probe: &[f32]) -> Option<u64> {
+    if probe.is_empty() || recording.len() < probe.len() {
+        return None;
+    }
+
+    let mut best_offset = 0;
+    let mut best_correlation = 0.0;
+    for offset in 0..=recording.len() - probe.len() {
+        let window = &recording[offset..offset + probe.len()];
+        let correlation: f32 = window.iter().zip(probe).map(|(a, b)| a * b).sum();
+        if correlation > best_correlation {
+            best_offset = offset;
+            best_correlation = correlation;
+        }
+    }
+
+    // Normalise against the energy of both, so the match doesn't depend on the level
+    let window = &recording[best_offset..best_offset + probe.len()];
+    let window_energy: f32 = window.iter().map(|s| s * s).sum();
+    let probe_energy: f32 = probe.iter().map(|s| s * s).sum();
+    let energy = (window_energy * probe_energy).sqrt();
+    if energy == 0.0 || best_correlation / energy < MINIMUM_CORRELATION {
+        return None;
+    }
+    Some(best_offset as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording_with_probe(offset: usize, gain: f32) -> Vec<f32> {
+        let mut recording = vec![0.0; 4096];
+        for (index, sample) in probe_signal().iter().enumerate() {
+            recording[offset + index] = sample * gain;
+        }
+        recording
+    }
+
+    #[test]
+    fn finds_the_probe_offset() {
+        let recording = recording_with_probe(1234, 1.0);
+        assert_eq!(probe_offset(&recording, &probe_signal()), Some(1234));
+    }
+
+    #[test]
+    fn finds_a_quiet_probe_under_other_audio() {
+        let mut recording = recording_with_probe(300, 0.5);
+
+        // A tone playing over the top shouldn't move the result
+        for (index, sample) in recording.iter_mut().enumerate() {
+            *sample += (index as f32 * 0.05).sin() * 0.1;
+        }
+        assert_eq!(probe_offset(&recording, &probe_signal()), Some(300));
+    }
+
+    #[test]
+    fn ignores_a_single_loud_sample() {
+        let mut recording = recording_with_probe(2000, 0.5);
+        recording[100] = 1.0;
+        assert_eq!(probe_offset(&recording, &probe_signal()), Some(2000));
+    }
+
+    #[test]
+    fn reports_nothing_without_the_probe() {
+        let silence = vec![0.0; 4096];
+        assert_eq!(probe_offset(&silence, &probe_signal()), None);
+
+        let tone: Vec<f32> = (0..4096).map(|i| (i as f32 * 0.05).sin()).collect();
+        assert_eq!(probe_offset(&tone, &probe_signal()), None);
+    }
+
+    #[test]
+    fn reports_nothing_for_a_short_recording() {
+        let recording = vec![0.0; 16];
+        assert_eq!(probe_offset(&recording, &probe_signal()), None);
+    }
+}
@@ -1,5 +1,6 @@
+use crate::handler::pipewire::components::profile::ProfileManagement;
 use crate::handler::pipewire::manager::PipewireManager;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use pipeweaver_pipewire::{LinkPorts, oneshot};
 use pipeweaver_pipewire::{LinkType, PipewireMessage};
 use ulid::Ulid;
@@ -85,8 +86,8 @@ impl LinkManagement for PipewireManager {
     }
 
     async fn link_create_filter_to_node(&self, source: Ulid, target: Ulid) -> Result<()> {
-        self.create_link(LinkType::Filter(source), LinkType::Node(target))
-            .await
+        let target = self.node_link_type(source, target);
+        self.create_link(LinkType::Filter(source), target).await
     }
     async fn link_create_filter_to_filter(&self, source: Ulid, target: Ulid) -> Result<()> {
         self.create_link(LinkType::Filter(source), LinkType::Filter(target))
@@ -223,8 +224,8 @@ impl LinkManagement for PipewireManager {
     }
 
     async fn link_remove_filter_to_node(&self, source: Ulid, target: Ulid) -> Result<()> {
-        self.remove_link(LinkType::Filter(source), LinkType::Node(target))
-            .await
+        let target = self.node_link_type(source, target);
+        self.remove_link(LinkType::Filter(source), target).await
     }
     async fn link_remove_filter_to_filter(&self, source: Ulid, target: Ulid) -> Result<()> {
         self.remove_link(LinkType::Filter(source), LinkType::Filter(target))
@@ -332,14 +333,23 @@ impl LinkManagement for PipewireManager {
 trait LinkManagementLocal {
     async fn create_link(&self, source: LinkType, target: LinkType) -> Result<()>;
     async fn remove_link(&self, source: LinkType, target: LinkType) -> Result<()>;
+    fn node_link_type(&self, filter: Ulid, target: Ulid) -> LinkType;
 }
 
 impl LinkManagementLocal for PipewireManager {
     async fn create_link(&self, source: LinkType, target: LinkType) -> Result<()> {
+        // Links into a physical device can be passive, so the hardware is free to suspend when
+        // it's idle, anything between our own nodes and filters needs to stay active.
+        let passive = match &target {
+            LinkType::UnmanagedNode(id, _) => self.device_nodes.contains_key(id),
+            LinkType::Node(_) | LinkType::NodeChannelPair(_, _) | LinkType::Filter(_) => false,
+        };
+
         let (send, recv) = oneshot::channel();
-        let message = PipewireMessage::CreateDeviceLink(source, target, send);
+        let message = PipewireMessage::CreateDeviceLink(source, target, passive, send);
         self.pipewire().send_message(message)?;
-        recv.await?;
+        recv.await
+            .map_err(|_| anyhow!("Link creation aborted before it became ready"))?;
 
         Ok(())
     }
@@ -348,4 +358,24 @@ impl LinkManagementLocal for PipewireManager {
         let message = PipewireMessage::RemoveDeviceLink(source, target);
         self.pipewire().send_message(message)
     }
+
+    fn node_link_type(&self, filter: Ulid, target: Ulid) -> LinkType {
+        // A source may be routed to a specific channel pair on a multichannel target, we only
+        // get the source's mix filter here, so work back to the source that owns it.
+        let source = self
+            .source_map
+            .iter()
+            .find(|(_, map)| map.values().any(|id| *id == filter))
+            .map(|(id, _)| *id);
+
+        let pair = source.and_then(|source| {
+            let target = self.get_virtual_target(target)?;
+            target.channel_routes.get(&source).copied()
+        });
+
+        match pair {
+            Some(pair) if pair > 0 => LinkType::NodeChannelPair(target, pair),
+            _ => LinkType::Node(target),
+        }
+    }
 }
@@ -1,9 +1,12 @@
+use crate::handler::pipewire::components::aggregate::AggregateSinkManagement;
+use crate::handler::pipewire::components::mute::MuteManager;
 use crate::handler::pipewire::components::node::NodeManagement;
+use crate::handler::pipewire::components::physical::PhysicalDevices;
 use crate::handler::pipewire::components::routing::RoutingManagement;
 use crate::handler::pipewire::components::volume::VolumeManager;
 use crate::handler::pipewire::manager::PipewireManager;
 use anyhow::Result;
-use log::debug;
+use log::{debug, warn};
 use pipeweaver_profile::DeviceDescription;
 use pipeweaver_shared::{NodeType, OrderGroup};
 use ulid::Ulid;
@@ -21,6 +24,9 @@ impl LoadProfile for PipewireManager {
         self.profile_create_nodes().await?;
         self.profile_load_volumes().await?;
         self.profile_apply_routing().await?;
+        self.apply_auto_mute_rules().await?;
+        self.aggregate_load().await?;
+        self.profile_connect_physical().await;
 
         Ok(())
     }
@@ -67,6 +73,7 @@ trait LoadProfileLocal {
     async fn profile_create_nodes(&mut self) -> Result<()>;
     async fn profile_load_volumes(&mut self) -> Result<()>;
     async fn profile_apply_routing(&mut self) -> Result<()>;
+    async fn profile_connect_physical(&mut self);
     fn check_device_order_present(&mut self, dev: &DeviceDescription, source: bool) -> Result<()>;
     fn validate_name(description: &mut DeviceDescription, all_devices: &mut Vec<(Ulid, String)>);
     fn validate_device_order(&mut self, source: bool) -> Result<()>;
@@ -153,6 +160,25 @@ impl LoadProfileLocal for PipewireManager {
         self.routing_load().await
     }
 
+    async fn profile_connect_physical(&mut self) {
+        // Attach any hardware we already know about to the profile's physical nodes. Devices
+        // which aren't present yet will be matched by name in source / target_device_added when
+        // they appear, so a profile from another machine will pick up its devices as they arrive.
+        let mut physical = Vec::new();
+        for device in &self.profile.devices.sources.physical_devices {
+            physical.push(device.description.id);
+        }
+        for device in &self.profile.devices.targets.physical_devices {
+            physical.push(device.description.id);
+        }
+
+        for id in physical {
+            if let Err(e) = self.connect_for_node(id).await {
+                warn!("Unable to Connect Devices for Node {}: {}", id, e);
+            }
+        }
+    }
+
     fn check_device_order_present(&mut self, dev: &DeviceDescription, source: bool) -> Result<()> {
         let order_list = if source {
             &mut self.profile.devices.sources.device_order
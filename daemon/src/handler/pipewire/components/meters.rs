@@ -0,0 +1,106 @@
+use crate::handler::pipewire::components::volume::VolumeManager;
+use crate::handler::pipewire::manager::PipewireManager;
+use anyhow::Result;
+use log::debug;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+use ulid::Ulid;
+
+// Meters report every 100ms, this gives every meter time to report at least twice
+const METER_SAMPLE: Duration = Duration::from_millis(250);
+
+// Meters switched on for a reading stay on this long after the last one, so a script polling
+// for readings doesn't repeatedly link and unlink every meter
+const METER_HOLD: Duration = Duration::from_secs(2);
+
+/// Provides single readings from the meters, switching them on briefly if metering is off.
+pub(crate) trait MeterReadings {
+    /// Handles a metering change from the worker, this won't switch the meters off while
+    /// they're being held on for a reading
+    async fn meters_set_requested(&mut self, enabled: bool) -> Result<()>;
+
+    /// Sends the current meter readings, if the meters are off they'll be switched on and the
+    /// readings sent once they've had time to report
+    async fn meters_read(&mut self, callback: oneshot::Sender<HashMap<Ulid, u8>>) -> Result<()>;
+
+    /// When meters_tick next needs to run, if at all
+    fn meters_deadline(&self) -> Option<Instant>;
+    async fn meters_tick(&mut self);
+}
+
+impl MeterReadings for PipewireManager {
+    async fn meters_set_requested(&mut self, enabled: bool) -> Result<()> {
+        self.meter_requested = enabled;
+        if !enabled && self.meter_hold.is_some() {
+            // The hold will switch them off once it expires
+            return Ok(());
+        }
+        self.set_metering(enabled).await
+    }
+
+    async fn meters_read(&mut self, callback: oneshot::Sender<HashMap<Ulid, u8>>) -> Result<()> {
+        let now = Instant::now();
+
+        if !self.meter_enabled {
+            debug!("[Meters] Enabling Meters for a Reading");
+            self.set_metering(true).await?;
+            self.meter_ready = Some(now + METER_SAMPLE);
+        }
+
+        if !self.meter_requested {
+            self.meter_hold = Some(now + METER_HOLD);
+        }
+
+        if self.meter_ready.is_some() {
+            // Still waiting on the meters to report
+            self.meter_readers.push(callback);
+        } else {
+            let _ = callback.send(self.meter_readings());
+        }
+        Ok(())
+    }
+
+    fn meters_deadline(&self) -> Option<Instant> {
+        match (self.meter_ready, self.meter_hold) {
+            (Some(ready), Some(hold)) => Some(ready.min(hold)),
+            (ready, hold) => ready.or(hold),
+        }
+    }
+
+    async fn meters_tick(&mut self) {
+        let now = Instant::now();
+
+        if self.meter_ready.is_some_and(|ready| ready <= now) {
+            self.meter_ready = None;
+
+            let readings = self.meter_readings();
+            for reader in self.meter_readers.drain(..) {
+                let _ = reader.send(readings.clone());
+            }
+        }
+
+        if self.meter_hold.is_some_and(|hold| hold <= now) {
+            self.meter_hold = None;
+            if !self.meter_requested {
+                debug!("[Meters] Reading Hold Expired, Disabling Meters");
+                let _ = self.set_metering(false).await;
+            }
+        }
+    }
+}
+
+trait MeterReadingsLocal {
+    fn meter_readings(&self) -> HashMap<Ulid, u8>;
+}
+
+impl MeterReadingsLocal for PipewireManager {
+    fn meter_readings(&self) -> HashMap<Ulid, u8> {
+        // A meter which hasn't reported (eg. its node is suspended) is reading silence
+        self.meter_map
+            .keys()
+            .map(|node| (*node, self.meter_levels.get(node).copied().unwrap_or(0)))
+            .collect()
+    }
+}
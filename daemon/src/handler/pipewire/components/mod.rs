@@ -1,13 +1,22 @@
+pub(crate) mod aggregate;
 pub(crate) mod application;
 mod audio_filters;
+pub(crate) mod capture;
 pub(crate) mod defaults;
+pub(crate) mod eq;
 mod filters;
+pub(crate) mod latency;
 pub(crate) mod links;
 pub(crate) mod load_profile;
+pub(crate) mod meters;
 pub(crate) mod mute;
 pub(crate) mod node;
+pub(crate) mod orphans;
 pub(crate) mod physical;
 pub(crate) mod port_maps;
 pub(crate) mod profile;
+pub(crate) mod reconcile;
+pub(crate) mod restore;
 pub(crate) mod routing;
+pub(crate) mod test_tone;
 pub(crate) mod volume;
@@ -5,15 +5,132 @@ use crate::handler::pipewire::components::profile::ProfileManagement;
 use crate::handler::pipewire::components::routing::RoutingManagement;
 use crate::handler::pipewire::components::volume::VolumeManager;
 use crate::handler::pipewire::manager::PipewireManager;
+use crate::handler::primary_worker::ManagerMessage;
 use anyhow::{Result, anyhow, bail};
 use log::{debug, info, warn};
 use pipeweaver_pipewire::PipewireMessage;
-use pipeweaver_profile::MuteStates;
+use pipeweaver_profile::{AutoMuteRule, MuteStates, Profile};
 use pipeweaver_shared::{Mix, MuteState, MuteTarget, NodeType};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 use strum::IntoEnumIterator;
+use tokio::time::Instant;
 use ulid::Ulid;
 
+// Mute changes to the same node within this window are merged, and only the last is applied
+const MUTE_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// A mute change which is waiting for the debounce to settle
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub(crate) enum PendingMute {
+    Source(Ulid, MuteTarget),
+    Target(Ulid),
+}
+
+/// Client mute changes waiting to be applied, and when the debounce expires
+#[derive(Debug, Default)]
+pub(crate) struct PendingMutes {
+    changes: HashMap<PendingMute, MuteState>,
+    deadline: Option<Instant>,
+}
+
+impl PendingMutes {
+    /// Any earlier change for this node is superseded, and the debounce starts again
+    fn queue(&mut self, mute: PendingMute, state: MuteState, now: Instant) {
+        self.changes.insert(mute, state);
+        self.deadline = Some(now + MUTE_DEBOUNCE);
+    }
+
+    /// Takes every waiting change, leaving nothing for the debounce to do
+    fn take(&mut self) -> Vec<(PendingMute, MuteState)> {
+        self.deadline = None;
+        self.changes.drain().collect()
+    }
+
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Drops every waiting change without applying it
+    pub(crate) fn discard(&mut self) {
+        self.take();
+    }
+
+    /// Shows the waiting changes in a copy of the profile, so status reads see the state things
+    /// are about to be in without cutting the debounce short.
+    pub(crate) fn overlay(&self, profile: &mut Profile) {
+        let sources = &mut profile.devices.sources;
+        let targets = &mut profile.devices.targets;
+
+        for (mute, state) in &self.changes {
+            match *mute {
+                PendingMute::Source(id, target) => {
+                    let physical = sources.physical_devices.iter_mut();
+                    let physical = physical.map(|d| (d.description.id, &mut d.mute_states));
+                    let virtual_sources = sources.virtual_devices.iter_mut();
+                    let virtual_sources =
+                        virtual_sources.map(|d| (d.description.id, &mut d.mute_states));
+
+                    let mut found = physical.chain(virtual_sources);
+                    if let Some((_, states)) = found.find(|(i, _)| *i == id) {
+                        match state {
+                            MuteState::Unmuted => states.mute_state.retain(|&e| e != target),
+                            MuteState::Muted => {
+                                states.mute_state.insert(target);
+                            }
+                        }
+                    }
+                }
+                PendingMute::Target(id) => {
+                    let physical = targets.physical_devices.iter_mut();
+                    let physical = physical.map(|d| (d.description.id, &mut d.mute_state));
+                    let virtual_targets = targets.virtual_devices.iter_mut();
+                    let virtual_targets =
+                        virtual_targets.map(|d| (d.description.id, &mut d.mute_state));
+
+                    let mut found = physical.chain(virtual_targets);
+                    if let Some((_, mute_state)) = found.find(|(i, _)| *i == id) {
+                        *mute_state = *state;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// What needs to happen to any waiting mute changes before a manager message is handled
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum PendingMuteAction {
+    /// The message doesn't touch the profile, or handles the waiting changes itself
+    Keep,
+
+    /// The message reads or changes the profile, so has to see the changes in place
+    Apply,
+
+    /// The message replaces the profile (a rollback), the changes belong to the old one
+    Discard,
+}
+
+pub(crate) fn pending_mute_action(message: &ManagerMessage) -> PendingMuteAction {
+    match message {
+        // Commands apply them unless they're mutes themselves, and status reads overlay them
+        ManagerMessage::Execute(..) | ManagerMessage::GetAudioConfiguration(_) => {
+            PendingMuteAction::Keep
+        }
+        ManagerMessage::SetMetering(_)
+        | ManagerMessage::GetMeters(_)
+        | ManagerMessage::GetAudioQuantum(_)
+        | ManagerMessage::GetDevicePorts(..)
+        | ManagerMessage::SetRealtimePriority(..) => PendingMuteAction::Keep,
+        ManagerMessage::RestoreProfile(..) => PendingMuteAction::Discard,
+        _ => PendingMuteAction::Apply,
+    }
+}
+
 pub(crate) trait MuteManager {
     async fn add_target_mute_node(
         &mut self,
@@ -28,6 +145,12 @@ pub(crate) trait MuteManager {
         target: Ulid,
     ) -> Result<()>;
     async fn clear_target_mute_nodes(&mut self, id: Ulid, state: MuteTarget) -> Result<()>;
+    async fn set_mute_target_label(
+        &mut self,
+        id: Ulid,
+        target: MuteTarget,
+        label: String,
+    ) -> Result<()>;
 
     async fn set_source_mute_state(
         &mut self,
@@ -37,11 +160,21 @@ pub(crate) trait MuteManager {
     ) -> Result<()>;
     async fn set_target_mute_state(&mut self, id: Ulid, state: MuteState) -> Result<()>;
 
+    /// Queues a mute change from a client, so a burst of toggles (eg. a bouncing button) only
+    /// acts on the state things end up in. The node is validated now, so errors are returned
+    /// with the command rather than only being logged once the debounce expires.
+    fn queue_mute_state(&mut self, mute: PendingMute, state: MuteState) -> Result<()>;
+    async fn apply_pending_mutes(&mut self);
+
     async fn is_source_muted_to_some(&self, source: Ulid, target: Ulid) -> Result<bool>;
     async fn is_source_muted_to_all(&self, source: Ulid) -> Result<bool>;
     async fn get_target_mute_state(&self, target: Ulid) -> Result<MuteState>;
 
     async fn handle_source_effective_mute(&self, source: Ulid) -> Result<()>;
+
+    async fn add_auto_mute_rule(&mut self, target: Ulid, mix: Mix, source: Ulid) -> Result<()>;
+    async fn del_auto_mute_rule(&mut self, target: Ulid, mix: Mix, source: Ulid) -> Result<()>;
+    async fn apply_auto_mute_rules(&mut self) -> Result<()>;
 }
 
 impl MuteManager for PipewireManager {
@@ -143,6 +276,23 @@ impl MuteManager for PipewireManager {
         Ok(())
     }
 
+    async fn set_mute_target_label(
+        &mut self,
+        id: Ulid,
+        target: MuteTarget,
+        label: String,
+    ) -> Result<()> {
+        let label = label.trim();
+        if label.is_empty() {
+            bail!("Mute Target Label cannot be empty");
+        }
+
+        let mute_state = self.get_source_mute_states_mut(id)?;
+        mute_state.mute_target_labels[target] = label.to_string();
+
+        Ok(())
+    }
+
     async fn set_source_mute_state(
         &mut self,
         id: Ulid,
@@ -315,6 +465,44 @@ impl MuteManager for PipewireManager {
         Ok(())
     }
 
+    fn queue_mute_state(&mut self, mute: PendingMute, state: MuteState) -> Result<()> {
+        // The same checks the setters make before they change anything
+        match mute {
+            PendingMute::Source(id, _) => {
+                self.get_source_mute_states(id)?;
+            }
+            PendingMute::Target(id) => {
+                let node_type = self.get_node_type(id).ok_or(anyhow!("Unknown Node"))?;
+                let err = anyhow!("Unable to Locate Target");
+                match node_type {
+                    NodeType::PhysicalTarget => self.get_physical_target(id).map(|_| ()),
+                    NodeType::VirtualTarget => self.get_virtual_target(id).map(|_| ()),
+                    _ => bail!("Provided Target is a Source Node"),
+                }
+                .ok_or(err)?;
+            }
+        }
+
+        self.pending_mutes.queue(mute, state, Instant::now());
+        Ok(())
+    }
+
+    async fn apply_pending_mutes(&mut self) {
+        // Changes which ended up where they started are a no-op in the setters. As the nodes
+        // were checked when queued, anything failing here is Pipewire refusing the change.
+        for (mute, state) in self.pending_mutes.take() {
+            let result = match mute {
+                PendingMute::Source(id, target) => {
+                    self.set_source_mute_state(id, target, state).await
+                }
+                PendingMute::Target(id) => self.set_target_mute_state(id, state).await,
+            };
+            if let Err(e) = result {
+                warn!("Unable to apply {:?} to {:?}: {}", state, mute, e);
+            }
+        }
+    }
+
     async fn is_source_muted_to_some(&self, source: Ulid, target: Ulid) -> Result<bool> {
         let states = self.get_source_mute_states(source)?;
         for state in MuteTarget::iter() {
@@ -330,6 +518,11 @@ impl MuteManager for PipewireManager {
     }
 
     async fn is_source_muted_to_all(&self, source: Ulid) -> Result<bool> {
+        // An automatic mute silences the source everywhere, same as a 'Mute to All'
+        if self.auto_muted_sources.contains(&source) {
+            return Ok(true);
+        }
+
         let states = self.get_source_mute_states(source)?;
         for state in MuteTarget::iter() {
             if states.mute_state.contains(&state) && states.mute_targets[state].is_empty() {
@@ -390,6 +583,100 @@ impl MuteManager for PipewireManager {
 
         Ok(())
     }
+
+    async fn add_auto_mute_rule(&mut self, target: Ulid, mix: Mix, source: Ulid) -> Result<()> {
+        let node_type = self.get_node_type(target).ok_or(anyhow!("Unknown Node"))?;
+        if !matches!(
+            node_type,
+            NodeType::PhysicalTarget | NodeType::VirtualTarget
+        ) {
+            bail!("Provided Target is a Source Node");
+        }
+
+        let node_type = self.get_node_type(source).ok_or(anyhow!("Unknown Node"))?;
+        if !matches!(
+            node_type,
+            NodeType::PhysicalSource | NodeType::VirtualSource
+        ) {
+            bail!("Provided Source is a Target Node");
+        }
+
+        let rule = AutoMuteRule {
+            trigger_target: target,
+            trigger_mix: mix,
+            affected_source: source,
+        };
+        if self.profile.auto_mute_rules.contains(&rule) {
+            bail!("Auto Mute Rule already exists");
+        }
+
+        self.profile.auto_mute_rules.push(rule);
+        self.apply_auto_mute_rules().await
+    }
+
+    async fn del_auto_mute_rule(&mut self, target: Ulid, mix: Mix, source: Ulid) -> Result<()> {
+        let rule = AutoMuteRule {
+            trigger_target: target,
+            trigger_mix: mix,
+            affected_source: source,
+        };
+        if !self.profile.auto_mute_rules.contains(&rule) {
+            bail!("Auto Mute Rule not Found");
+        }
+
+        self.profile.auto_mute_rules.retain(|r| r != &rule);
+        self.apply_auto_mute_rules().await
+    }
+
+    async fn apply_auto_mute_rules(&mut self) -> Result<()> {
+        // Work out which sources should currently be muted by a rule
+        let mut should_mute = HashSet::new();
+        for rule in self.profile.auto_mute_rules.clone() {
+            if self.get_node_type(rule.affected_source).is_none() {
+                continue;
+            }
+            if let Ok(mix) = self.routing_get_target_mix(&rule.trigger_target).await
+                && mix == rule.trigger_mix
+            {
+                should_mute.insert(rule.affected_source);
+            }
+        }
+
+        let to_mute: Vec<Ulid> = should_mute
+            .difference(&self.auto_muted_sources)
+            .copied()
+            .collect();
+        let to_restore: Vec<Ulid> = self
+            .auto_muted_sources
+            .difference(&should_mute)
+            .copied()
+            .collect();
+
+        for source in to_mute {
+            debug!("Auto Muting Source {}", source);
+
+            // If the user has already muted this to all, the volume is already gone
+            let already_muted = self.is_source_muted_to_all(source).await?;
+            self.auto_muted_sources.insert(source);
+            if !already_muted {
+                self.mute_remove_volume(source).await?;
+            }
+            self.handle_source_effective_mute(source).await?;
+        }
+
+        for source in to_restore {
+            debug!("Restoring Auto Muted Source {}", source);
+            self.auto_muted_sources.remove(&source);
+
+            // Only bring the volume back if the user hasn't muted it themselves in the meantime
+            if self.get_node_type(source).is_some() && !self.is_source_muted_to_all(source).await? {
+                self.mute_restore_volume(source).await?;
+                self.handle_source_effective_mute(source).await?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 trait MuteManagerLocal {
@@ -522,6 +809,11 @@ impl MuteManagerLocal for PipewireManager {
     }
 
     async fn mute_restore_volume(&mut self, source: Ulid) -> Result<()> {
+        if self.auto_muted_sources.contains(&source) {
+            debug!("Source is Auto Muted, not restoring volume");
+            return Ok(());
+        }
+
         let mix_err = anyhow!("Unable to Find Source Mixes");
         let map = self.source_map.get(&source).copied().ok_or(mix_err)?;
 
@@ -635,3 +927,139 @@ impl MuteManagerLocal for PipewireManager {
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipeweaver_profile::{PhysicalSourceDevice, VirtualTargetDevice};
+    use tokio::sync::oneshot;
+
+    #[test]
+    fn rapid_toggles_settle_on_the_last_state() {
+        let mut pending = PendingMutes::default();
+        let mute = PendingMute::Source(Ulid::new(), MuteTarget::TargetA);
+
+        let start = Instant::now();
+        let mut now = start;
+        let mut state = MuteState::Unmuted;
+        for _ in 0..10 {
+            state = match state {
+                MuteState::Unmuted => MuteState::Muted,
+                MuteState::Muted => MuteState::Unmuted,
+            };
+            pending.queue(mute, state, now);
+            now += Duration::from_millis(10);
+        }
+
+        // Each toggle pushes the deadline back from when it arrived
+        let last = start + Duration::from_millis(90);
+        assert_eq!(pending.deadline(), Some(last + MUTE_DEBOUNCE));
+
+        // Only the final state is applied, and once it has been nothing is left waiting
+        assert_eq!(pending.take(), vec![(mute, MuteState::Unmuted)]);
+        assert!(pending.is_empty());
+        assert_eq!(pending.deadline(), None);
+    }
+
+    #[test]
+    fn toggles_on_different_nodes_are_kept_apart() {
+        let mut pending = PendingMutes::default();
+        let source = PendingMute::Source(Ulid::new(), MuteTarget::TargetA);
+        let target = PendingMute::Target(Ulid::new());
+
+        let now = Instant::now();
+        pending.queue(source, MuteState::Muted, now);
+        pending.queue(target, MuteState::Muted, now);
+        pending.queue(source, MuteState::Unmuted, now);
+
+        let mut changes = pending.take();
+        changes.sort_by_key(|(mute, _)| matches!(mute, PendingMute::Target(_)));
+        assert_eq!(
+            changes,
+            vec![(source, MuteState::Unmuted), (target, MuteState::Muted)]
+        );
+    }
+
+    #[test]
+    fn status_reads_show_queued_mutes() {
+        let mut profile = Profile::default();
+        let source = PhysicalSourceDevice::default();
+        let source_id = source.description.id;
+        profile.devices.sources.physical_devices.push(source);
+
+        let mut pending = PendingMutes::default();
+        let now = Instant::now();
+        pending.queue(
+            PendingMute::Source(source_id, MuteTarget::TargetB),
+            MuteState::Muted,
+            now,
+        );
+
+        let mut status = profile.clone();
+        pending.overlay(&mut status);
+        let states = &status.devices.sources.physical_devices[0].mute_states;
+        assert!(states.mute_state.contains(&MuteTarget::TargetB));
+
+        // The change is only shown, it's still waiting to be applied
+        assert!(
+            profile.devices.sources.physical_devices[0]
+                .mute_states
+                .mute_state
+                .is_empty()
+        );
+        assert!(!pending.is_empty());
+    }
+
+    #[test]
+    fn rollback_discards_queued_mutes() {
+        let mut profile = Profile::default();
+        let target = VirtualTargetDevice::default();
+        let target_id = target.description.id;
+        profile.devices.targets.virtual_devices.push(target);
+
+        // A transaction queued a mute, then a later command failed
+        let mut pending = PendingMutes::default();
+        pending.queue(
+            PendingMute::Target(target_id),
+            MuteState::Muted,
+            Instant::now(),
+        );
+
+        let (tx, _rx) = oneshot::channel();
+        let rollback = ManagerMessage::RestoreProfile(Box::new(profile.clone()), tx);
+        assert_eq!(pending_mute_action(&rollback), PendingMuteAction::Discard);
+        pending.discard();
+
+        // Nothing is left to be applied once the debounce would have expired
+        assert!(pending.is_empty());
+        assert_eq!(pending.deadline(), None);
+        pending.overlay(&mut profile);
+        assert_eq!(
+            profile.devices.targets.virtual_devices[0].mute_state,
+            MuteState::Unmuted
+        );
+    }
+
+    #[test]
+    fn profile_messages_apply_queued_mutes_first() {
+        let (tx, _rx) = oneshot::channel();
+        let apply = ManagerMessage::ApplyEqPreset(String::from("Flat"), Ulid::new(), tx);
+        assert_eq!(pending_mute_action(&apply), PendingMuteAction::Apply);
+
+        let (tx, _rx) = oneshot::channel();
+        let restore = ManagerMessage::RestoreVolumes(Default::default(), tx);
+        assert_eq!(pending_mute_action(&restore), PendingMuteAction::Apply);
+
+        let (tx, _rx) = oneshot::channel();
+        let snapshot = ManagerMessage::SnapshotVolumes(tx);
+        assert_eq!(pending_mute_action(&snapshot), PendingMuteAction::Apply);
+
+        let (tx, _rx) = oneshot::channel();
+        let preview = ManagerMessage::PreviewMix(Mix::B, true, tx);
+        assert_eq!(pending_mute_action(&preview), PendingMuteAction::Apply);
+        assert_eq!(
+            pending_mute_action(&ManagerMessage::Quit),
+            PendingMuteAction::Apply
+        );
+    }
+}
@@ -2,26 +2,74 @@ use crate::handler::pipewire::components::application::ApplicationManagement;
 use crate::handler::pipewire::components::filters::FilterManagement;
 use crate::handler::pipewire::components::links::LinkManagement;
 use crate::handler::pipewire::components::load_profile::LoadProfile;
+use crate::handler::pipewire::components::mute::MuteManager;
 use crate::handler::pipewire::components::physical::PhysicalDevices;
 use crate::handler::pipewire::components::profile::ProfileManagement;
 use crate::handler::pipewire::components::routing::RoutingManagement;
+use crate::handler::pipewire::components::test_tone::TestToneManagement;
 use crate::handler::pipewire::components::volume::VolumeManager;
 use crate::handler::pipewire::manager::PipewireManager;
-use crate::{APP_ID, APP_NAME};
 use anyhow::{Result, anyhow, bail};
 use enum_map::{EnumMap, enum_map};
+use log::debug;
 use pipeweaver_pipewire::oneshot;
 use pipeweaver_pipewire::{MediaClass, NodeProperties, PipewireMessage};
 use pipeweaver_profile::{
     DeviceDescription, PhysicalSourceDevice, PhysicalTargetDevice, VirtualSourceDevice,
     VirtualTargetDevice,
 };
-use pipeweaver_shared::{Colour, Mix, NodeType, OrderGroup};
+use pipeweaver_shared::{Colour, Mix, MuteState, MuteTarget, NodeType, OrderGroup};
 use strum::IntoEnumIterator;
 use ulid::Ulid;
 
 type GroupList = EnumMap<OrderGroup, Vec<Ulid>>;
 
+const MAX_NODE_CHANNELS: u8 = 16;
+
+// The colours new nodes cycle through when the settings don't provide a palette
+const DEFAULT_PALETTE: [Colour; 8] = [
+    Colour {
+        red: 0,
+        green: 255,
+        blue: 255,
+    },
+    Colour {
+        red: 255,
+        green: 99,
+        blue: 71,
+    },
+    Colour {
+        red: 124,
+        green: 252,
+        blue: 0,
+    },
+    Colour {
+        red: 255,
+        green: 215,
+        blue: 0,
+    },
+    Colour {
+        red: 186,
+        green: 85,
+        blue: 211,
+    },
+    Colour {
+        red: 30,
+        green: 144,
+        blue: 255,
+    },
+    Colour {
+        red: 255,
+        green: 105,
+        blue: 180,
+    },
+    Colour {
+        red: 255,
+        green: 140,
+        blue: 0,
+    },
+];
+
 /// This crate contains everything needed to create a Pipewire node
 pub(crate) trait NodeManagement {
     fn get_node_type(&self, id: Ulid) -> Option<NodeType>;
@@ -35,12 +83,34 @@ pub(crate) trait NodeManagement {
     ) -> Result<()>;
     async fn node_rename(&mut self, id: Ulid, name: String) -> Result<()>;
     async fn node_remove(&mut self, id: Ulid) -> Result<()>;
+    async fn node_reset(&mut self, id: Ulid) -> Result<()>;
 
     async fn node_set_group(&mut self, id: Ulid, group: OrderGroup) -> Result<()>;
-    async fn node_set_position(&mut self, id: Ulid, position: u8) -> Result<()>;
+    async fn node_set_position(
+        &mut self,
+        id: Ulid,
+        group: Option<OrderGroup>,
+        position: u8,
+    ) -> Result<()>;
 
     async fn node_set_colour(&mut self, id: Ulid, colour: Colour) -> Result<()>;
+    async fn node_set_channels(&mut self, id: Ulid, channels: u8) -> Result<()>;
     fn get_target_node_count(&self) -> usize;
+
+    /// Rebuilds a virtual node from the profile, used when Pipewire removes it from under us
+    async fn node_recreate(&mut self, id: Ulid) -> Result<()>;
+
+    /// Generates the Pipewire facing name for one of our nodes
+    fn node_identifier(&self, id: Ulid, name: &str) -> String;
+
+    /// Used to set up the parameters needed for one of our Pipewire Nodes
+    fn create_node_props(
+        &self,
+        class: MediaClass,
+        id: Ulid,
+        name: &str,
+        volume: u8,
+    ) -> NodeProperties;
 }
 
 impl NodeManagement for PipewireManager {
@@ -98,6 +168,7 @@ impl NodeManagement for PipewireManager {
             id,
             name: name.clone(),
             colour: self.get_colour(name),
+            eq: None,
         };
 
         // Store this in the profile, and setup default blank routing table
@@ -156,6 +227,14 @@ impl NodeManagement for PipewireManager {
         // Load the initial volumes onto the node
         self.load_initial_volume(id).await?;
 
+        // A new source won't have any routes yet
+        if matches!(
+            node_type,
+            NodeType::PhysicalSource | NodeType::VirtualSource
+        ) {
+            self.routing_update_idle(id).await?;
+        }
+
         Ok(id)
     }
 
@@ -207,8 +286,10 @@ impl NodeManagement for PipewireManager {
         let description = self.get_device_description(id)?;
         description.name = name;
 
-        // Create a local version of this description, create the node tree and load volumes
+        // Create a local version of this description, create the node tree and load volumes,
+        // the new tree is fully connected, so it's no longer idle until the routes say so
         let local_desc = description.clone();
+        self.idle_sources.remove(&id);
         self.node_create(node_type, &local_desc).await?;
         self.load_initial_volume(id).await?;
         self.sync_pipewire_volume(id).await;
@@ -217,6 +298,7 @@ impl NodeManagement for PipewireManager {
         match node_type {
             NodeType::PhysicalSource | NodeType::VirtualSource => {
                 self.routing_load_source(&id).await?;
+                self.routing_update_idle(id).await?;
             }
             NodeType::PhysicalTarget | NodeType::VirtualTarget => {
                 self.routing_load_target(&id).await?
@@ -235,43 +317,110 @@ impl NodeManagement for PipewireManager {
         // This is complicated, it depends purely on the node type and what we're trying to do here.
 
         if let Some(node_type) = self.get_node_type(id) {
+            // Stop any Test Tone before its Target goes away
+            if self.test_tones.contains_key(&id) {
+                self.test_tone_stop(id).await?;
+            }
+
+            // Unlink any tap first, otherwise the dropped link is re-established
+            self.routing_unload_taps(id).await?;
+
             match node_type {
                 NodeType::PhysicalSource => self.node_remove_physical_source(id, true).await?,
                 NodeType::VirtualSource => self.node_remove_virtual_source(id, true).await?,
                 NodeType::PhysicalTarget => self.node_remove_physical_target(id, true).await?,
                 NodeType::VirtualTarget => self.node_remove_virtual_target(id, true).await?,
             }
-        }
-        Ok(())
-    }
 
-    async fn node_set_group(&mut self, id: Ulid, group: OrderGroup) -> Result<()> {
-        let device_order = self.get_device_order_group(id)?;
+            // Removing a target may have left sources with nowhere to go
+            self.idle_sources.remove(&id);
+            if matches!(
+                node_type,
+                NodeType::PhysicalTarget | NodeType::VirtualTarget
+            ) {
+                let sources: Vec<Ulid> = self.source_map.keys().copied().collect();
+                for source in sources {
+                    self.routing_update_idle(source).await?;
+                }
+            }
+
+            // Drop any automatic mute rules which reference this node
+            self.auto_muted_sources.remove(&id);
+            self.profile
+                .auto_mute_rules
+                .retain(|r| r.trigger_target != id && r.affected_source != id);
+
+            // And any channel pair assignments for it
+            for target in &mut self.profile.devices.targets.virtual_devices {
+                target.channel_routes.remove(&id);
+            }
 
-        // Remove this node from it's existing group
-        Self::find_order_group_by_id(id, device_order)?.retain(|d| d != &id);
+            // Sources tapping this target carry on as regular Virtual Sources
+            for source in &mut self.profile.devices.sources.virtual_devices {
+                if source.tap_target == Some(id) {
+                    source.tap_target = None;
+                }
+            }
 
-        // Set it to the front of the new group
-        device_order[group].insert(0, id);
+            if self.profile.monitor_target == Some(id) {
+                self.profile.monitor_target = None;
+            }
+            if self.profile.recorder_target == Some(id) {
+                self.profile.recorder_target = None;
+            }
 
+            // If applications were falling back to this, they'll now stay on the aggregate
+            if let Some(aggregate) = &mut self.profile.aggregate_sink
+                && aggregate.fallback == Some(id)
+            {
+                aggregate.fallback = None;
+            }
+        }
         Ok(())
     }
 
-    async fn node_set_position(&mut self, id: Ulid, position: u8) -> Result<()> {
-        let device_order = self.get_device_order_group(id)?;
-        let order = Self::find_order_group_by_id(id, device_order)?;
+    async fn node_reset(&mut self, id: Ulid) -> Result<()> {
+        let node_type = self
+            .get_node_type(id)
+            .ok_or(anyhow!("Node {} not Found", id))?;
 
-        // Remove it from the existing list
-        let position = position as usize;
-        order.retain(|d| d != &id);
-        if position >= order.len() {
-            order.push(id);
-        } else {
-            order.insert(position, id);
+        match node_type {
+            NodeType::PhysicalSource | NodeType::VirtualSource => {
+                // Unlink first so the mixes don't drag each other about, this errors if they're
+                // already unlinked, which is fine.
+                let _ = self.set_source_volume_linked(id, false).await;
+                for mix in Mix::iter() {
+                    self.set_source_volume(id, mix, 100, true).await?;
+                }
+                self.set_source_volume_linked(id, true).await?;
+
+                for target in MuteTarget::iter() {
+                    self.set_source_mute_state(id, target, MuteState::Unmuted)
+                        .await?;
+                    self.clear_target_mute_nodes(id, target).await?;
+                }
+            }
+            NodeType::PhysicalTarget | NodeType::VirtualTarget => {
+                self.set_target_volume(id, 100, true).await?;
+                self.set_target_mute_state(id, MuteState::Unmuted).await?;
+            }
         }
         Ok(())
     }
 
+    async fn node_set_group(&mut self, id: Ulid, group: OrderGroup) -> Result<()> {
+        order_set_group(self.get_device_order_group(id)?, id, group)
+    }
+
+    async fn node_set_position(
+        &mut self,
+        id: Ulid,
+        group: Option<OrderGroup>,
+        position: u8,
+    ) -> Result<()> {
+        order_set_position(self.get_device_order_group(id)?, id, group, position)
+    }
+
     async fn node_set_colour(&mut self, id: Ulid, colour: Colour) -> Result<()> {
         if let Some(node_type) = self.get_node_type(id) {
             let err = anyhow!("Cannot Find Node");
@@ -305,10 +454,236 @@ impl NodeManagement for PipewireManager {
         Ok(())
     }
 
+    async fn node_set_channels(&mut self, id: Ulid, channels: u8) -> Result<()> {
+        if self.get_node_type(id) != Some(NodeType::VirtualTarget) {
+            bail!("Only Virtual Targets support multiple channels");
+        }
+        if !(2..=MAX_NODE_CHANNELS).contains(&channels) || channels % 2 != 0 {
+            bail!(
+                "Channel count must be an even number between 2 and {}",
+                MAX_NODE_CHANNELS
+            );
+        }
+
+        let err = anyhow!("Unable to find Node");
+        let channels = (channels > 2).then_some(channels);
+        if self.get_virtual_target(id).ok_or(err)?.channels == channels {
+            return Ok(());
+        }
+
+        // Like renaming, the port layout can't be changed on an existing node, so we need to
+        // tear it down while it's still using the old layout, then build a new one.
+        self.node_remove_virtual_target(id, false).await?;
+
+        let err = anyhow!("Unable to find Node");
+        let target = self.get_virtual_target_mut(id).ok_or(err)?;
+        target.channels = channels;
+
+        // Any sources landing on a pair that no longer exists fall back to the first pair
+        let pairs = channels.unwrap_or(2) / 2;
+        target.channel_routes.retain(|_, pair| *pair < pairs);
+
+        let description = target.description.clone();
+        let attached = target.attached_devices.clone();
+
+        self.node_create(NodeType::VirtualTarget, &description)
+            .await?;
+        self.load_initial_volume(id).await?;
+        self.sync_pipewire_volume(id).await;
+        self.routing_load_target(&id).await?;
+        self.refresh_applications(id).await?;
+
+        for device in attached {
+            if let Some(node_id) = self.locate_node(device).map(|node| node.node_id) {
+                self.link_create_node_to_unmanaged(id, node_id).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn get_target_node_count(&self) -> usize {
         let devices = &self.profile.devices.targets;
         devices.physical_devices.len() + devices.virtual_devices.len()
     }
+
+    async fn node_recreate(&mut self, id: Ulid) -> Result<()> {
+        let node_type = self
+            .get_node_type(id)
+            .ok_or(anyhow!("Node {} not Found", id))?;
+
+        // The Pipewire node is already gone, but the filters and links around it aren't, so
+        // tear down whatever's left before building it all again.
+        match node_type {
+            NodeType::VirtualSource => self.node_remove_virtual_source(id, false).await?,
+            NodeType::VirtualTarget => self.node_remove_virtual_target(id, false).await?,
+            _ => bail!("Only Virtual Nodes are backed by a Pipewire Node"),
+        }
+
+        let description = self.get_device_description(id)?.clone();
+        self.idle_sources.remove(&id);
+        self.node_create(node_type, &description).await?;
+        self.load_initial_volume(id).await?;
+        self.sync_pipewire_volume(id).await;
+
+        // Whichever side of a tap this is, the link to the other side went with the node
+        self.routing_load_taps(id).await?;
+
+        if node_type == NodeType::VirtualSource {
+            self.routing_load_source(&id).await?;
+            self.routing_update_idle(id).await?;
+        } else {
+            self.routing_load_target(&id).await?;
+
+            let attached = self
+                .get_virtual_target(id)
+                .map(|target| target.attached_devices.clone())
+                .unwrap_or_default();
+            for device in attached {
+                if let Some(node_id) = self.locate_node(device).map(|node| node.node_id) {
+                    self.link_create_node_to_unmanaged(id, node_id).await?;
+                }
+            }
+            self.test_tone_relink(id).await?;
+        }
+
+        self.refresh_applications(id).await
+    }
+
+    fn node_identifier(&self, id: Ulid, name: &str) -> String {
+        let prefix = &self.naming.app_name;
+        let identifier = sanitise_identifier(prefix, name);
+
+        // Different display names (eg. two emoji only names) can end up with the same
+        // identifier, so if that happens, tag on the end of the node's ID to keep them apart.
+        let devices = &self.profile.devices;
+        let sources = devices
+            .sources
+            .virtual_devices
+            .iter()
+            .map(|d| &d.description);
+        let targets = devices
+            .targets
+            .virtual_devices
+            .iter()
+            .map(|d| &d.description);
+        let aggregate = self.profile.aggregate_sink.iter().map(|a| (a.id, &a.name));
+
+        let clash = sources
+            .chain(targets)
+            .map(|d| (d.id, &d.name))
+            .chain(aggregate)
+            .any(|(other, name)| other != id && sanitise_identifier(prefix, name) == identifier);
+
+        if clash {
+            let id = id.to_string().to_lowercase();
+            return format!("{}_{}", identifier, &id[id.len() - 6..]);
+        }
+        identifier
+    }
+
+    fn create_node_props(
+        &self,
+        class: MediaClass,
+        id: Ulid,
+        name: &str,
+        volume: u8,
+    ) -> NodeProperties {
+        let managed_volume = matches!(class, MediaClass::Sink);
+
+        let identifier = self.node_identifier(id, name);
+
+        let buffer = self.profile.audio_node_quantum.map(|buffer| buffer.into());
+
+        NodeProperties {
+            node_id: id,
+            node_name: identifier.clone(),
+            node_nick: identifier,
+            node_description: format!("{} {}", self.naming.app_name, name),
+            initial_volume: volume,
+            naming: self.naming.clone(),
+            linger: false,
+            class,
+            managed_volume,
+            volume_curve: self.profile.volume_curve,
+            channels: 2,
+            buffer,
+            rate: self.clock_rate.unwrap_or(48000),
+            ready_sender: None,
+        }
+    }
+}
+
+/// Pipewire node names are expected to be fairly plain, so only ASCII letters, digits, '-' and
+/// '_' are kept. Spaces become '_', and any run of other characters (punctuation, emoji,
+/// non-latin scripts) is collapsed into a single '_'.
+pub(crate) fn sanitise_identifier(prefix: &str, name: &str) -> String {
+    let mut identifier = String::new();
+    for char in format!("{} {}", prefix, name).chars() {
+        if char == ' ' {
+            identifier.push('_');
+        } else if char.is_ascii_alphanumeric() || char == '-' || char == '_' {
+            identifier.push(char.to_ascii_lowercase());
+        } else if !identifier.ends_with('_') {
+            identifier.push('_');
+        }
+    }
+    identifier
+}
+
+/// Moves a node to the front of another group
+fn order_set_group(device_order: &mut GroupList, id: Ulid, group: OrderGroup) -> Result<()> {
+    let (_, current) = device_order
+        .iter_mut()
+        .find(|(_, vec)| vec.contains(&id))
+        .ok_or(anyhow!("Id Not Found in Vec List"))?;
+
+    // Remove this node from it's existing group
+    current.retain(|d| d != &id);
+
+    // Set it to the front of the new group
+    device_order[group].insert(0, id);
+
+    Ok(())
+}
+
+/// Moves a node within its group, if a group is given the position is only applied while the
+/// node is still in it
+fn order_set_position(
+    device_order: &mut GroupList,
+    id: Ulid,
+    group: Option<OrderGroup>,
+    position: u8,
+) -> Result<()> {
+    // Resolve the group the node is in *now*, a client may have sent this position before
+    // it saw a group change, in which case the index is relative to a different list.
+    let current = device_order
+        .iter()
+        .find(|(_, vec)| vec.contains(&id))
+        .map(|(group, _)| group)
+        .ok_or(anyhow!("Id Not Found in Vec List"))?;
+
+    if let Some(group) = group
+        && group != current
+    {
+        debug!(
+            "Ignoring position for {} in {:?}, node is now in {:?}",
+            id, group, current
+        );
+        return Ok(());
+    }
+
+    let order = &mut device_order[current];
+
+    // Remove it from the existing list
+    let position = position as usize;
+    order.retain(|d| d != &id);
+    if position >= order.len() {
+        order.push(id);
+    } else {
+        order.insert(position, id);
+    }
+    Ok(())
 }
 
 trait NodeManagementLocal {
@@ -318,6 +693,7 @@ trait NodeManagementLocal {
     async fn node_create_physical_target(&mut self, desc: &DeviceDescription) -> Result<()>;
     async fn node_create_virtual_target(&mut self, desc: &DeviceDescription) -> Result<()>;
     async fn node_create_a_b_volumes(&mut self, desc: &DeviceDescription) -> Result<(Ulid, Ulid)>;
+    async fn node_create_eq(&mut self, desc: &DeviceDescription) -> Result<Ulid>;
     async fn node_pw_create(&mut self, props: NodeProperties) -> Result<()>;
 
     async fn node_remove_physical_source(&mut self, id: Ulid, profile_remove: bool) -> Result<()>;
@@ -329,9 +705,6 @@ trait NodeManagementLocal {
     /// Used to Remove all Links from a Filter
     async fn remove_routes(&mut self, source: Ulid, target: Ulid) -> Result<()>;
 
-    /// Used to set up the parameters needed for a Pipewire Node
-    fn create_node_props(&self, class: MediaClass, desc: &DeviceDescription) -> NodeProperties;
-
     fn get_device_order_group(&mut self, id: Ulid) -> Result<&mut GroupList>;
     fn find_order_group_by_id(id: Ulid, map: &mut GroupList) -> Result<&mut Vec<Ulid>>;
     fn get_colour(&self, name: String) -> Colour;
@@ -354,9 +727,11 @@ impl NodeManagementLocal for PipewireManager {
 
         let (mix_a, mix_b) = self.node_create_a_b_volumes(desc).await?;
 
-        // Now we need to link our filter to the Mixes
-        self.link_create_filter_to_filter(desc.id, mix_a).await?;
-        self.link_create_filter_to_filter(desc.id, mix_b).await?;
+        // Now we need to link our filter through the EQ to the Mixes
+        let eq = self.node_create_eq(desc).await?;
+        self.link_create_filter_to_filter(desc.id, eq).await?;
+        self.link_create_filter_to_filter(eq, mix_a).await?;
+        self.link_create_filter_to_filter(eq, mix_b).await?;
 
         // Add this for mapping physical devices
         self.physical_source.insert(desc.id, vec![]);
@@ -371,7 +746,8 @@ impl NodeManagementLocal for PipewireManager {
 
     async fn node_create_virtual_source(&mut self, desc: &DeviceDescription) -> Result<()> {
         // A 'Virtual' source is a pipewire node that's selectable by the user.
-        let properties = self.create_node_props(MediaClass::Sink, desc);
+        let volume = self.get_node_volume(desc.id, Mix::A)?;
+        let properties = self.create_node_props(MediaClass::Sink, desc.id, &desc.name, volume);
         self.node_pw_create(properties).await?;
 
         // Create a Meter
@@ -387,9 +763,11 @@ impl NodeManagementLocal for PipewireManager {
         // Generate the A/B Mixes
         let (mix_a, mix_b) = self.node_create_a_b_volumes(desc).await?;
 
-        // Now we need to link our node to the Mixes
-        self.link_create_node_to_filter(desc.id, mix_a).await?;
-        self.link_create_node_to_filter(desc.id, mix_b).await?;
+        // Now we need to link our node through the EQ to the Mixes
+        let eq = self.node_create_eq(desc).await?;
+        self.link_create_node_to_filter(desc.id, eq).await?;
+        self.link_create_filter_to_filter(eq, mix_a).await?;
+        self.link_create_filter_to_filter(eq, mix_b).await?;
 
         // Create a map for this ID to the mixes
         self.source_map
@@ -429,7 +807,14 @@ impl NodeManagementLocal for PipewireManager {
 
     async fn node_create_virtual_target(&mut self, desc: &DeviceDescription) -> Result<()> {
         // Virtual Targets (Such as Stream Mix) have a volume node and a target node
-        let properties = self.create_node_props(MediaClass::Source, desc);
+        let volume = self.get_node_volume(desc.id, Mix::A)?;
+        let mut properties =
+            self.create_node_props(MediaClass::Source, desc.id, &desc.name, volume);
+        if let Some(target) = self.get_virtual_target(desc.id)
+            && let Some(channels) = target.channels
+        {
+            properties.channels = channels;
+        }
         self.node_pw_create(properties).await?;
 
         // Create a meter and attach it to the volume
@@ -454,13 +839,25 @@ impl NodeManagementLocal for PipewireManager {
         Ok((mix_a, mix_b))
     }
 
+    async fn node_create_eq(&mut self, desc: &DeviceDescription) -> Result<Ulid> {
+        let settings = desc.eq.clone().unwrap_or_default();
+        let eq = self
+            .filter_eq_create(format!("{} EQ", desc.name), settings)
+            .await?;
+        self.eq_map.insert(desc.id, eq);
+
+        Ok(eq)
+    }
+
     async fn node_pw_create(&mut self, mut props: NodeProperties) -> Result<()> {
         let (send, recv) = oneshot::channel();
         props.ready_sender = Some(send);
+        let id = props.node_id;
 
         let message = PipewireMessage::CreateDeviceNode(props);
         self.pipewire().send_message(message)?;
-        recv.await?;
+        recv.await
+            .map_err(|_| anyhow!("Node {} creation aborted before it became ready", id))?;
 
         Ok(())
     }
@@ -474,6 +871,18 @@ impl NodeManagementLocal for PipewireManager {
             }
         }
 
+        // Devices attached as a Mono Sum go through their own filter, remove those too
+        let sums: Vec<Ulid> = self
+            .mono_sum_filters
+            .iter()
+            .filter(|((_, source), _)| *source == id)
+            .map(|(_, sum)| *sum)
+            .collect();
+        self.mono_sum_filters.retain(|(_, source), _| *source != id);
+        for sum in sums {
+            self.filter_remove(sum).await?;
+        }
+
         // Detach and destroy the Meter
         if let Some(&meter) = self.meter_map.get(&id) {
             if self.meter_enabled {
@@ -483,11 +892,17 @@ impl NodeManagementLocal for PipewireManager {
             self.meter_map.remove(&id);
         }
 
-        // Next, we detach the links from the pass through to the A/B mixes
+        // Next, we detach the links from the pass through, via the EQ, to the A/B mixes
+        let eq = self.eq_map.remove(&id);
+        if let Some(eq) = eq {
+            self.link_remove_filter_to_filter(id, eq).await?;
+        }
         if let Some(mix_map) = self.source_map.get(&id) {
             let mix_map = *mix_map;
             for mix in Mix::iter() {
-                self.link_remove_filter_to_filter(id, mix_map[mix]).await?;
+                if let Some(eq) = eq {
+                    self.link_remove_filter_to_filter(eq, mix_map[mix]).await?;
+                }
 
                 // Remove all links from this Mix to all defined outputs
                 self.remove_routes(id, mix_map[mix]).await?;
@@ -497,6 +912,10 @@ impl NodeManagementLocal for PipewireManager {
             }
         }
 
+        if let Some(eq) = eq {
+            self.filter_remove(eq).await?;
+        }
+
         // Remove the Base pass through filter from the tree
         self.filter_remove(id).await?;
 
@@ -527,11 +946,17 @@ impl NodeManagementLocal for PipewireManager {
     async fn node_remove_virtual_source(&mut self, id: Ulid, profile_remove: bool) -> Result<()> {
         // Virtual Sources are a little easier, still a bit of a repeat from the above
         // in places, but we don't have to deal with Unmanaged sources, and our node
-        // connects directly to the Mix A / B volume filters
+        // connects via the EQ to the Mix A / B volume filters
+        let eq = self.eq_map.remove(&id);
+        if let Some(eq) = eq {
+            self.link_remove_node_to_filter(id, eq).await?;
+        }
         if let Some(mix_map) = self.source_map.get(&id) {
             let mix_map = *mix_map;
             for mix in Mix::iter() {
-                self.link_remove_node_to_filter(id, mix_map[mix]).await?;
+                if let Some(eq) = eq {
+                    self.link_remove_filter_to_filter(eq, mix_map[mix]).await?;
+                }
 
                 // Remove all links from this Mix to all defined outputs
                 self.remove_routes(id, mix_map[mix]).await?;
@@ -550,6 +975,10 @@ impl NodeManagementLocal for PipewireManager {
             self.meter_map.remove(&id);
         }
 
+        if let Some(eq) = eq {
+            self.filter_remove(eq).await?;
+        }
+
         // Remove the Node from the Pipewire tree
         self.node_pw_remove(id).await?;
 
@@ -716,34 +1145,6 @@ impl NodeManagementLocal for PipewireManager {
         Ok(())
     }
 
-    fn create_node_props(&self, class: MediaClass, desc: &DeviceDescription) -> NodeProperties {
-        let volume = self.get_node_volume(desc.id, Mix::A).unwrap();
-
-        let managed_volume = matches!(class, MediaClass::Sink);
-
-        let identifier = format!("{} {}", APP_NAME, desc.name)
-            .to_lowercase()
-            .replace(" ", "_");
-
-        let buffer = self.profile.audio_node_quantum.map(|buffer| buffer.into());
-
-        NodeProperties {
-            node_id: desc.id,
-            node_name: identifier.clone(),
-            node_nick: identifier,
-            node_description: format!("{} {}", APP_NAME, desc.name),
-            initial_volume: volume,
-            app_id: APP_ID.to_string(),
-            app_name: APP_NAME.to_lowercase(),
-            linger: false,
-            class,
-            managed_volume,
-            buffer,
-            rate: self.clock_rate.unwrap_or(48000),
-            ready_sender: None,
-        }
-    }
-
     fn get_device_order_group(&mut self, id: Ulid) -> Result<&mut GroupList> {
         if let Some(node_type) = self.get_node_type(id) {
             let device_order = match node_type {
@@ -771,67 +1172,179 @@ impl NodeManagementLocal for PipewireManager {
     fn get_colour(&self, name: String) -> Colour {
         // This is probably unhelpful in most use cases, but here are some default
         // colours for what people would have as potential default devices.
-        match name.as_str() {
-            "Microphone" => Colour {
+        let named = match name.as_str() {
+            "Microphone" => Some(Colour {
                 red: 47,
                 green: 24,
                 blue: 71,
-            },
-            "PC Line In" => Colour {
+            }),
+            "PC Line In" => Some(Colour {
                 red: 98,
                 green: 17,
                 blue: 99,
-            },
-            "System" => Colour {
+            }),
+            "System" => Some(Colour {
                 red: 153,
                 green: 98,
                 blue: 30,
-            },
-            "Browser" => Colour {
+            }),
+            "Browser" => Some(Colour {
                 red: 211,
                 green: 139,
                 blue: 93,
-            },
-            "Game" => Colour {
+            }),
+            "Game" => Some(Colour {
                 red: 243,
                 green: 255,
                 blue: 182,
-            },
-            "Music" => Colour {
+            }),
+            "Music" => Some(Colour {
                 red: 115,
                 green: 158,
                 blue: 130,
-            },
-            "Chat" => Colour {
+            }),
+            "Chat" => Some(Colour {
                 red: 44,
                 green: 85,
                 blue: 48,
-            },
-            "Headphones" => Colour {
+            }),
+            "Headphones" => Some(Colour {
                 red: 0,
                 green: 255,
                 blue: 255,
-            },
-            "Stream Mix" => Colour {
+            }),
+            "Stream Mix" => Some(Colour {
                 red: 19,
                 green: 64,
                 blue: 116,
-            },
-            "VOD" => Colour {
+            }),
+            "VOD" => Some(Colour {
                 red: 19,
                 green: 49,
                 blue: 92,
-            },
-            "Chat Mic" => Colour {
+            }),
+            "Chat Mic" => Some(Colour {
                 red: 11,
                 green: 37,
                 blue: 69,
-            },
-            _ => Colour {
-                red: 0,
-                green: 255,
-                blue: 255,
-            },
+            }),
+            _ => None,
+        };
+        if let Some(colour) = named {
+            return colour;
         }
+
+        // Anything else takes the next colour from the palette, going by how many nodes there
+        // are, so nodes created one after another don't all end up the same colour.
+        let palette = match self.palette.is_empty() {
+            true => DEFAULT_PALETTE.as_slice(),
+            false => self.palette.as_slice(),
+        };
+
+        let devices = &self.profile.devices;
+        let count = devices.sources.physical_devices.len()
+            + devices.sources.virtual_devices.len()
+            + devices.targets.physical_devices.len()
+            + devices.targets.virtual_devices.len();
+        palette[count % palette.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(ids: &[Ulid]) -> GroupList {
+        let mut order = GroupList::default();
+        order[OrderGroup::Default] = ids.to_vec();
+        order
+    }
+
+    #[test]
+    fn positions_follow_interleaved_group_changes() {
+        let (a, b, c) = (Ulid::new(), Ulid::new(), Ulid::new());
+        let mut order = order(&[a, b, c]);
+
+        order_set_group(&mut order, b, OrderGroup::Pinned).unwrap();
+        assert_eq!(order[OrderGroup::Pinned], vec![b]);
+        assert_eq!(order[OrderGroup::Default], vec![a, c]);
+
+        // Sent by a client which hadn't seen b move yet, so it's dropped
+        order_set_position(&mut order, b, Some(OrderGroup::Default), 1).unwrap();
+        assert_eq!(order[OrderGroup::Pinned], vec![b]);
+        assert_eq!(order[OrderGroup::Default], vec![a, c]);
+
+        order_set_position(&mut order, c, Some(OrderGroup::Default), 0).unwrap();
+        assert_eq!(order[OrderGroup::Default], vec![c, a]);
+
+        order_set_group(&mut order, a, OrderGroup::Pinned).unwrap();
+        order_set_position(&mut order, a, Some(OrderGroup::Pinned), 5).unwrap();
+        assert_eq!(order[OrderGroup::Pinned], vec![b, a]);
+        assert_eq!(order[OrderGroup::Default], vec![c]);
+
+        order_set_position(&mut order, a, Some(OrderGroup::Default), 0).unwrap();
+        assert_eq!(order[OrderGroup::Pinned], vec![b, a]);
+
+        order_set_group(&mut order, a, OrderGroup::Default).unwrap();
+        order_set_position(&mut order, c, None, 0).unwrap();
+        assert_eq!(order[OrderGroup::Pinned], vec![b]);
+        assert_eq!(order[OrderGroup::Default], vec![c, a]);
+        assert!(order[OrderGroup::Hidden].is_empty());
+    }
+
+    #[test]
+    fn unknown_nodes_are_rejected() {
+        let mut order = order(&[Ulid::new()]);
+        let missing = Ulid::new();
+
+        assert!(order_set_group(&mut order, missing, OrderGroup::Hidden).is_err());
+        assert!(order_set_position(&mut order, missing, None, 0).is_err());
+        assert!(order[OrderGroup::Hidden].is_empty());
+    }
+
+    #[test]
+    fn identifiers_keep_plain_names() {
+        assert_eq!(
+            sanitise_identifier("pipeweaver", "Chat Mic"),
+            "pipeweaver_chat_mic"
+        );
+        assert_eq!(
+            sanitise_identifier("pipeweaver", "Game-Audio_2"),
+            "pipeweaver_game-audio_2"
+        );
+    }
+
+    #[test]
+    fn identifiers_replace_punctuation() {
+        assert_eq!(
+            sanitise_identifier("pipeweaver", "Music: Spotify/Web.App"),
+            "pipeweaver_music__spotify_web_app"
+        );
+        assert_eq!(
+            sanitise_identifier("pipeweaver", "\"Quoted\" $name"),
+            "pipeweaver_quoted__name"
+        );
+    }
+
+    #[test]
+    fn identifiers_replace_emoji() {
+        assert_eq!(
+            sanitise_identifier("pipeweaver", "Music 🎵🎶"),
+            "pipeweaver_music_"
+        );
+        assert_eq!(
+            sanitise_identifier("pipeweaver", "🎮Game"),
+            "pipeweaver_game"
+        );
+    }
+
+    #[test]
+    fn identifiers_replace_non_latin_scripts() {
+        assert_eq!(sanitise_identifier("pipeweaver", "Микрофон"), "pipeweaver_");
+        assert_eq!(
+            sanitise_identifier("pipeweaver", "マイク Mic"),
+            "pipeweaver__mic"
+        );
+        assert_eq!(sanitise_identifier("pipeweaver", "Café"), "pipeweaver_caf_");
     }
 }
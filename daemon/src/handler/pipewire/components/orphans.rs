@@ -0,0 +1,110 @@
+use crate::handler::pipewire::components::node::sanitise_identifier;
+use crate::handler::pipewire::manager::PipewireManager;
+use anyhow::Result;
+use log::{info, warn};
+use pipeweaver_pipewire::{DeviceNode, NamingScheme, PipewireMessage};
+
+/// If the daemon previously crashed, nodes which were created by it may still be hanging
+/// around in Pipewire. As they're no longer owned by us they show up as regular devices, but
+/// are still named using our naming scheme and placed in our node group, so we can find and
+/// remove them.
+pub(crate) trait OrphanManagement {
+    fn orphans_find(&self) -> Vec<u32>;
+    async fn orphans_cleanup(&mut self) -> Result<usize>;
+}
+
+impl OrphanManagement for PipewireManager {
+    fn orphans_find(&self) -> Vec<u32> {
+        self.device_nodes
+            .values()
+            .filter(|node| is_orphan(node, &self.naming))
+            .map(|node| node.node_id)
+            .collect()
+    }
+
+    async fn orphans_cleanup(&mut self) -> Result<usize> {
+        let mut removed = 0;
+        for id in self.orphans_find() {
+            let name = self.device_nodes.get(&id).and_then(|n| n.name.clone());
+
+            let message = PipewireMessage::DestroyUnmanagedNode(id);
+            match self.pipewire().send_message(message) {
+                Ok(()) => {
+                    info!("[Orphans] Removed Orphaned Node {} ({:?})", id, name);
+                    removed += 1;
+                }
+                Err(e) => warn!("[Orphans] Unable to Remove Node {}: {}", id, e),
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+/// The app name is configurable, so a matching name alone could be a real device (eg. an app
+/// name of 'alsa'), a node is only ours if it's also in our node group.
+fn is_orphan(node: &DeviceNode, naming: &NamingScheme) -> bool {
+    // Every node name starts with the sanitised app name, followed by an underscore
+    let prefix = sanitise_identifier(&naming.app_name, "");
+    if !node.name.as_ref().is_some_and(|n| n.starts_with(&prefix)) {
+        return false;
+    }
+
+    match &node.group {
+        Some(group) => *group == naming.node_group(),
+        None => {
+            warn!(
+                "[Orphans] Node {} ({:?}) matches our naming but has no group, skipping",
+                node.node_id, node.name
+            );
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipeweaver_pipewire::MediaClass;
+
+    fn naming() -> NamingScheme {
+        NamingScheme {
+            app_id: String::from("io.github.pipeweaver"),
+            app_name: String::from("alsa"),
+            group_prefix: String::from("pipeweaver"),
+        }
+    }
+
+    fn node(name: &str, group: Option<String>) -> DeviceNode {
+        DeviceNode {
+            node_id: 1,
+            node_class: MediaClass::Sink,
+            is_usable: true,
+            name: Some(String::from(name)),
+            nickname: None,
+            description: None,
+            group,
+            volume: 100,
+            muted: false,
+            ports: Default::default(),
+        }
+    }
+
+    #[test]
+    fn nodes_in_our_group_are_orphans() {
+        let naming = naming();
+        let name = sanitise_identifier(&naming.app_name, "System");
+
+        assert!(is_orphan(&node(&name, Some(naming.node_group())), &naming));
+    }
+
+    #[test]
+    fn nodes_sharing_our_prefix_are_not_orphans() {
+        let naming = naming();
+        let name = sanitise_identifier(&naming.app_name, "card0");
+
+        // A real device whose name happens to match the configured app name
+        assert!(!is_orphan(&node(&name, None), &naming));
+        assert!(!is_orphan(&node(&name, Some(String::from("hw"))), &naming));
+    }
+}
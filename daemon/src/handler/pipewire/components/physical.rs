@@ -1,14 +1,16 @@
+use crate::handler::pipewire::components::filters::FilterManagement;
 use crate::handler::pipewire::components::links::LinkManagement;
 use crate::handler::pipewire::components::node::NodeManagement;
 use crate::handler::pipewire::components::profile::ProfileManagement;
 use crate::handler::pipewire::manager::PipewireManager;
 use crate::handler::primary_worker::WorkerMessage;
 use anyhow::{Result, anyhow, bail};
-use log::debug;
-use pipeweaver_ipc::commands::PhysicalDevice;
-use pipeweaver_pipewire::{DeviceNode, PipewireMessage};
-use pipeweaver_profile::PhysicalDeviceDescriptor;
-use pipeweaver_shared::{DeviceType, MuteState, NodeType};
+use enum_map::enum_map;
+use log::{debug, warn};
+use pipeweaver_ipc::commands::{DevicePort, DevicePorts, PhysicalDevice};
+use pipeweaver_pipewire::{DeviceNode, Direction, LinkPorts, PipewireMessage, oneshot};
+use pipeweaver_profile::{PhysicalDeviceDescriptor, PhysicalSourceDevice};
+use pipeweaver_shared::{ChannelMode, DeviceType, MuteState, NodeType, PortDirection};
 use tokio::sync::mpsc::Sender;
 use ulid::Ulid;
 
@@ -35,11 +37,23 @@ pub(crate) trait PhysicalDevices {
 
     async fn add_device_to_node(&mut self, id: Ulid, node_id: u32) -> Result<()>;
     async fn remove_device_from_node(&mut self, id: Ulid, vec_index: usize) -> Result<()>;
+    async fn set_device_channel_mode(
+        &mut self,
+        id: Ulid,
+        vec_index: usize,
+        mode: Option<ChannelMode>,
+    ) -> Result<()>;
+    async fn split_device(&mut self, id: Ulid, vec_index: usize) -> Result<Ulid>;
 
     async fn set_device_volume(&mut self, node_id: Ulid, volume: u8) -> Result<()>;
     async fn set_device_mute(&mut self, node_id: Ulid, muted: bool) -> Result<()>;
 
+    async fn set_auto_match(&mut self, enabled: bool, sender: Sender<WorkerMessage>) -> Result<()>;
+
     fn locate_node(&self, descriptor: PhysicalDeviceDescriptor) -> Option<&DeviceNode>;
+
+    /// Fetches every port on a device node, including the ones we don't normally expose
+    async fn get_device_ports(&self, node_id: u32) -> Result<DevicePorts>;
 }
 
 impl PhysicalDevices for PipewireManager {
@@ -87,16 +101,18 @@ impl PhysicalDevices for PipewireManager {
         // description checks, the node Names should be valid.
         match node_type {
             NodeType::PhysicalSource => {
-                for device in &self.node_list[DeviceType::Source] {
-                    if !device.is_usable {
-                        continue;
-                    }
+                let usable: Vec<_> = self.node_list[DeviceType::Source]
+                    .iter()
+                    .filter(|device| device.is_usable)
+                    .map(|device| (device.node_id, device.name.clone()))
+                    .collect();
 
+                for (node_id, name) in usable {
                     // Try and match this against our node, check by Name first
                     for paired in &devices {
                         // Check by Name First
-                        if paired.name == device.name {
-                            self.link_create_unmanaged_to_filter(device.node_id, id)
+                        if paired.name == name {
+                            self.source_device_link(node_id, id, paired.channel_mode)
                                 .await?;
                         }
                     }
@@ -129,83 +145,51 @@ impl PhysicalDevices for PipewireManager {
         node: PhysicalDevice,
         sender: Sender<WorkerMessage>,
     ) -> Result<()> {
+        if !self.auto_match {
+            debug!("Auto Match Disabled, not attaching {:?}", node.name);
+            return Ok(());
+        }
+
         // We need to check through our profile to see if we can find this device
         let devices = self.profile.devices.sources.physical_devices.clone();
-        'start: for (dev_i, device) in devices.iter().enumerate() {
-            // Clarification needed first, we go over the 'attached device' list twice, the
-            // first time to match the absolute ALSA defined name, and the second time to
-            // attempt to match the description (Human Readable Name). The main reason we do
-            // this is so if you have two devices with the same description, we don't want to
-            // incorrectly match, or consider the work done, the ALSA match should hopefully catch
-            // them all.
-
-            if let Some(node_name) = &node.name {
-                for (name_i, dev) in device.attached_devices.iter().enumerate() {
-                    if let Some(name) = &dev.name
-                        && name == node_name
-                    {
-                        debug!("Attaching Node {} to {}", node_name, device.description.id);
-
-                        // Got a hit, attach to our filter, and bring it into the tree
-                        self.link_create_unmanaged_to_filter(node.node_id, device.description.id)
-                            .await?;
-
-                        if let Some(devices) = self.physical_source.get_mut(&device.description.id)
-                            && !devices.contains(&node.node_id)
-                        {
-                            devices.push(node.node_id);
-                        }
-
-                        // We'll force upgrade the description regardless, just to ensure the
-                        // node is accurately represented
-                        let mut descriptor = dev.clone();
-                        descriptor.description = node.description.clone();
-
-                        let mut device = device.clone();
-                        device.attached_devices[name_i] = descriptor;
-                        self.profile.devices.sources.physical_devices[dev_i] = device;
-
-                        // Let the Primary Worker know we've changed the config
-                        let _ = sender.send(WorkerMessage::ProfileChanged).await;
-
-                        break 'start;
-                    }
-                }
+        let name = node.name.as_deref();
+        let description = node.description.as_deref();
+
+        for found in source_device_matches(&devices, name, description) {
+            let device = &devices[found.source];
+            let dev = &device.attached_devices[found.attachment];
+            let (node_id, id) = (node.node_id, device.description.id);
+
+            // Got a hit, attach to our filter, and bring it into the tree
+            debug!("Attaching Node {:?} to {}", node.name, id);
+            self.source_device_link(node_id, id, dev.channel_mode)
+                .await?;
+
+            if let Some(devices) = self.physical_source.get_mut(&id)
+                && !devices.contains(&node_id)
+            {
+                devices.push(node_id);
             }
 
-            if let Some(node_desc) = &node.description {
-                for (desc_i, dev) in device.attached_devices.iter().enumerate() {
-                    if let Some(desc) = &dev.description
-                        && desc == node_desc
-                    {
-                        // Firstly, attach the Node
-                        debug!("Attaching Node {} to {}", node_desc, device.description.id);
-                        self.link_create_unmanaged_to_filter(node.node_id, device.description.id)
-                            .await?;
-
-                        if let Some(devices) = self.physical_source.get_mut(&device.description.id)
-                            && !devices.contains(&node.node_id)
-                        {
-                            devices.push(node.node_id);
-                        }
-
-                        debug!("Updating Profile Node to Name: {:?}", node.name);
-
-                        // This is kinda ugly, but due to likely a changed node name, we need to
-                        // update the profile to ensure this now matches the new location for
-                        // future checks. Again, we *WANT* to defer to the name where possible.
-                        let mut descriptor = dev.clone();
-                        descriptor.name = node.name.clone();
+            let mut descriptor = dev.clone();
+            if found.by_name {
+                // We'll force upgrade the description regardless, just to ensure the node is
+                // accurately represented
+                descriptor.description = node.description.clone();
+            } else {
+                // This is kinda ugly, but due to likely a changed node name, we need to update
+                // the profile to ensure this now matches the new location for future checks.
+                // Again, we *WANT* to defer to the name where possible.
+                debug!("Updating Profile Node to Name: {:?}", node.name);
+                descriptor.name = node.name.clone();
+            }
 
-                        let mut device = device.clone();
-                        device.attached_devices[desc_i] = descriptor;
-                        self.profile.devices.sources.physical_devices[dev_i] = device;
-                        let _ = sender.send(WorkerMessage::ProfileChanged).await;
+            let mut device = device.clone();
+            device.attached_devices[found.attachment] = descriptor;
+            self.profile.devices.sources.physical_devices[found.source] = device;
 
-                        break 'start;
-                    }
-                }
-            }
+            // Let the Primary Worker know we've changed the config
+            let _ = sender.send(WorkerMessage::ProfileChanged).await;
         }
 
         Ok(())
@@ -216,6 +200,11 @@ impl PhysicalDevices for PipewireManager {
         node: PhysicalDevice,
         sender: Sender<WorkerMessage>,
     ) -> Result<()> {
+        if !self.auto_match {
+            debug!("Auto Match Disabled, not attaching {:?}", node.name);
+            return Ok(());
+        }
+
         // Same as source node above, so read the comments there :)
         let devices = self.profile.devices.targets.physical_devices.clone();
         'start: for (dev_i, device) in devices.iter().enumerate() {
@@ -388,6 +377,19 @@ impl PhysicalDevices for PipewireManager {
             devs.retain(|id| *id != node_id);
         }
 
+        // Any Mono Sum filters this device was feeding have nothing left to do
+        let sums: Vec<Ulid> = self
+            .mono_sum_filters
+            .iter()
+            .filter(|((device, _), _)| *device == node_id)
+            .map(|(_, sum)| *sum)
+            .collect();
+        self.mono_sum_filters
+            .retain(|(device, _), _| *device != node_id);
+        for sum in sums {
+            self.filter_remove(sum).await?;
+        }
+
         Ok(())
     }
 
@@ -408,17 +410,17 @@ impl PhysicalDevices for PipewireManager {
             // Check each attached device to see if it matches this node_id
             for attached in &device.attached_devices {
                 // Try to locate this attached device in our device_nodes
-                if let Some(pw_node) = self.locate_node(attached.clone())
-                    && pw_node.node_id == node_id
+                if self
+                    .locate_node(attached.clone())
+                    .is_some_and(|pw_node| pw_node.node_id == node_id)
                 {
                     // Found it! Remove the link from this unmanaged node to the filter
                     debug!(
                         "Disconnecting Source Node {} from Filter {}",
                         node_id, device.description.id
                     );
-                    let _ = self
-                        .link_remove_unmanaged_to_filter(node_id, device.description.id)
-                        .await;
+                    let (id, mode) = (device.description.id, attached.channel_mode);
+                    let _ = self.source_device_unlink(node_id, id, mode).await;
                 }
             }
         }
@@ -483,22 +485,27 @@ impl PhysicalDevices for PipewireManager {
                 let new_node = PhysicalDeviceDescriptor {
                     name: node.name.clone(),
                     description: node.description.clone(),
+                    channel_mode: None,
                 };
-                if device.attached_devices.contains(&new_node) {
+
+                // The Channel Mode isn't part of the device's identity, so don't compare it
+                if device.attached_devices.iter().any(|attached| {
+                    attached.name == new_node.name && attached.description == new_node.description
+                }) {
                     bail!("Device is already attached to this node");
                 }
 
                 device.attached_devices.push(new_node.clone());
-                let pw_node = self.locate_node(new_node);
-                if let Some(node) = pw_node {
-                    self.link_create_unmanaged_to_filter(node.node_id, id)
-                        .await?;
+                let pw_node = self.locate_node(new_node).map(|node| node.node_id);
+                if let Some(node_id) = pw_node {
+                    self.source_device_link(node_id, id, None).await?;
                 }
             }
             NodeType::PhysicalTarget => {
                 let new_node = PhysicalDeviceDescriptor {
                     name: node.name.clone(),
                     description: node.description.clone(),
+                    channel_mode: None,
                 };
 
                 // We need to do sync checks, a device can't be attached to two
@@ -545,6 +552,7 @@ impl PhysicalDevices for PipewireManager {
                 let new_node = PhysicalDeviceDescriptor {
                     name: node.name.clone(),
                     description: node.description.clone(),
+                    channel_mode: None,
                 };
 
                 if device.attached_devices.contains(&new_node) {
@@ -575,12 +583,12 @@ impl PhysicalDevices for PipewireManager {
                 }
 
                 let descriptor = device.attached_devices.remove(vec_index);
+                let mode = descriptor.channel_mode;
 
                 // Attempt to locate this node in our list
-                let pw_node = self.locate_node(descriptor);
-                if let Some(node) = pw_node {
-                    self.link_remove_unmanaged_to_filter(node.node_id, id)
-                        .await?;
+                let pw_node = self.locate_node(descriptor).map(|node| node.node_id);
+                if let Some(node_id) = pw_node {
+                    self.source_device_unlink(node_id, id, mode).await?;
                 }
             }
             NodeType::PhysicalTarget => {
@@ -618,6 +626,86 @@ impl PhysicalDevices for PipewireManager {
         Ok(())
     }
 
+    async fn set_device_channel_mode(
+        &mut self,
+        id: Ulid,
+        vec_index: usize,
+        mode: Option<ChannelMode>,
+    ) -> Result<()> {
+        let node_type = self.get_node_type(id).ok_or(anyhow!("Unknown Node"))?;
+        if node_type != NodeType::PhysicalSource {
+            bail!("Channel Modes can only be set on Physical Sources");
+        }
+
+        let error = anyhow!("Unable to Locate Node: {}", id);
+        let device = self.get_physical_source_mut(id).ok_or(error)?;
+        if vec_index >= device.attached_devices.len() {
+            bail!("Invalid Device Index");
+        }
+
+        let old_mode = device.attached_devices[vec_index].channel_mode;
+        if old_mode == mode {
+            return Ok(());
+        }
+        device.attached_devices[vec_index].channel_mode = mode;
+
+        // If the device is present, swap out the links for the new layout
+        let descriptor = device.attached_devices[vec_index].clone();
+        if let Some(node_id) = self.locate_node(descriptor).map(|node| node.node_id) {
+            self.source_device_unlink(node_id, id, old_mode).await?;
+            self.source_device_link(node_id, id, mode).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn split_device(&mut self, id: Ulid, vec_index: usize) -> Result<Ulid> {
+        let node_type = self.get_node_type(id).ok_or(anyhow!("Unknown Node"))?;
+        if node_type != NodeType::PhysicalSource {
+            bail!("Only devices on Physical Sources can be split");
+        }
+
+        let error = anyhow!("Unable to Locate Node: {}", id);
+        let device = self.get_physical_source(id).ok_or(error)?;
+        let Some(descriptor) = device.attached_devices.get(vec_index).cloned() else {
+            bail!("Invalid Device Index");
+        };
+        let name = format!("{} 2", device.description.name);
+
+        // If the device is present, make sure there's actually something to split
+        if let Some(node) = self.locate_node(descriptor.clone())
+            && node.ports[Direction::Out].len() < 2
+        {
+            bail!("Device only has a single channel");
+        }
+
+        // Create a new source for the second channel, this will fail if the name is taken
+        let new_id = self.node_new(NodeType::PhysicalSource, name).await?;
+
+        let original_mode = descriptor.channel_mode;
+        if let Err(e) = self
+            .split_device_attach(id, vec_index, new_id, descriptor)
+            .await
+        {
+            // Don't leave behind a half split device, put things back how they were
+            if let Err(remove) = self.node_remove(new_id).await {
+                warn!(
+                    "Unable to remove Node {} after failing to Split: {}",
+                    new_id, remove
+                );
+            }
+            if let Err(restore) = self
+                .set_device_channel_mode(id, vec_index, original_mode)
+                .await
+            {
+                warn!("Unable to restore Channel Mode on {}: {}", id, restore);
+            }
+            return Err(e);
+        }
+
+        Ok(new_id)
+    }
+
     async fn set_device_volume(&mut self, id: Ulid, volume: u8) -> Result<()> {
         let node = self
             .node_list
@@ -650,6 +738,36 @@ impl PhysicalDevices for PipewireManager {
         }
     }
 
+    async fn set_auto_match(&mut self, enabled: bool, sender: Sender<WorkerMessage>) -> Result<()> {
+        if self.auto_match == enabled {
+            return Ok(());
+        }
+        self.auto_match = enabled;
+        if !enabled {
+            return Ok(());
+        }
+
+        // Run the match again for anything which appeared while we were disabled, skipping
+        // devices which are already attached so we don't rebuild their links.
+        let sources = self.node_list[DeviceType::Source].clone();
+        for device in sources {
+            let mut attached = self.physical_source.values().flatten();
+            if device.is_usable && !attached.any(|id| *id == device.node_id) {
+                self.source_device_added(device, sender.clone()).await?;
+            }
+        }
+
+        let targets = self.node_list[DeviceType::Target].clone();
+        for device in targets {
+            let mut attached = self.physical_target.values().flatten();
+            if device.is_usable && !attached.any(|id| *id == device.node_id) {
+                self.target_device_added(device, sender.clone()).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn locate_node(&self, descriptor: PhysicalDeviceDescriptor) -> Option<&DeviceNode> {
         if let Some(name) = descriptor.name {
             let node = self
@@ -670,9 +788,295 @@ impl PhysicalDevices for PipewireManager {
 
         None
     }
+
+    async fn get_device_ports(&self, node_id: u32) -> Result<DevicePorts> {
+        let (tx, rx) = oneshot::channel();
+        let message = PipewireMessage::GetDevicePorts(node_id, tx);
+        self.pipewire().send_message(message)?;
+        let ports = rx.recv()??;
+
+        let convert = |direction: Direction| {
+            ports[direction]
+                .iter()
+                .map(|port| DevicePort {
+                    port_id: port.port_id,
+                    name: port.name.clone(),
+                    channel: port.channel.clone(),
+                    is_monitor: port.is_monitor,
+                })
+                .collect()
+        };
+
+        Ok(enum_map! {
+            PortDirection::In => convert(Direction::In),
+            PortDirection::Out => convert(Direction::Out),
+        })
+    }
 }
 
-#[allow(unused)]
-trait PhysicalDevicesLocal {}
+trait PhysicalDevicesLocal {
+    async fn split_device_attach(
+        &mut self,
+        id: Ulid,
+        vec_index: usize,
+        new_id: Ulid,
+        descriptor: PhysicalDeviceDescriptor,
+    ) -> Result<()>;
+    fn source_link_ports(&self, node_id: u32, mode: Option<ChannelMode>) -> Vec<Option<LinkPorts>>;
+    async fn source_device_link(
+        &mut self,
+        node_id: u32,
+        id: Ulid,
+        mode: Option<ChannelMode>,
+    ) -> Result<()>;
+    async fn source_device_unlink(
+        &mut self,
+        node_id: u32,
+        id: Ulid,
+        mode: Option<ChannelMode>,
+    ) -> Result<()>;
+}
 
-impl PhysicalDevicesLocal for PipewireManager {}
+impl PhysicalDevicesLocal for PipewireManager {
+    /// Points the existing source at the first channel, and the new one at the second
+    async fn split_device_attach(
+        &mut self,
+        id: Ulid,
+        vec_index: usize,
+        new_id: Ulid,
+        descriptor: PhysicalDeviceDescriptor,
+    ) -> Result<()> {
+        self.set_device_channel_mode(id, vec_index, Some(ChannelMode::MonoFirst))
+            .await?;
+
+        let error = anyhow!("Unable to Locate Node: {}", new_id);
+        let device = self.get_physical_source_mut(new_id).ok_or(error)?;
+        device.attached_devices.push(PhysicalDeviceDescriptor {
+            channel_mode: Some(ChannelMode::MonoSecond),
+            ..descriptor
+        });
+        self.connect_for_node(new_id).await
+    }
+
+    /// Works out which port pairs need linking from a source device to a filter, `None` here
+    /// means defer to the automatic stereo / mono detection.
+    fn source_link_ports(&self, node_id: u32, mode: Option<ChannelMode>) -> Vec<Option<LinkPorts>> {
+        let Some(mode) = mode else {
+            return vec![None];
+        };
+
+        let channels: Vec<String> = self
+            .device_nodes
+            .get(&node_id)
+            .map(|node| {
+                node.ports[Direction::Out]
+                    .iter()
+                    .map(|port| port.channel.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // A single channel device is already sent to both sides, nothing to override
+        if channels.len() < 2 {
+            return vec![None];
+        }
+
+        let first = channels[0].clone();
+        let second = channels[1].clone();
+        match mode {
+            ChannelMode::Stereo => vec![Some(LinkPorts {
+                left: first,
+                right: second,
+            })],
+            ChannelMode::MonoSum => vec![
+                // Pipewire will mix multiple links into a port, so this gives us the sum
+                Some(LinkPorts {
+                    left: first.clone(),
+                    right: first,
+                }),
+                Some(LinkPorts {
+                    left: second.clone(),
+                    right: second,
+                }),
+            ],
+            ChannelMode::MonoFirst => vec![Some(LinkPorts {
+                left: first.clone(),
+                right: first,
+            })],
+            ChannelMode::MonoSecond => vec![Some(LinkPorts {
+                left: second.clone(),
+                right: second,
+            })],
+        }
+    }
+
+    async fn source_device_link(
+        &mut self,
+        node_id: u32,
+        id: Ulid,
+        mode: Option<ChannelMode>,
+    ) -> Result<()> {
+        let ports = self.source_link_ports(node_id, mode);
+
+        // Pipewire adds together everything linked into a port, so a mono sum goes through its
+        // own filter first to bring the level back down
+        if mode == Some(ChannelMode::MonoSum) && ports.iter().all(Option::is_some) {
+            let name = format!("{}-{}-mono-sum", id, node_id);
+            let sum = self.filter_mono_sum_create(name).await?;
+            self.mono_sum_filters.insert((node_id, id), sum);
+
+            for ports in ports.into_iter().flatten() {
+                self.link_create_unmanaged_ports_to_filter(node_id, ports, sum)
+                    .await?;
+            }
+            return self.link_create_filter_to_filter(sum, id).await;
+        }
+
+        for ports in ports {
+            match ports {
+                None => self.link_create_unmanaged_to_filter(node_id, id).await?,
+                Some(ports) => {
+                    self.link_create_unmanaged_ports_to_filter(node_id, ports, id)
+                        .await?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn source_device_unlink(
+        &mut self,
+        node_id: u32,
+        id: Ulid,
+        mode: Option<ChannelMode>,
+    ) -> Result<()> {
+        // Removing the mono sum filter takes all of its links with it
+        if let Some(sum) = self.mono_sum_filters.remove(&(node_id, id)) {
+            return self.filter_remove(sum).await;
+        }
+
+        for ports in self.source_link_ports(node_id, mode) {
+            match ports {
+                None => self.link_remove_unmanaged_to_filter(node_id, id).await?,
+                Some(ports) => {
+                    self.link_remove_unmanaged_ports_to_filter(node_id, ports, id)
+                        .await?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A physical source which a newly added device should be attached to
+#[derive(Debug, PartialEq)]
+struct SourceDeviceMatch {
+    source: usize,
+    attachment: usize,
+    by_name: bool,
+}
+
+/// Finds the sources a new device belongs to. A device feeds a single source, unless it's been
+/// split (see split_device), in which case each source takes one of its channels.
+fn source_device_matches(
+    sources: &[PhysicalSourceDevice],
+    name: Option<&str>,
+    description: Option<&str>,
+) -> Vec<SourceDeviceMatch> {
+    let mut matches = vec![];
+    for (source, device) in sources.iter().enumerate() {
+        // Clarification needed first, we go over the 'attached device' list twice, the first
+        // time to match the absolute ALSA defined name, and the second time to attempt to match
+        // the description (Human Readable Name). The main reason we do this is so if you have
+        // two devices with the same description, we don't want to incorrectly match, or
+        // consider the work done, the ALSA match should hopefully catch them all.
+        let attached = &device.attached_devices;
+        let by_name = attached
+            .iter()
+            .position(|dev| name.is_some() && dev.name.as_deref() == name)
+            .map(|attachment| (attachment, true));
+        let by_description = || {
+            attached
+                .iter()
+                .position(|dev| description.is_some() && dev.description.as_deref() == description)
+                .map(|attachment| (attachment, false))
+        };
+
+        let Some((attachment, by_name)) = by_name.or_else(by_description) else {
+            continue;
+        };
+        matches.push(SourceDeviceMatch {
+            source,
+            attachment,
+            by_name,
+        });
+
+        // Only a single channel leaves the rest of the device free for another source
+        let mode = attached[attachment].channel_mode;
+        if !matches!(mode, Some(ChannelMode::MonoFirst | ChannelMode::MonoSecond)) {
+            break;
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(name: &str, mode: Option<ChannelMode>) -> PhysicalSourceDevice {
+        let mut device = PhysicalSourceDevice::default();
+        device.attached_devices.push(PhysicalDeviceDescriptor {
+            name: Some(String::from(name)),
+            description: Some(String::from("USB Microphone")),
+            channel_mode: mode,
+        });
+        device
+    }
+
+    #[test]
+    fn devices_attach_to_a_single_source() {
+        let sources = vec![source("mic", None), source("mic", None)];
+
+        let matches = source_device_matches(&sources, Some("mic"), Some("USB Microphone"));
+        let first = SourceDeviceMatch {
+            source: 0,
+            attachment: 0,
+            by_name: true,
+        };
+        assert_eq!(matches, vec![first]);
+    }
+
+    #[test]
+    fn split_devices_attach_to_each_channel() {
+        let sources = vec![
+            source("mic", Some(ChannelMode::MonoFirst)),
+            source("mic", Some(ChannelMode::MonoSecond)),
+        ];
+
+        let matches = source_device_matches(&sources, Some("mic"), None);
+        let sources: Vec<usize> = matches.iter().map(|m| m.source).collect();
+        assert_eq!(sources, vec![0, 1]);
+    }
+
+    #[test]
+    fn names_are_preferred_over_descriptions() {
+        let mut sources = vec![source("other", None)];
+        sources[0].attached_devices.insert(
+            0,
+            PhysicalDeviceDescriptor {
+                name: Some(String::from("old")),
+                description: Some(String::from("USB Microphone")),
+                channel_mode: None,
+            },
+        );
+
+        let matches = source_device_matches(&sources, Some("other"), Some("USB Microphone"));
+        assert_eq!(matches[0].attachment, 1);
+        assert!(matches[0].by_name);
+
+        let matches = source_device_matches(&sources, Some("new"), Some("USB Microphone"));
+        assert_eq!(matches[0].attachment, 0);
+        assert!(!matches[0].by_name);
+    }
+}
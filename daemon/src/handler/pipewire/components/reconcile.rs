@@ -0,0 +1,143 @@
+use crate::handler::pipewire::components::links::LinkManagement;
+use crate::handler::pipewire::components::mute::MuteManager;
+use crate::handler::pipewire::components::node::NodeManagement;
+use crate::handler::pipewire::components::routing::RoutingManagement;
+use crate::handler::pipewire::manager::PipewireManager;
+use anyhow::Result;
+use log::{info, warn};
+use pipeweaver_ipc::commands::{LinkReport, RouteLink};
+use pipeweaver_pipewire::{LinkType, ManagedLink, PipewireMessage, oneshot};
+use pipeweaver_shared::{Mix, NodeType};
+use std::collections::HashMap;
+use strum::IntoEnumIterator;
+use ulid::Ulid;
+
+/// Brings the links between the Source Mixes and their Targets back in line with the routing
+/// table, for when a failed operation (or something outside the daemon) has left them out of
+/// step. Only links leaving a Mix are considered, everything else is owned by its node.
+pub(crate) trait LinkReconciliation {
+    async fn reconcile_links(&mut self) -> Result<LinkReport>;
+}
+
+impl LinkReconciliation for PipewireManager {
+    async fn reconcile_links(&mut self) -> Result<LinkReport> {
+        let mut existing = self.reconcile_existing_routes().await?;
+        let mut report = LinkReport::default();
+
+        let routes = self.profile.routes.clone();
+        for (source, targets) in routes {
+            if !self.source_map.contains_key(&source) {
+                continue;
+            }
+
+            for target in targets {
+                // Muted routes are unlinked on purpose, and missing targets can't be linked
+                if self.get_node_type(target).is_none()
+                    || self.is_source_muted_to_some(source, target).await?
+                {
+                    continue;
+                }
+
+                let mix = self.routing_get_target_mix(&target).await?;
+                let route = RouteLink {
+                    source,
+                    target,
+                    mix,
+                };
+                match existing.remove(&route) {
+                    Some(link) if link.connected => {}
+                    Some(link) => {
+                        warn!(
+                            "[Links] Route {} -> {} not Connected, Rebuilding",
+                            source, target
+                        );
+                        self.link_remove_type_to_type(link.source, link.destination)
+                            .await?;
+                        self.reconcile_create(route).await?;
+                        report.repaired.push(route);
+                    }
+                    None => {
+                        warn!("[Links] Route {} -> {} Missing, Creating", source, target);
+                        self.reconcile_create(route).await?;
+                        report.created.push(route);
+                    }
+                }
+            }
+        }
+
+        // Anything left over doesn't belong to a route (or belongs to one on the other Mix)
+        for (route, link) in existing {
+            warn!(
+                "[Links] Link {} -> {} has no Route, Removing",
+                route.source, route.target
+            );
+            self.link_remove_type_to_type(link.source, link.destination)
+                .await?;
+            report.removed.push(route);
+        }
+
+        info!(
+            "[Links] Reconciled: {} Created, {} Repaired, {} Removed",
+            report.created.len(),
+            report.repaired.len(),
+            report.removed.len()
+        );
+        Ok(report)
+    }
+}
+
+trait LinkReconciliationLocal {
+    /// Every link currently leaving a Mix, by the route it represents
+    async fn reconcile_existing_routes(&self) -> Result<HashMap<RouteLink, ManagedLink>>;
+    async fn reconcile_create(&self, route: RouteLink) -> Result<()>;
+}
+
+impl LinkReconciliationLocal for PipewireManager {
+    async fn reconcile_existing_routes(&self) -> Result<HashMap<RouteLink, ManagedLink>> {
+        let (tx, rx) = oneshot::channel();
+        self.pipewire()
+            .send_message(PipewireMessage::GetManagedLinks(tx))?;
+        let links: Vec<ManagedLink> = rx.recv()??;
+
+        // Work back from each Mix filter to the Source and Mix it belongs to
+        let mixes: HashMap<Ulid, (Ulid, Mix)> = self
+            .source_map
+            .iter()
+            .flat_map(|(source, map)| Mix::iter().map(move |mix| (map[mix], (*source, mix))))
+            .collect();
+
+        let mut routes = HashMap::new();
+        for link in links {
+            let LinkType::Filter(filter) = &link.source else {
+                continue;
+            };
+            let Some(&(source, mix)) = mixes.get(filter) else {
+                continue;
+            };
+            let target = match &link.destination {
+                LinkType::Node(id) | LinkType::NodeChannelPair(id, _) | LinkType::Filter(id) => *id,
+                LinkType::UnmanagedNode(..) => continue,
+            };
+            routes.insert(
+                RouteLink {
+                    source,
+                    target,
+                    mix,
+                },
+                link,
+            );
+        }
+        Ok(routes)
+    }
+
+    async fn reconcile_create(&self, route: RouteLink) -> Result<()> {
+        let map = self.source_map[&route.source];
+        if self.get_node_type(route.target) == Some(NodeType::VirtualTarget) {
+            self.link_create_filter_to_node(map[route.mix], route.target)
+                .await
+        } else {
+            self.link_create_filter_to_filter(map[route.mix], route.target)
+                .await
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::handler::pipewire::components::mute::MuteManager;
+use crate::handler::pipewire::components::profile::ProfileManagement;
+use crate::handler::pipewire::components::routing::RoutingManagement;
+use crate::handler::pipewire::components::volume::VolumeManager;
+use crate::handler::pipewire::manager::PipewireManager;
+use anyhow::Result;
+use log::{debug, info};
+use pipeweaver_ipc::commands::VolumeSnapshot;
+use pipeweaver_profile::{MuteStates, Profile};
+use pipeweaver_shared::{MuteState, MuteTarget};
+use std::collections::HashSet;
+use strum::IntoEnumIterator;
+use ulid::Ulid;
+
+/// Puts the running setup back to an earlier profile without restarting the manager, used to
+/// undo a failed transaction. Only routes, mixes, mutes and volumes can be restored like this,
+/// returns false if anything else differs and the manager needs to reload the profile instead.
+pub(crate) trait ProfileRestore {
+    async fn restore_profile(&mut self, profile: Profile) -> Result<bool>;
+}
+
+impl ProfileRestore for PipewireManager {
+    async fn restore_profile(&mut self, profile: Profile) -> Result<bool> {
+        if restore_key(&self.profile)? != restore_key(&profile)? {
+            debug!("[Restore] Profile Structure has changed, unable to restore in place");
+            return Ok(false);
+        }
+
+        info!("[Restore] Restoring Profile in place");
+        self.restore_routes(&profile).await?;
+        self.restore_mutes(&profile).await?;
+        self.restore_mixes(&profile).await?;
+        self.restore_volumes(restore_volume_snapshot(&profile))
+            .await?;
+        Ok(true)
+    }
+}
+
+trait ProfileRestoreLocal {
+    async fn restore_routes(&mut self, profile: &Profile) -> Result<()>;
+    async fn restore_mutes(&mut self, profile: &Profile) -> Result<()>;
+    async fn restore_source_mutes(&mut self, id: Ulid, states: &MuteStates) -> Result<()>;
+    async fn restore_mixes(&mut self, profile: &Profile) -> Result<()>;
+}
+
+impl ProfileRestoreLocal for PipewireManager {
+    async fn restore_routes(&mut self, profile: &Profile) -> Result<()> {
+        let sources: HashSet<Ulid> = profile
+            .routes
+            .keys()
+            .chain(self.profile.routes.keys())
+            .copied()
+            .collect();
+
+        for source in sources {
+            let targets = profile.routes.get(&source).cloned().unwrap_or_default();
+            let current = self
+                .profile
+                .routes
+                .get(&source)
+                .cloned()
+                .unwrap_or_default();
+            for target in current.difference(&targets) {
+                self.routing_set_route(source, *target, false).await?;
+            }
+            for target in targets.difference(&current) {
+                self.routing_set_route(source, *target, true).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn restore_mutes(&mut self, profile: &Profile) -> Result<()> {
+        let sources = &profile.devices.sources;
+        let physical = sources.physical_devices.iter();
+        let physical = physical.map(|d| (d.description.id, &d.mute_states));
+        let virtual_sources = sources.virtual_devices.iter();
+        let virtual_sources = virtual_sources.map(|d| (d.description.id, &d.mute_states));
+        for (id, states) in physical.chain(virtual_sources) {
+            self.restore_source_mutes(id, states).await?;
+        }
+
+        let targets = &profile.devices.targets;
+        let physical = targets.physical_devices.iter();
+        let physical = physical.map(|d| (d.description.id, d.mute_state));
+        let virtual_targets = targets.virtual_devices.iter();
+        let virtual_targets = virtual_targets.map(|d| (d.description.id, d.mute_state));
+        for (id, state) in physical.chain(virtual_targets) {
+            if self.get_target_mute_state(id).await? != state {
+                self.set_target_mute_state(id, state).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn restore_source_mutes(&mut self, id: Ulid, states: &MuteStates) -> Result<()> {
+        let current = match self.get_physical_source(id) {
+            Some(device) => device.mute_states.clone(),
+            None => match self.get_virtual_source(id) {
+                Some(device) => device.mute_states.clone(),
+                None => return Ok(()),
+            },
+        };
+
+        // Changing the targets unmutes the Mute Target, so these go before the states
+        for (mute, targets) in &states.mute_targets {
+            for target in current.mute_targets[mute].difference(targets) {
+                self.del_target_mute_node(id, mute, *target).await?;
+            }
+            for target in targets.difference(&current.mute_targets[mute]) {
+                self.add_target_mute_node(id, mute, *target).await?;
+            }
+        }
+        for mute in MuteTarget::iter() {
+            let state = match states.mute_state.contains(&mute) {
+                true => MuteState::Muted,
+                false => MuteState::Unmuted,
+            };
+            self.set_source_mute_state(id, mute, state).await?;
+        }
+        Ok(())
+    }
+
+    async fn restore_mixes(&mut self, profile: &Profile) -> Result<()> {
+        let targets = &profile.devices.targets;
+        let physical = targets.physical_devices.iter();
+        let physical = physical.map(|d| (d.description.id, d.mix));
+        let virtual_targets = targets.virtual_devices.iter();
+        let virtual_targets = virtual_targets.map(|d| (d.description.id, d.mix));
+
+        let mixes: Vec<_> = physical.chain(virtual_targets).collect();
+        for (id, mix) in mixes {
+            let current = match self.get_physical_target(id) {
+                Some(device) => device.mix,
+                None => match self.get_virtual_target(id) {
+                    Some(device) => device.mix,
+                    None => continue,
+                },
+            };
+            if current != mix {
+                self.routing_set_target_mix(id, mix).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The profile with everything restore_profile can put back cleared, if these match between
+/// two profiles, one can be restored over the other in place.
+fn restore_key(profile: &Profile) -> Result<serde_json::Value> {
+    let mut profile = profile.clone();
+    profile.routes.clear();
+
+    let sources = &mut profile.devices.sources;
+    for device in &mut sources.physical_devices {
+        device.mute_states.mute_state.clear();
+        device.mute_states.mute_targets = Default::default();
+        device.volumes = Default::default();
+        device.record_armed = false;
+    }
+    for device in &mut sources.virtual_devices {
+        device.mute_states.mute_state.clear();
+        device.mute_states.mute_targets = Default::default();
+        device.volumes = Default::default();
+        device.record_armed = false;
+    }
+
+    let targets = &mut profile.devices.targets;
+    for device in &mut targets.physical_devices {
+        device.mute_state = Default::default();
+        device.mix = Default::default();
+        device.volume = 0;
+    }
+    for device in &mut targets.virtual_devices {
+        device.mute_state = Default::default();
+        device.mix = Default::default();
+        device.volume = 0;
+    }
+    Ok(serde_json::to_value(profile)?)
+}
+
+fn restore_volume_snapshot(profile: &Profile) -> VolumeSnapshot {
+    let mut snapshot = VolumeSnapshot::default();
+
+    let sources = &profile.devices.sources;
+    for device in &sources.physical_devices {
+        let volumes = device.volumes.clone();
+        snapshot.sources.insert(device.description.id, volumes);
+    }
+    for device in &sources.virtual_devices {
+        let volumes = device.volumes.clone();
+        snapshot.sources.insert(device.description.id, volumes);
+    }
+
+    let targets = &profile.devices.targets;
+    for device in &targets.physical_devices {
+        snapshot
+            .targets
+            .insert(device.description.id, device.volume);
+    }
+    for device in &targets.virtual_devices {
+        snapshot
+            .targets
+            .insert(device.description.id, device.volume);
+    }
+    snapshot
+}
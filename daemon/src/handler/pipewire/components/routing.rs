@@ -2,12 +2,33 @@ use crate::handler::pipewire::components::links::LinkManagement;
 use crate::handler::pipewire::components::mute::MuteManager;
 use crate::handler::pipewire::components::node::NodeManagement;
 use crate::handler::pipewire::components::profile::ProfileManagement;
+use crate::handler::pipewire::components::test_tone::TestToneManagement;
+use crate::handler::pipewire::components::volume::VolumeManager;
 use crate::handler::pipewire::manager::PipewireManager;
+use crate::handler::primary_worker::WorkerMessage;
 use anyhow::{Result, anyhow, bail};
 use log::{debug, warn};
 use pipeweaver_shared::{Mix, NodeType};
+use std::collections::HashSet;
+use std::time::Duration;
+use strum::IntoEnumIterator;
+use tokio::time::Instant;
 use ulid::Ulid;
 
+const PREVIEW_FADE_STEPS: u8 = 5;
+const PREVIEW_FADE_STEP: Duration = Duration::from_millis(10);
+
+/// A fade out and back in around a Mix Preview switching, stepped from the main loop
+pub(crate) struct MixFade {
+    target: Ulid,
+
+    // The preview to switch to at the bottom of the fade, None to return to the profile mix
+    preview: Option<Mix>,
+    step: u8,
+    rising: bool,
+    next: Instant,
+}
+
 pub(crate) trait RoutingManagement {
     async fn routing_load(&mut self) -> Result<()>;
     async fn routing_load_source(&mut self, source: &Ulid) -> Result<()>;
@@ -16,9 +37,40 @@ pub(crate) trait RoutingManagement {
 
     async fn routing_set_route(&mut self, source: Ulid, target: Ulid, enabled: bool) -> Result<()>;
     async fn routing_route_exists(&self, source: Ulid, target: Ulid) -> Result<bool>;
+    async fn routing_set_channel_pair(
+        &mut self,
+        source: Ulid,
+        target: Ulid,
+        pair: u8,
+    ) -> Result<()>;
 
     async fn routing_get_target_mix(&self, id: &Ulid) -> Result<Mix>;
     async fn routing_set_target_mix(&mut self, target: Ulid, mix: Mix) -> Result<()>;
+
+    async fn routing_set_monitor_target(&mut self, target: Option<Ulid>) -> Result<()>;
+    async fn routing_auto_monitor(&mut self, source: Ulid) -> Result<()>;
+
+    /// Sets the Target armed sources are routed to, moving any armed routes across
+    async fn routing_set_recorder_target(&mut self, target: Option<Ulid>) -> Result<()>;
+    async fn routing_set_record_arm(&mut self, source: Ulid, armed: bool) -> Result<()>;
+
+    async fn routing_preview_mix(&mut self, mix: Mix, active: bool) -> Result<()>;
+
+    /// When routing_fade_tick next needs to run, if at all
+    fn routing_fade_deadline(&self) -> Option<Instant>;
+    async fn routing_fade_tick(&mut self);
+
+    async fn routing_set_idle_unrouted(&mut self, enabled: bool) -> Result<()>;
+    async fn routing_update_idle(&mut self, source: Ulid) -> Result<()>;
+
+    /// Creates a Virtual Source which is fed by the output of a Target
+    async fn routing_create_tap_source(&mut self, target: Ulid, name: String) -> Result<Ulid>;
+
+    /// Links any taps this node is part of, either as the tap or as the tapped Target
+    async fn routing_load_taps(&mut self, id: Ulid) -> Result<()>;
+
+    /// Unlinks any taps this node is part of, so the links aren't re-established once dropped
+    async fn routing_unload_taps(&mut self, id: Ulid) -> Result<()>;
 }
 
 impl RoutingManagement for PipewireManager {
@@ -27,11 +79,42 @@ impl RoutingManagement for PipewireManager {
         // and establish links between the sources and targets
         debug!("Loading Routing..");
 
+        // A tap routed back into a Target that feeds it, directly or through other taps,
+        // would feed back on itself. Drop the routes which close a loop, and say which.
+        let taps: Vec<(Ulid, Ulid)> = self.routing_taps().collect();
+        for (source, target) in taps {
+            let routes: Vec<Ulid> = self
+                .profile
+                .routes
+                .get(&source)
+                .map(|r| r.iter().copied().collect())
+                .unwrap_or_default();
+            for route in routes {
+                if self.routing_feeds(route, source) {
+                    warn!(
+                        "[Routing] Removing route from Tap {} to {}, it feeds back into the Tap",
+                        source, route
+                    );
+                    if let Some(routes) = self.profile.routes.get_mut(&source) {
+                        routes.remove(&route);
+                    }
+                }
+            }
+            debug!("[Routing] Loading Tap of {} into {}", target, source);
+            self.routing_load_taps(source).await?;
+        }
+
         let routing = &self.profile.routes.clone();
         for source in routing.keys() {
             self.routing_load_source(source).await?;
         }
 
+        // Disconnect anything that's not going anywhere
+        let sources: Vec<Ulid> = self.source_map.keys().copied().collect();
+        for source in sources {
+            self.routing_update_idle(source).await?;
+        }
+
         Ok(())
     }
 
@@ -106,6 +189,9 @@ impl RoutingManagement for PipewireManager {
         if enabled == exists {
             bail!("Requested route change already set");
         }
+        if enabled && self.routing_feeds(target, source) {
+            bail!("A Tap cannot be routed to a Target which feeds it");
+        }
         if enabled {
             route.insert(target);
             self.handle_source_effective_mute(source).await?;
@@ -114,6 +200,14 @@ impl RoutingManagement for PipewireManager {
             self.handle_source_effective_mute(source).await?;
         }
 
+        // A route to the Recorder is what arming a source means, so keep the two in step
+        if self.profile.recorder_target == Some(target) {
+            self.routing_set_armed_flag(source, enabled);
+        }
+
+        // This may be the first, or last route for the source, so wake it up or idle it
+        self.routing_update_idle(source).await?;
+
         // Next, we need to get the A/B IDs for the Source
         let target_type = self.get_node_type(target).unwrap();
         if let Some(map) = self.source_map.get(&source).copied() {
@@ -175,6 +269,52 @@ impl RoutingManagement for PipewireManager {
             .is_some_and(|targets| targets.contains(&target)))
     }
 
+    async fn routing_set_channel_pair(
+        &mut self,
+        source: Ulid,
+        target: Ulid,
+        pair: u8,
+    ) -> Result<()> {
+        if self.get_node_type(target) != Some(NodeType::VirtualTarget) {
+            bail!("Channel pairs can only be set on Virtual Targets");
+        }
+
+        let err = anyhow!("Unable to Locate Target");
+        let node = self.get_virtual_target(target).ok_or(err)?;
+        let pairs = node.channels.unwrap_or(2) / 2;
+        if pair >= pairs {
+            bail!("Target only has {} channel pairs", pairs);
+        }
+
+        let current = node.channel_routes.get(&source).copied().unwrap_or(0);
+        if current == pair {
+            return Ok(());
+        }
+
+        // If the route is currently linked, it needs to be removed using the old channel pair
+        let linked = self.routing_route_exists(source, target).await?
+            && !self.is_source_muted_to_some(source, target).await?;
+        let map = self.source_map.get(&source).copied();
+        let mix = self.routing_get_target_mix(&target).await?;
+
+        if linked && let Some(map) = map {
+            self.link_remove_filter_to_node(map[mix], target).await?;
+        }
+
+        let err = anyhow!("Unable to Locate Target");
+        let node = self.get_virtual_target_mut(target).ok_or(err)?;
+        if pair == 0 {
+            node.channel_routes.remove(&source);
+        } else {
+            node.channel_routes.insert(source, pair);
+        }
+
+        if linked && let Some(map) = map {
+            self.link_create_filter_to_node(map[mix], target).await?;
+        }
+        Ok(())
+    }
+
     async fn routing_get_target_mix(&self, id: &Ulid) -> Result<Mix> {
         let error = anyhow!("Cannot Locate Node");
         let node_type = self.get_node_type(*id).ok_or(error)?;
@@ -185,6 +325,13 @@ impl RoutingManagement for PipewireManager {
             bail!("Provided Target is a Source Node");
         }
 
+        // A held preview takes the place of the stored mix until it's released
+        if let Some((target, mix)) = self.mix_preview
+            && target == *id
+        {
+            return Ok(mix);
+        }
+
         let err = anyhow!("Failed to Locate Target");
         let mix = if node_type == NodeType::PhysicalTarget {
             self.get_physical_target(*id).ok_or(err)?.mix
@@ -195,10 +342,26 @@ impl RoutingManagement for PipewireManager {
     }
 
     async fn routing_set_target_mix(&mut self, target: Ulid, mix: Mix) -> Result<()> {
+        // Setting the mix ends any preview held on this target
+        let previewed = self.mix_preview.is_some_and(|(id, _)| id == target);
+        if self
+            .mix_fade
+            .as_ref()
+            .is_some_and(|fade| fade.target == target)
+        {
+            self.routing_finish_fade().await?;
+        }
+        if previewed {
+            self.routing_switch_preview(target, None).await?;
+        }
+
         let current = self.routing_get_target_mix(&target).await?;
 
         // Ok, first thing's first, lets see if this is actually changed
         if current == mix {
+            if previewed {
+                return Ok(());
+            }
             bail!("Nothing to Do, Mixes Match");
         }
 
@@ -213,6 +376,363 @@ impl RoutingManagement for PipewireManager {
 
         //let target_node = self.get_target_filter_node(target)?;
 
+        self.routing_relink_target_mix(target, current, mix).await?;
+
+        // Update the Profile
+        if node_type == NodeType::PhysicalTarget {
+            self.get_physical_target_mut(target)
+                .ok_or(anyhow!("Unknown Node"))?
+                .mix = mix;
+        } else {
+            self.get_virtual_target_mut(target)
+                .ok_or(anyhow!("Unknown Node"))?
+                .mix = mix;
+        }
+
+        // A Test Tone limited to a Mix should follow it
+        self.test_tone_sync(target).await?;
+
+        // Changing the mix may trigger (or lift) an automatic mute
+        self.apply_auto_mute_rules().await?;
+        Ok(())
+    }
+
+    async fn routing_set_monitor_target(&mut self, target: Option<Ulid>) -> Result<()> {
+        if let Some(target) = target
+            && !matches!(
+                self.get_node_type(target),
+                Some(NodeType::PhysicalTarget) | Some(NodeType::VirtualTarget)
+            )
+        {
+            bail!("Monitor Target must be a Target");
+        }
+
+        self.profile.monitor_target = target;
+        Ok(())
+    }
+
+    async fn routing_auto_monitor(&mut self, source: Ulid) -> Result<()> {
+        let Some(target) = self.profile.monitor_target else {
+            bail!("No Monitor Target has been set");
+        };
+
+        // Go through the normal route path, so it's validated the same as a manual change
+        debug!(
+            "[Routing] Routing new Source {} to Monitor {}",
+            source, target
+        );
+        self.routing_set_route(source, target, true).await
+    }
+
+    async fn routing_set_recorder_target(&mut self, target: Option<Ulid>) -> Result<()> {
+        if let Some(target) = target
+            && !matches!(
+                self.get_node_type(target),
+                Some(NodeType::PhysicalTarget) | Some(NodeType::VirtualTarget)
+            )
+        {
+            bail!("Recorder Target must be a Target");
+        }
+
+        let previous = self.profile.recorder_target;
+        if previous == target {
+            return Ok(());
+        }
+
+        let sources = &self.profile.devices.sources;
+        let sources: Vec<Ulid> = sources
+            .physical_devices
+            .iter()
+            .map(|d| d.description.id)
+            .chain(sources.virtual_devices.iter().map(|d| d.description.id))
+            .collect();
+
+        // Check this before we start moving routes, so we don't stop half way
+        if let Some(target) = target
+            && let Some(source) = sources
+                .iter()
+                .find(|s| self.routing_record_armed(**s) && self.routing_feeds(target, **s))
+        {
+            bail!("Armed Source {} is fed by {}", source, target);
+        }
+
+        // Only armed sources feed the Recorder, so we'd have to drop any other route to it
+        if let Some(target) = target {
+            for source in &sources {
+                if !self.routing_record_armed(*source)
+                    && self.routing_route_exists(*source, target).await?
+                {
+                    bail!("Unarmed Source {} is already routed to {}", source, target);
+                }
+            }
+        }
+
+        // Changed first, so the routes below don't alter the arm states
+        self.profile.recorder_target = target;
+
+        let mut moved = vec![];
+        let result = self
+            .routing_move_armed_routes(&sources, previous, target, &mut moved)
+            .await;
+        if let Err(e) = result {
+            // Put back anything we moved before the failure, with the previous Recorder in
+            // place so restoring its routes re-arms the sources rather than disarming them
+            self.profile.recorder_target = previous;
+            for (source, target, enabled) in moved.into_iter().rev() {
+                let exists = self.routing_route_exists(source, target).await;
+                if !matches!(exists, Ok(exists) if exists == enabled) {
+                    continue;
+                }
+                if let Err(e) = self.routing_set_route(source, target, !enabled).await {
+                    warn!(
+                        "[Routing] Unable to Restore Route {} -> {}: {}",
+                        source, target, e
+                    );
+                }
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn routing_set_record_arm(&mut self, source: Ulid, armed: bool) -> Result<()> {
+        if !matches!(
+            self.get_node_type(source),
+            Some(NodeType::PhysicalSource) | Some(NodeType::VirtualSource)
+        ) {
+            bail!("Only Sources can be armed for recording");
+        }
+
+        // Without a Recorder there's nothing to route, it'll be applied when one is set
+        if let Some(target) = self.profile.recorder_target
+            && self.routing_route_exists(source, target).await? != armed
+        {
+            debug!("[Routing] Setting Record Arm on {} to {}", source, armed);
+            self.routing_set_route(source, target, armed).await?;
+        }
+        self.routing_set_armed_flag(source, armed);
+        Ok(())
+    }
+
+    async fn routing_preview_mix(&mut self, mix: Mix, active: bool) -> Result<()> {
+        if !active {
+            // A fade on its way down hasn't switched yet, so it holds the latest preview
+            let held = match &self.mix_fade {
+                Some(fade) if !fade.rising => Some((fade.target, fade.preview)),
+                _ => self.mix_preview.map(|(target, mix)| (target, Some(mix))),
+            };
+
+            // Only release the preview being held, a release of an earlier one is stale
+            let Some((target, Some(held))) = held else {
+                return Ok(());
+            };
+            if held != mix {
+                return Ok(());
+            }
+
+            debug!("[Routing] Ending Preview of {:?} on {}", mix, target);
+            return self.routing_start_fade(target, None).await;
+        }
+
+        let Some(target) = self.profile.monitor_target else {
+            bail!("No Monitor Target has been set");
+        };
+
+        // The Monitor Target has changed since the last preview, drop that one straight away
+        if let Some((previous, _)) = self.mix_preview
+            && previous != target
+        {
+            self.routing_finish_fade().await?;
+            self.routing_switch_preview(previous, None).await?;
+        }
+
+        debug!("[Routing] Previewing {:?} on {}", mix, target);
+        self.routing_start_fade(target, Some(mix)).await
+    }
+
+    fn routing_fade_deadline(&self) -> Option<Instant> {
+        self.mix_fade.as_ref().map(|fade| fade.next)
+    }
+
+    async fn routing_fade_tick(&mut self) {
+        let Some(mut fade) = self.mix_fade.take() else {
+            return;
+        };
+
+        if !fade.rising && fade.step == 0 {
+            // We're silent, so swap the mix and let the clients know the preview changed
+            if let Err(e) = self.routing_switch_preview(fade.target, fade.preview).await {
+                warn!("[Routing] Unable to Switch Preview: {}", e);
+            }
+            let _ = self
+                .worker_sender
+                .send(WorkerMessage::TransientChange)
+                .await;
+            fade.rising = true;
+        }
+
+        if fade.rising {
+            fade.step += 1;
+        } else {
+            fade.step -= 1;
+        }
+        if let Err(e) = self.routing_fade_apply(fade.target, fade.step).await {
+            // The target has probably gone, there's nothing left to fade
+            warn!("[Routing] Unable to Fade Preview: {}", e);
+            return;
+        }
+
+        if !(fade.rising && fade.step == PREVIEW_FADE_STEPS) {
+            fade.next = Instant::now() + PREVIEW_FADE_STEP;
+            self.mix_fade = Some(fade);
+        }
+    }
+
+    async fn routing_set_idle_unrouted(&mut self, enabled: bool) -> Result<()> {
+        self.profile.idle_unrouted_sources = enabled;
+
+        let sources: Vec<Ulid> = self.source_map.keys().copied().collect();
+        for source in sources {
+            self.routing_update_idle(source).await?;
+        }
+        Ok(())
+    }
+
+    async fn routing_update_idle(&mut self, source: Ulid) -> Result<()> {
+        let Some(map) = self.source_map.get(&source).copied() else {
+            bail!("Unable to obtain volume map for Source");
+        };
+        let Some(eq) = self.eq_map.get(&source).copied() else {
+            bail!("Unable to obtain EQ for Source");
+        };
+
+        let unrouted = self
+            .profile
+            .routes
+            .get(&source)
+            .is_none_or(|r| r.is_empty());
+        let idle = self.profile.idle_unrouted_sources && unrouted;
+        if idle == self.idle_sources.contains(&source) {
+            return Ok(());
+        }
+
+        // The meter is attached before the mixes, and volumes live in the mix filters, so both
+        // carry on working while the source is idle.
+        for mix in Mix::iter() {
+            if idle {
+                self.link_remove_filter_to_filter(eq, map[mix]).await?
+            } else {
+                self.link_create_filter_to_filter(eq, map[mix]).await?
+            }
+        }
+
+        debug!("[Routing] Source {} Idle: {}", source, idle);
+        if idle {
+            self.idle_sources.insert(source);
+        } else {
+            self.idle_sources.remove(&source);
+        }
+        Ok(())
+    }
+
+    async fn routing_create_tap_source(&mut self, target: Ulid, name: String) -> Result<Ulid> {
+        if !matches!(
+            self.get_node_type(target),
+            Some(NodeType::PhysicalTarget | NodeType::VirtualTarget)
+        ) {
+            bail!("Only Targets can be tapped");
+        }
+
+        let id = self.node_new(NodeType::VirtualSource, name).await?;
+        if let Some(source) = self.get_virtual_source_mut(id) {
+            source.tap_target = Some(target);
+        }
+
+        debug!("[Routing] Tapping Target {} into Source {}", target, id);
+        self.routing_load_taps(id).await?;
+        Ok(id)
+    }
+
+    async fn routing_load_taps(&mut self, id: Ulid) -> Result<()> {
+        let taps: Vec<(Ulid, Ulid)> = self
+            .routing_taps()
+            .filter(|(source, target)| *source == id || *target == id)
+            .collect();
+
+        for (source, target) in taps {
+            match self.get_node_type(target) {
+                Some(NodeType::VirtualTarget) => {
+                    self.link_create_node_to_node(target, source).await?
+                }
+                Some(NodeType::PhysicalTarget) => {
+                    self.link_create_filter_to_node(target, source).await?
+                }
+                _ => warn!(
+                    "[Routing] Tapped Target {} for {} not Found",
+                    target, source
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    async fn routing_unload_taps(&mut self, id: Ulid) -> Result<()> {
+        let taps: Vec<(Ulid, Ulid)> = self
+            .routing_taps()
+            .filter(|(source, target)| *source == id || *target == id)
+            .collect();
+
+        for (source, target) in taps {
+            match self.get_node_type(target) {
+                Some(NodeType::VirtualTarget) => {
+                    self.link_remove_node_to_node(target, source).await?
+                }
+                Some(NodeType::PhysicalTarget) => {
+                    self.link_remove_filter_to_node(target, source).await?
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+trait RoutingManagementLocal {
+    async fn routing_relink_target_mix(&self, target: Ulid, from: Mix, to: Mix) -> Result<()>;
+
+    /// Sets (or with None, clears) the preview on a target, moving its links to match
+    async fn routing_switch_preview(&mut self, target: Ulid, preview: Option<Mix>) -> Result<()>;
+
+    /// Fades the target out, switches the preview, and fades it back in from the main loop
+    async fn routing_start_fade(&mut self, target: Ulid, preview: Option<Mix>) -> Result<()>;
+
+    /// Completes any fade in progress immediately
+    async fn routing_finish_fade(&mut self) -> Result<()>;
+    async fn routing_fade_apply(&self, target: Ulid, step: u8) -> Result<()>;
+    /// Every tap, as the tapping Source and the Target it's tapping
+    fn routing_taps(&self) -> impl Iterator<Item = (Ulid, Ulid)>;
+
+    /// Whether audio from a Target reaches a Source, following taps and their routes
+    fn routing_feeds(&self, target: Ulid, source: Ulid) -> bool;
+
+    /// Moves armed routes between Recorder Targets, noting each change before it's made
+    async fn routing_move_armed_routes(
+        &mut self,
+        sources: &[Ulid],
+        previous: Option<Ulid>,
+        target: Option<Ulid>,
+        moved: &mut Vec<(Ulid, Ulid, bool)>,
+    ) -> Result<()>;
+
+    fn routing_record_armed(&self, source: Ulid) -> bool;
+    fn routing_set_armed_flag(&mut self, source: Ulid, armed: bool);
+}
+
+impl RoutingManagementLocal for PipewireManager {
+    async fn routing_relink_target_mix(&self, target: Ulid, from: Mix, to: Mix) -> Result<()> {
+        let error = anyhow!("Cannot Locate Node");
+        let node_type = self.get_node_type(target).ok_or(error)?;
+
         // Next, grab all the routes to this target
         for (source, targets) in &self.profile.routes {
             if targets.contains(&target) {
@@ -221,29 +741,153 @@ impl RoutingManagement for PipewireManager {
                     // We need to detach the link from this source, and attach it to a new one
                     if let Some(map) = self.source_map.get(source).copied() {
                         if node_type == NodeType::PhysicalTarget {
-                            self.link_remove_filter_to_filter(map[current], target)
-                                .await?;
-                            self.link_create_filter_to_filter(map[mix], target).await?;
+                            self.link_remove_filter_to_filter(map[from], target).await?;
+                            self.link_create_filter_to_filter(map[to], target).await?;
                         } else {
-                            self.link_remove_filter_to_node(map[current], target)
-                                .await?;
-                            self.link_create_filter_to_node(map[mix], target).await?;
+                            self.link_remove_filter_to_node(map[from], target).await?;
+                            self.link_create_filter_to_node(map[to], target).await?;
                         }
                     }
                 }
             }
         }
+        Ok(())
+    }
 
-        // Update the Profile
-        if node_type == NodeType::PhysicalTarget {
-            self.get_physical_target_mut(target)
-                .ok_or(anyhow!("Unknown Node"))?
-                .mix = mix;
-        } else {
-            self.get_virtual_target_mut(target)
-                .ok_or(anyhow!("Unknown Node"))?
-                .mix = mix;
+    async fn routing_switch_preview(&mut self, target: Ulid, preview: Option<Mix>) -> Result<()> {
+        let from = self.routing_get_target_mix(&target).await;
+        self.mix_preview = preview.map(|mix| (target, mix));
+
+        // If the target has gone there are no links left to move
+        let from = from?;
+        let to = self.routing_get_target_mix(&target).await?;
+        if from == to {
+            return Ok(());
+        }
+        self.routing_relink_target_mix(target, from, to).await?;
+
+        // A Test Tone limited to a Mix should follow it
+        self.test_tone_sync(target).await?;
+
+        // Changing the mix may trigger (or lift) an automatic mute
+        self.apply_auto_mute_rules().await
+    }
+
+    async fn routing_start_fade(&mut self, target: Ulid, preview: Option<Mix>) -> Result<()> {
+        // A fade already on its way down only needs to know where it's going now
+        if let Some(fade) = &mut self.mix_fade
+            && fade.target == target
+            && !fade.rising
+        {
+            fade.preview = preview;
+            return Ok(());
+        }
+
+        // Otherwise, head back down from wherever the volume currently is
+        let step = match self.mix_fade.take_if(|fade| fade.target == target) {
+            Some(fade) => fade.step,
+            None => PREVIEW_FADE_STEPS,
+        };
+        self.routing_finish_fade().await?;
+
+        self.mix_fade = Some(MixFade {
+            target,
+            preview,
+            step,
+            rising: false,
+            next: Instant::now(),
+        });
+        Ok(())
+    }
+
+    async fn routing_finish_fade(&mut self) -> Result<()> {
+        let Some(fade) = self.mix_fade.take() else {
+            return Ok(());
+        };
+        if !fade.rising {
+            self.routing_switch_preview(fade.target, fade.preview)
+                .await?;
+        }
+        self.routing_fade_apply(fade.target, PREVIEW_FADE_STEPS)
+            .await
+    }
+
+    async fn routing_fade_apply(&self, target: Ulid, step: u8) -> Result<()> {
+        // The profile volume isn't touched, so it's restored exactly at the top of the fade
+        let volume = self.get_node_volume(target, Mix::A)?;
+        let level = (volume as u32 * step as u32 / PREVIEW_FADE_STEPS as u32) as u8;
+        self.volume_target_apply(target, level).await
+    }
+
+    fn routing_taps(&self) -> impl Iterator<Item = (Ulid, Ulid)> {
+        let sources = &self.profile.devices.sources.virtual_devices;
+        sources
+            .iter()
+            .filter_map(|d| d.tap_target.map(|target| (d.description.id, target)))
+    }
+
+    fn routing_feeds(&self, target: Ulid, source: Ulid) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = vec![target];
+
+        while let Some(target) = pending.pop() {
+            if !visited.insert(target) {
+                continue;
+            }
+            for (tap, _) in self.routing_taps().filter(|(_, t)| *t == target) {
+                if tap == source {
+                    return true;
+                }
+                if let Some(routes) = self.profile.routes.get(&tap) {
+                    pending.extend(routes.iter().copied());
+                }
+            }
+        }
+        false
+    }
+
+    async fn routing_move_armed_routes(
+        &mut self,
+        sources: &[Ulid],
+        previous: Option<Ulid>,
+        target: Option<Ulid>,
+        moved: &mut Vec<(Ulid, Ulid, bool)>,
+    ) -> Result<()> {
+        for &source in sources {
+            if !self.routing_record_armed(source) {
+                continue;
+            }
+
+            if let Some(previous) = previous
+                && self.routing_route_exists(source, previous).await?
+            {
+                moved.push((source, previous, false));
+                self.routing_set_route(source, previous, false).await?;
+            }
+
+            if let Some(target) = target
+                && !self.routing_route_exists(source, target).await?
+            {
+                moved.push((source, target, true));
+                self.routing_set_route(source, target, true).await?;
+            }
         }
         Ok(())
     }
+
+    fn routing_record_armed(&self, source: Ulid) -> bool {
+        if let Some(device) = self.get_physical_source(source) {
+            return device.record_armed;
+        }
+        self.get_virtual_source(source)
+            .is_some_and(|d| d.record_armed)
+    }
+
+    fn routing_set_armed_flag(&mut self, source: Ulid, armed: bool) {
+        if let Some(device) = self.get_physical_source_mut(source) {
+            device.record_armed = armed;
+        } else if let Some(device) = self.get_virtual_source_mut(source) {
+            device.record_armed = armed;
+        }
+    }
 }
@@ -0,0 +1,151 @@
+use crate::handler::pipewire::components::filters::FilterManagement;
+use crate::handler::pipewire::components::links::LinkManagement;
+use crate::handler::pipewire::components::node::NodeManagement;
+use crate::handler::pipewire::components::routing::RoutingManagement;
+use crate::handler::pipewire::manager::PipewireManager;
+use anyhow::{Result, anyhow, bail};
+use log::debug;
+use pipeweaver_ipc::commands::TestSignal;
+use pipeweaver_shared::{Mix, NodeType};
+use ulid::Ulid;
+
+/// A test signal being played into a target
+pub(crate) struct TestTone {
+    filter: Ulid,
+
+    // Only play while the target is listening to this Mix, None for regardless of Mix
+    mix: Option<Mix>,
+    linked: bool,
+}
+
+/// Plays a test signal into a target for calibration. The signal is linked in alongside the
+/// target's selected mix, so when limited to a mix it's only heard while that mix is selected.
+pub(crate) trait TestToneManagement {
+    async fn test_tone_start(
+        &mut self,
+        target: Ulid,
+        mix: Option<Mix>,
+        signal: TestSignal,
+        level: u8,
+    ) -> Result<()>;
+    async fn test_tone_stop(&mut self, target: Ulid) -> Result<()>;
+
+    /// Links or unlinks a target's test tone to match its current mix
+    async fn test_tone_sync(&mut self, target: Ulid) -> Result<()>;
+
+    /// Relinks a target's test tone after the target's node has been recreated
+    async fn test_tone_relink(&mut self, target: Ulid) -> Result<()>;
+}
+
+impl TestToneManagement for PipewireManager {
+    async fn test_tone_start(
+        &mut self,
+        target: Ulid,
+        mix: Option<Mix>,
+        signal: TestSignal,
+        level: u8,
+    ) -> Result<()> {
+        if !matches!(
+            self.get_node_type(target),
+            Some(NodeType::PhysicalTarget | NodeType::VirtualTarget)
+        ) {
+            bail!("Test Tones can only be played into Targets");
+        }
+        if level > 100 {
+            bail!("Level must be between 0 and 100");
+        }
+        if let TestSignal::Sine(frequency) = signal
+            && !(20..=20000).contains(&frequency)
+        {
+            bail!("Frequency must be between 20 and 20000Hz");
+        }
+
+        // Only one tone per target, replace anything that's already playing
+        if self.test_tones.contains_key(&target) {
+            self.test_tone_stop(target).await?;
+        }
+
+        let filter = self.filter_tone_create(signal, level).await?;
+        self.test_tones.insert(
+            target,
+            TestTone {
+                filter,
+                mix,
+                linked: false,
+            },
+        );
+
+        debug!("[Tone] Started {:?} on {} ({:?})", signal, target, mix);
+        self.test_tone_sync(target).await
+    }
+
+    async fn test_tone_stop(&mut self, target: Ulid) -> Result<()> {
+        let tone = self
+            .test_tones
+            .remove(&target)
+            .ok_or(anyhow!("No Test Tone playing on {}", target))?;
+
+        if tone.linked {
+            self.test_tone_unlink(tone.filter, target).await?;
+        }
+        self.filter_remove(tone.filter).await?;
+
+        debug!("[Tone] Stopped on {}", target);
+        Ok(())
+    }
+
+    async fn test_tone_sync(&mut self, target: Ulid) -> Result<()> {
+        let Some(tone) = self.test_tones.get(&target) else {
+            return Ok(());
+        };
+        let (filter, mix, linked) = (tone.filter, tone.mix, tone.linked);
+
+        let wanted = match mix {
+            Some(mix) => self.routing_get_target_mix(&target).await? == mix,
+            None => true,
+        };
+
+        if wanted && !linked {
+            self.test_tone_link(filter, target).await?;
+        } else if !wanted && linked {
+            self.test_tone_unlink(filter, target).await?;
+        }
+
+        if let Some(tone) = self.test_tones.get_mut(&target) {
+            tone.linked = wanted;
+        }
+        Ok(())
+    }
+
+    async fn test_tone_relink(&mut self, target: Ulid) -> Result<()> {
+        // Any links went with the old node
+        if let Some(tone) = self.test_tones.get_mut(&target) {
+            tone.linked = false;
+        }
+        self.test_tone_sync(target).await
+    }
+}
+
+trait TestToneManagementLocal {
+    async fn test_tone_link(&mut self, filter: Ulid, target: Ulid) -> Result<()>;
+    async fn test_tone_unlink(&mut self, filter: Ulid, target: Ulid) -> Result<()>;
+}
+
+impl TestToneManagementLocal for PipewireManager {
+    async fn test_tone_link(&mut self, filter: Ulid, target: Ulid) -> Result<()> {
+        // Feed the target the same way a source's mix does
+        if self.get_node_type(target) == Some(NodeType::PhysicalTarget) {
+            self.link_create_filter_to_filter(filter, target).await
+        } else {
+            self.link_create_filter_to_node(filter, target).await
+        }
+    }
+
+    async fn test_tone_unlink(&mut self, filter: Ulid, target: Ulid) -> Result<()> {
+        if self.get_node_type(target) == Some(NodeType::PhysicalTarget) {
+            self.link_remove_filter_to_filter(filter, target).await
+        } else {
+            self.link_remove_filter_to_node(filter, target).await
+        }
+    }
+}
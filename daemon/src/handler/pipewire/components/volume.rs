@@ -6,9 +6,11 @@ use crate::handler::pipewire::components::profile::ProfileManagement;
 use crate::handler::pipewire::manager::PipewireManager;
 use anyhow::{Result, anyhow, bail};
 use log::debug;
+use pipeweaver_ipc::commands::VolumeSnapshot;
 use pipeweaver_pipewire::{FilterValue, PipewireMessage, oneshot};
 use pipeweaver_profile::Volumes;
 use pipeweaver_shared::{Mix, MuteState, MuteTarget, NodeType};
+use strum::IntoEnumIterator;
 use ulid::Ulid;
 
 pub(crate) trait VolumeManager {
@@ -32,8 +34,17 @@ pub(crate) trait VolumeManager {
 
     async fn set_target_volume(&mut self, id: Ulid, volume: u8, from_api: bool) -> Result<()>;
 
+    async fn set_all_source_volumes(&mut self, mix: Mix, volume: u8) -> Result<()>;
+    async fn set_all_target_volumes(&mut self, volume: u8) -> Result<()>;
+
     async fn set_metering(&mut self, enabled: bool) -> Result<()>;
     fn get_node_volume(&self, id: Ulid, mix: Mix) -> Result<u8>;
+
+    /// Applies a volume to a target without storing it in the profile
+    async fn volume_target_apply(&self, id: Ulid, volume: u8) -> Result<()>;
+
+    fn snapshot_volumes(&self) -> VolumeSnapshot;
+    async fn restore_volumes(&mut self, snapshot: VolumeSnapshot) -> Result<()>;
 }
 
 impl VolumeManager for PipewireManager {
@@ -393,6 +404,53 @@ impl VolumeManager for PipewireManager {
         Ok(())
     }
 
+    async fn set_all_source_volumes(&mut self, mix: Mix, volume: u8) -> Result<()> {
+        if !(0..=100).contains(&volume) {
+            bail!("Volume Must be between 0 and 100");
+        }
+
+        let sources = &self.profile.devices.sources;
+        let ids: Vec<Ulid> = sources
+            .physical_devices
+            .iter()
+            .map(|d| d.description.id)
+            .chain(sources.virtual_devices.iter().map(|d| d.description.id))
+            .collect();
+
+        // If anything fails part way through, put everything back how it was
+        let snapshot = self.snapshot_volumes();
+        for id in ids {
+            if let Err(e) = self.set_source_volume(id, mix, volume, true).await {
+                let _ = self.restore_volumes(snapshot).await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_all_target_volumes(&mut self, volume: u8) -> Result<()> {
+        if !(0..=100).contains(&volume) {
+            bail!("Volume Must be between 0 and 100");
+        }
+
+        let targets = &self.profile.devices.targets;
+        let ids: Vec<Ulid> = targets
+            .physical_devices
+            .iter()
+            .map(|d| d.description.id)
+            .chain(targets.virtual_devices.iter().map(|d| d.description.id))
+            .collect();
+
+        let snapshot = self.snapshot_volumes();
+        for id in ids {
+            if let Err(e) = self.set_target_volume(id, volume, true).await {
+                let _ = self.restore_volumes(snapshot).await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     async fn set_metering(&mut self, enabled: bool) -> Result<()> {
         if enabled == self.meter_enabled {
             // Nothing to do, changing to existing state.
@@ -438,6 +496,10 @@ impl VolumeManager for PipewireManager {
             }
         }
         self.meter_enabled = enabled;
+        if !enabled {
+            // Anything we've got is stale now
+            self.meter_levels.clear();
+        }
         Ok(())
     }
 
@@ -457,6 +519,73 @@ impl VolumeManager for PipewireManager {
             NodeType::VirtualTarget => Ok(self.get_virtual_target(id).ok_or(err)?.volume),
         }
     }
+
+    async fn volume_target_apply(&self, id: Ulid, volume: u8) -> Result<()> {
+        self.volume_target_load_with_mute(id, volume).await
+    }
+
+    fn snapshot_volumes(&self) -> VolumeSnapshot {
+        let devices = &self.profile.devices;
+        let physical = devices.sources.physical_devices.iter();
+        let virtual_sources = devices.sources.virtual_devices.iter();
+
+        let mut snapshot = VolumeSnapshot::default();
+        for (id, volumes) in physical
+            .map(|d| (d.description.id, &d.volumes))
+            .chain(virtual_sources.map(|d| (d.description.id, &d.volumes)))
+        {
+            snapshot.sources.insert(id, volumes.clone());
+        }
+
+        for device in &devices.targets.physical_devices {
+            snapshot
+                .targets
+                .insert(device.description.id, device.volume);
+        }
+        for device in &devices.targets.virtual_devices {
+            snapshot
+                .targets
+                .insert(device.description.id, device.volume);
+        }
+        snapshot
+    }
+
+    async fn restore_volumes(&mut self, snapshot: VolumeSnapshot) -> Result<()> {
+        for (id, volumes) in snapshot.sources {
+            // Nodes may have been removed since the snapshot was taken
+            if !matches!(
+                self.get_node_type(id),
+                Some(NodeType::PhysicalSource | NodeType::VirtualSource)
+            ) {
+                debug!("Skipping Volume Restore for missing Source {}", id);
+                continue;
+            }
+
+            // Unlink while we set the volumes, otherwise each mix will drag the other along
+            let linked = self.get_volumes(id)?.volumes_linked.take();
+            for mix in Mix::iter() {
+                let volume = volumes.volume[mix].min(100);
+                if let Err(e) = self.set_source_volume(id, mix, volume, true).await {
+                    // Put the link back, so a failure doesn't leave the mixes unlinked
+                    self.get_volumes(id)?.volumes_linked = linked;
+                    return Err(e);
+                }
+            }
+            self.get_volumes(id)?.volumes_linked = volumes.volumes_linked;
+        }
+
+        for (id, volume) in snapshot.targets {
+            if !matches!(
+                self.get_node_type(id),
+                Some(NodeType::PhysicalTarget | NodeType::VirtualTarget)
+            ) {
+                debug!("Skipping Volume Restore for missing Target {}", id);
+                continue;
+            }
+            self.set_target_volume(id, volume.min(100), true).await?;
+        }
+        Ok(())
+    }
 }
 
 trait VolumeManagerLocal {
@@ -1,13 +1,16 @@
+use crate::handler::pipewire::components::aggregate::AggregateSinkManagement;
 use crate::handler::pipewire::components::application::ApplicationManagement;
 use crate::handler::pipewire::components::defaults::DefaultHandlers;
+use crate::handler::pipewire::components::eq::EqManagement;
 use crate::handler::pipewire::components::load_profile::LoadProfile;
-use crate::handler::pipewire::components::mute::MuteManager;
+use crate::handler::pipewire::components::mute::{MuteManager, PendingMute};
 use crate::handler::pipewire::components::node::NodeManagement;
 use crate::handler::pipewire::components::physical::PhysicalDevices;
 use crate::handler::pipewire::components::routing::RoutingManagement;
 use crate::handler::pipewire::components::volume::VolumeManager;
 use crate::handler::pipewire::manager::PipewireManager;
 use anyhow::{Error, bail};
+use log::warn;
 use pipeweaver_ipc::commands::{APICommand, PWCommandResponse};
 use pipeweaver_shared::MuteState::{Muted, Unmuted};
 use pipeweaver_shared::{Mix, NodeType};
@@ -20,8 +23,53 @@ pub(crate) trait IPCHandler {
 
 impl IPCHandler for PipewireManager {
     async fn handle_command(&mut self, command: Cmd) -> Result<Resp, Error> {
+        // Mute changes are held back to be merged, anything else needs them applied first so
+        // commands still take effect in the order they were sent
+        let is_mute = matches!(
+            command,
+            Cmd::AddSourceMuteTarget(..)
+                | Cmd::AddSourceMuteTargetByName(..)
+                | Cmd::DelSourceMuteTarget(..)
+                | Cmd::DelSourceMuteTargetByName(..)
+                | Cmd::SetTargetMuteState(..)
+                | Cmd::SetTargetMuteStatesByName(..)
+        );
+        if !is_mute && !self.pending_mutes.is_empty() {
+            self.apply_pending_mutes().await;
+        }
+
         match command {
-            Cmd::CreateNode(node_type, id) => self.node_new(node_type, id).await.map(Resp::Id),
+            Cmd::CreateNode(node_type, name, auto_monitor) => {
+                // Check this up front, so we don't end up with a half configured node
+                if auto_monitor {
+                    if !matches!(
+                        node_type,
+                        NodeType::PhysicalSource | NodeType::VirtualSource
+                    ) {
+                        bail!("Only Sources can be routed to the Monitor Target");
+                    }
+                    if self.profile.monitor_target.is_none() {
+                        bail!("No Monitor Target has been set");
+                    }
+                }
+
+                let id = self.node_new(node_type, name).await?;
+                if auto_monitor && let Err(e) = self.routing_auto_monitor(id).await {
+                    // Don't leave behind a node which isn't set up the way it was asked for
+                    if let Err(remove) = self.node_remove(id).await {
+                        warn!(
+                            "Unable to remove Node {} after failing to Monitor it: {}",
+                            id, remove
+                        );
+                    }
+                    return Err(e);
+                }
+                Ok(Resp::Id(id))
+            }
+            Cmd::CreateTargetTapSource(target, name) => self
+                .routing_create_tap_source(target, name)
+                .await
+                .map(Resp::Id),
 
             Cmd::RenameNode(id, new) => self.node_rename(id, new).await.map(|_| Resp::Ok),
             Cmd::RenameNodeByName(name, new) => {
@@ -51,6 +99,14 @@ impl IPCHandler for PipewireManager {
                     bail!("Node name {} not Found", name);
                 }
             }
+            Cmd::ResetNode(id) => self.node_reset(id).await.map(|_| Resp::Ok),
+            Cmd::ResetNodeByName(name) => {
+                if let Some(id) = self.get_node_id_by_name(&name) {
+                    self.node_reset(id).await.map(|_| Resp::Ok)
+                } else {
+                    bail!("Node name {} not Found", name);
+                }
+            }
 
             Cmd::SetSourceVolume(id, mix, volume) => self
                 .set_source_volume(id, mix, volume, true)
@@ -60,6 +116,13 @@ impl IPCHandler for PipewireManager {
                 .set_target_volume(id, volume, true)
                 .await
                 .map(|_| Resp::Ok),
+            Cmd::SetAllSourceVolumes(mix, volume) => self
+                .set_all_source_volumes(mix, volume)
+                .await
+                .map(|_| Resp::Ok),
+            Cmd::SetAllTargetVolumes(volume) => {
+                self.set_all_target_volumes(volume).await.map(|_| Resp::Ok)
+            }
             Cmd::SetVolumeByName(name, mix, volume) => {
                 let mix = if let Some(mix) = mix { mix } else { Mix::A };
 
@@ -147,6 +210,33 @@ impl IPCHandler for PipewireManager {
                     bail!("Source name {} not Found", source_name);
                 }
             }
+            Cmd::SetTargetChannels(id, channels) => {
+                self.node_set_channels(id, channels).await.map(|_| Resp::Ok)
+            }
+            Cmd::SetTargetChannelsByName(name, channels) => {
+                if let Some(id) = self.get_node_id_by_name(&name) {
+                    self.node_set_channels(id, channels).await.map(|_| Resp::Ok)
+                } else {
+                    bail!("Node name {} not Found", name);
+                }
+            }
+            Cmd::SetRouteChannelPair(source, target, pair) => self
+                .routing_set_channel_pair(source, target, pair)
+                .await
+                .map(|_| Resp::Ok),
+            Cmd::SetRouteChannelPairByNames(source_name, target_name, pair) => {
+                if let Some(source_id) = self.get_node_id_by_name(&source_name) {
+                    if let Some(target_id) = self.get_node_id_by_name(&target_name) {
+                        self.routing_set_channel_pair(source_id, target_id, pair)
+                            .await
+                            .map(|_| Resp::Ok)
+                    } else {
+                        bail!("Target name {} not Found", target_name);
+                    }
+                } else {
+                    bail!("Source name {} not Found", source_name);
+                }
+            }
             Cmd::ToggleRoute(source, target) => self
                 .routing_toggle_route(source, target)
                 .await
@@ -184,13 +274,11 @@ impl IPCHandler for PipewireManager {
             }
 
             Cmd::AddSourceMuteTarget(id, target) => self
-                .set_source_mute_state(id, target, Muted)
-                .await
+                .queue_mute_state(PendingMute::Source(id, target), Muted)
                 .map(|_| Resp::Ok),
             Cmd::AddSourceMuteTargetByName(name, target) => {
                 if let Some(id) = self.get_node_id_by_name(&name) {
-                    self.set_source_mute_state(id, target, Muted)
-                        .await
+                    self.queue_mute_state(PendingMute::Source(id, target), Muted)
                         .map(|_| Resp::Ok)
                 } else {
                     bail!("Source name {} not Found", name);
@@ -198,13 +286,11 @@ impl IPCHandler for PipewireManager {
             }
 
             Cmd::DelSourceMuteTarget(id, target) => self
-                .set_source_mute_state(id, target, Unmuted)
-                .await
+                .queue_mute_state(PendingMute::Source(id, target), Unmuted)
                 .map(|_| Resp::Ok),
             Cmd::DelSourceMuteTargetByName(name, target) => {
                 if let Some(id) = self.get_node_id_by_name(&name) {
-                    self.set_source_mute_state(id, target, Unmuted)
-                        .await
+                    self.queue_mute_state(PendingMute::Source(id, target), Unmuted)
                         .map(|_| Resp::Ok)
                 } else {
                     bail!("Node name {} not Found", name);
@@ -297,13 +383,13 @@ impl IPCHandler for PipewireManager {
                 }
             }
 
-            Cmd::SetTargetMuteState(id, state) => self
-                .set_target_mute_state(id, state)
+            Cmd::SetMuteTargetLabel(id, target, label) => self
+                .set_mute_target_label(id, target, label)
                 .await
                 .map(|_| Resp::Ok),
-            Cmd::SetTargetMuteStatesByName(name, state) => {
+            Cmd::SetMuteTargetLabelByName(name, target, label) => {
                 if let Some(id) = self.get_node_id_by_name(&name) {
-                    self.set_target_mute_state(id, state)
+                    self.set_mute_target_label(id, target, label)
                         .await
                         .map(|_| Resp::Ok)
                 } else {
@@ -311,6 +397,27 @@ impl IPCHandler for PipewireManager {
                 }
             }
 
+            Cmd::SetTargetMuteState(id, state) => self
+                .queue_mute_state(PendingMute::Target(id), state)
+                .map(|_| Resp::Ok),
+            Cmd::SetTargetMuteStatesByName(name, state) => {
+                if let Some(id) = self.get_node_id_by_name(&name) {
+                    self.queue_mute_state(PendingMute::Target(id), state)
+                        .map(|_| Resp::Ok)
+                } else {
+                    bail!("Node name {} not Found", name);
+                }
+            }
+
+            Cmd::AddAutoMuteRule(target, mix, source) => self
+                .add_auto_mute_rule(target, mix, source)
+                .await
+                .map(|_| Resp::Ok),
+            Cmd::DelAutoMuteRule(target, mix, source) => self
+                .del_auto_mute_rule(target, mix, source)
+                .await
+                .map(|_| Resp::Ok),
+
             Cmd::AttachPhysicalNode(id, node_id) => {
                 self.add_device_to_node(id, node_id).await.map(|_| Resp::Ok)
             }
@@ -336,6 +443,28 @@ impl IPCHandler for PipewireManager {
                 }
             }
 
+            Cmd::SetPhysicalNodeChannelMode(id, index, mode) => self
+                .set_device_channel_mode(id, index, mode)
+                .await
+                .map(|_| Resp::Ok),
+            Cmd::SetPhysicalNodeChannelModeByName(name, index, mode) => {
+                if let Some(id) = self.get_node_id_by_name(&name) {
+                    self.set_device_channel_mode(id, index, mode)
+                        .await
+                        .map(|_| Resp::Ok)
+                } else {
+                    bail!("Node name {} not Found", name);
+                }
+            }
+            Cmd::SplitPhysicalNode(id, index) => self.split_device(id, index).await.map(Resp::Id),
+            Cmd::SplitPhysicalNodeByName(name, index) => {
+                if let Some(id) = self.get_node_id_by_name(&name) {
+                    self.split_device(id, index).await.map(Resp::Id)
+                } else {
+                    bail!("Node name {} not Found", name);
+                }
+            }
+
             Cmd::SetApplicationRoute(definition, target_id) => self
                 .set_application_target(definition, target_id)
                 .await
@@ -354,6 +483,41 @@ impl IPCHandler for PipewireManager {
                 .clear_application_target(definition)
                 .await
                 .map(|_| Resp::Ok),
+            Cmd::CreateAggregateSink(name, fallback) => {
+                self.aggregate_create(name, fallback).await.map(Resp::Id)
+            }
+            Cmd::SetAggregateSinkFallback(fallback) => self
+                .aggregate_set_fallback(fallback)
+                .await
+                .map(|_| Resp::Ok),
+            Cmd::RemoveAggregateSink => self.aggregate_remove().await.map(|_| Resp::Ok),
+
+            Cmd::SetMonitorTarget(target) => self
+                .routing_set_monitor_target(target)
+                .await
+                .map(|_| Resp::Ok),
+            Cmd::SetRecorderTarget(target) => self
+                .routing_set_recorder_target(target)
+                .await
+                .map(|_| Resp::Ok),
+            Cmd::SetRecordArm(source, armed) => self
+                .routing_set_record_arm(source, armed)
+                .await
+                .map(|_| Resp::Ok),
+            Cmd::SetRecordArmByName(name, armed) => {
+                if let Some(id) = self.get_node_id_by_name(&name) {
+                    self.routing_set_record_arm(id, armed)
+                        .await
+                        .map(|_| Resp::Ok)
+                } else {
+                    bail!("Node name {} not Found", name);
+                }
+            }
+            Cmd::SetEqBands(id, bands) => self.set_eq_bands(id, bands).await.map(|_| Resp::Ok),
+            Cmd::SetIdleUnroutedSources(enabled) => self
+                .routing_set_idle_unrouted(enabled)
+                .await
+                .map(|_| Resp::Ok),
             Cmd::SetTransientApplicationRoute(id, route) => self
                 .set_application_transient_target(id, route)
                 .await
@@ -396,12 +560,28 @@ impl IPCHandler for PipewireManager {
                     bail!("Node name {} not Found", name);
                 }
             }
-            Cmd::SetOrder(id, position) => {
-                self.node_set_position(id, position).await.map(|_| Resp::Ok)
-            }
+            Cmd::SetOrder(id, position) => self
+                .node_set_position(id, None, position)
+                .await
+                .map(|_| Resp::Ok),
             Cmd::SetOrderByName(name, position) => {
                 if let Some(id) = self.get_node_id_by_name(&name) {
-                    self.node_set_position(id, position).await.map(|_| Resp::Ok)
+                    self.node_set_position(id, None, position)
+                        .await
+                        .map(|_| Resp::Ok)
+                } else {
+                    bail!("Node name {} not Found", name);
+                }
+            }
+            Cmd::SetOrderInGroup(id, group, position) => self
+                .node_set_position(id, Some(group), position)
+                .await
+                .map(|_| Resp::Ok),
+            Cmd::SetOrderInGroupByName(name, group, position) => {
+                if let Some(id) = self.get_node_id_by_name(&name) {
+                    self.node_set_position(id, Some(group), position)
+                        .await
+                        .map(|_| Resp::Ok)
                 } else {
                     bail!("Node name {} not Found", name);
                 }
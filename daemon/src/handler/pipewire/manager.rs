@@ -1,35 +1,52 @@
+use crate::handler::pipewire::components::aggregate::AggregateSinkManagement;
 use crate::handler::pipewire::components::application::{
     ApplicationManagement, get_application_type,
 };
+use crate::handler::pipewire::components::capture::{CaptureManagement, PendingCapture};
 use crate::handler::pipewire::components::defaults::DefaultHandlers;
+use crate::handler::pipewire::components::eq::EqManagement;
+use crate::handler::pipewire::components::latency::{LatencyManagement, PendingLatency};
 use crate::handler::pipewire::components::links::LinkManagement;
 use crate::handler::pipewire::components::load_profile::LoadProfile;
+use crate::handler::pipewire::components::meters::MeterReadings;
+use crate::handler::pipewire::components::mute::{
+    MuteManager, PendingMuteAction, PendingMutes, pending_mute_action,
+};
+use crate::handler::pipewire::components::node::NodeManagement;
+use crate::handler::pipewire::components::orphans::OrphanManagement;
 use crate::handler::pipewire::components::physical::PhysicalDevices;
+use crate::handler::pipewire::components::reconcile::LinkReconciliation;
+use crate::handler::pipewire::components::restore::ProfileRestore;
+use crate::handler::pipewire::components::routing::{MixFade, RoutingManagement};
+use crate::handler::pipewire::components::test_tone::{TestTone, TestToneManagement};
 use crate::handler::pipewire::components::volume::VolumeManager;
 use crate::handler::pipewire::ipc::IPCHandler;
 use crate::handler::primary_worker::WorkerMessage::{ManagerStopped, TransientChange};
 use crate::handler::primary_worker::{ManagerMessage, WorkerMessage};
 use crate::servers::http_server::MeterEvent;
+use anyhow::{Result, bail};
 use enum_map::{EnumMap, enum_map};
 use log::{debug, error, info, warn};
 use pipeweaver_ipc::commands::{
-    Application, AudioConfiguration, PWCommandResponse, PhysicalDevice, PhysicalDevicePort,
+    ActiveOverlays, Application, AttachedDeviceStatus, AudioConfiguration, AudioQuantum,
+    Capabilities, EngineStatus, MixPreview, PWCommandResponse, PhysicalDevice, PhysicalDevicePort,
+    QuantumMismatch, RealtimeStatus, XrunEvent, XrunSeverity,
 };
 use pipeweaver_pipewire::{
-    ApplicationNode, DeviceNode, Direction, MediaClass, NodeTarget, PipewireMessage,
+    ApplicationNode, DeviceNode, Direction, MediaClass, NamingScheme, NodeTarget, PipewireMessage,
     PipewireReceiver, PipewireRunner,
 };
 use pipeweaver_profile::Profile;
-use pipeweaver_shared::{AppTarget, DeviceType, Mix, PortDirection};
-use std::collections::HashMap;
+use pipeweaver_shared::{AppTarget, Colour, DeviceType, Mix, PortDirection};
+use std::collections::{HashMap, HashSet};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use strum::IntoEnumIterator;
 use tokio::select;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio::task::JoinHandle;
-use tokio::time::sleep;
+use tokio::time::{Instant, sleep, sleep_until};
 use ulid::Ulid;
 
 type StdRecv = std::sync::mpsc::Receiver<PipewireReceiver>;
@@ -43,14 +60,36 @@ pub(crate) struct PipewireManager {
     pub(crate) clock_rate: Option<u32>,
     pub(crate) default_source: Option<NodeTarget>,
     pub(crate) default_target: Option<NodeTarget>,
+    pub(crate) session_manager_present: bool,
+    cleanup_orphans: bool,
+    attach_defaults: bool,
+
+    // When disabled, devices are listed as they appear, but not attached to nodes
+    pub(crate) auto_match: bool,
+
+    // Used to brand and group the nodes we create
+    pub(crate) naming: NamingScheme,
+
+    // The colours new nodes cycle through, empty for the built-in palette
+    pub(crate) palette: Vec<Colour>,
 
     pub(crate) profile: Profile,
     pub(crate) source_map: HashMap<Ulid, EnumMap<Mix, Ulid>>,
 
+    // Maps a Source to the EQ filter sitting between it and its Mixes
+    pub(crate) eq_map: HashMap<Ulid, Ulid>,
+
+    // Sources which are currently muted by an AutoMuteRule
+    pub(crate) auto_muted_sources: HashSet<Ulid>,
+
     // Maps the connection of a PassThrough filter to a Physical Source id
     pub(crate) physical_source: HashMap<Ulid, Vec<u32>>,
     pub(crate) physical_target: HashMap<Ulid, Vec<u32>>,
 
+    // Maps a device (and the Physical Source it's attached to) to the filter averaging its
+    // channels, when it's attached with ChannelMode::MonoSum
+    pub(crate) mono_sum_filters: HashMap<(u32, Ulid), Ulid>,
+
     // Volume syncs which we're waiting for a response from Pipewire
     pub(crate) pending_volume_syncs: HashMap<u32, u8>,
 
@@ -62,6 +101,14 @@ pub(crate) struct PipewireManager {
     meter_receiver: Option<mpsc::Receiver<(Ulid, u8)>>,
     meter_broadcast: broadcast::Sender<MeterEvent>,
 
+    // The last level from each meter, whether metering was asked for by a client, and while
+    // the meters are on for a one-off reading, when it can be sent and when to switch them off
+    pub(crate) meter_levels: HashMap<Ulid, u8>,
+    pub(crate) meter_requested: bool,
+    pub(crate) meter_ready: Option<Instant>,
+    pub(crate) meter_hold: Option<Instant>,
+    pub(crate) meter_readers: Vec<oneshot::Sender<HashMap<Ulid, u8>>>,
+
     // A list of physical nodes
     pub(crate) node_list: EnumMap<DeviceType, Vec<PhysicalDevice>>,
     pub(crate) device_nodes: HashMap<u32, DeviceNode>,
@@ -69,11 +116,53 @@ pub(crate) struct PipewireManager {
     // A list of application nodes
     pub(crate) application_nodes: HashMap<u32, ApplicationNode>,
     pub(crate) application_target_ignore: HashMap<u32, Option<NodeTarget>>,
+
+    // Every filter reports the same xrun, this lets us only pass on the first
+    last_xrun_cycle: Option<u32>,
+
+    // The quantum the graph is actually running at, as reported by our filters
+    quantum: Option<u32>,
+
+    // Filters the watchdog has bypassed (silenced) for taking too long to process
+    pub(crate) bypassed_filters: HashSet<Ulid>,
+
+    // The realtime priority requested for the filter thread, and what it actually got
+    realtime_priority: Option<u8>,
+    realtime_status: Option<RealtimeStatus>,
+
+    // Sources which have been disconnected from their mixes as they're not routed anywhere
+    pub(crate) idle_sources: HashSet<Ulid>,
+
+    // While a Mix Preview is held, the Monitor Target and the Mix it's hearing. This is never
+    // stored in the profile, the target's own mix is returned to when it's released
+    pub(crate) mix_preview: Option<(Ulid, Mix)>,
+    pub(crate) mix_fade: Option<MixFade>,
+
+    // Sample captures which are recording, and the channel their filters send samples back on
+    pub(crate) captures: HashMap<Ulid, PendingCapture>,
+    pub(crate) capture_callback: Sender<(Ulid, Vec<f32>)>,
+    capture_receiver: Option<mpsc::Receiver<(Ulid, Vec<f32>)>>,
+
+    // Latency probes which are running, and the channel their filters send recordings back on
+    pub(crate) latency_probes: HashMap<Ulid, PendingLatency>,
+    pub(crate) latency_callback: Sender<(Ulid, Vec<f32>)>,
+    latency_receiver: Option<mpsc::Receiver<(Ulid, Vec<f32>)>>,
+
+    // Test Tones currently being played, keyed by the Target they're playing into
+    pub(crate) test_tones: HashMap<Ulid, TestTone>,
+
+    // Whether a Panic Mute is silencing all filters
+    panic_muted: bool,
+
+    // Client mute changes waiting for the debounce to expire
+    pub(crate) pending_mutes: PendingMutes,
 }
 
 impl PipewireManager {
     pub fn new(config: PipewireManagerConfig) -> Self {
         let (meter_tx, meter_rx) = mpsc::channel(32);
+        let (capture_tx, capture_rx) = mpsc::channel(4);
+        let (latency_tx, latency_rx) = mpsc::channel(4);
 
         Self {
             command_receiver: config.command_receiver,
@@ -84,13 +173,22 @@ impl PipewireManager {
             clock_rate: None,
             default_source: None,
             default_target: None,
+            session_manager_present: false,
+            cleanup_orphans: config.cleanup_orphans,
+            attach_defaults: config.attach_defaults,
+            auto_match: config.auto_match,
+            naming: config.naming,
+            palette: config.palette,
 
             profile: config.profile,
 
             source_map: HashMap::default(),
+            eq_map: HashMap::default(),
+            auto_muted_sources: HashSet::default(),
 
             physical_source: HashMap::default(),
             physical_target: HashMap::default(),
+            mono_sum_filters: HashMap::default(),
 
             pending_volume_syncs: HashMap::default(),
 
@@ -99,12 +197,35 @@ impl PipewireManager {
             meter_callback: meter_tx,
             meter_receiver: Some(meter_rx),
             meter_broadcast: config.meter_sender,
+            meter_levels: HashMap::new(),
+            meter_requested: false,
+            meter_ready: None,
+            meter_hold: None,
+            meter_readers: vec![],
 
             node_list: Default::default(),
             device_nodes: Default::default(),
 
             application_nodes: Default::default(),
             application_target_ignore: Default::default(),
+
+            last_xrun_cycle: None,
+            quantum: None,
+            bypassed_filters: HashSet::new(),
+            realtime_priority: config.realtime_priority,
+            realtime_status: None,
+            idle_sources: HashSet::new(),
+            mix_preview: None,
+            mix_fade: None,
+            captures: HashMap::new(),
+            capture_callback: capture_tx,
+            capture_receiver: Some(capture_rx),
+            latency_probes: HashMap::new(),
+            latency_callback: latency_tx,
+            latency_receiver: Some(latency_rx),
+            test_tones: HashMap::new(),
+            panic_muted: false,
+            pending_mutes: PendingMutes::default(),
         }
     }
 
@@ -116,8 +237,12 @@ impl PipewireManager {
     }
 
     async fn get_audio_config(&self) -> AudioConfiguration {
+        // Show queued mutes, they'll be applied before anything else can see the profile
+        let mut profile = self.profile.clone();
+        self.pending_mutes.overlay(&mut profile);
+
         AudioConfiguration {
-            profile: self.profile.clone(),
+            profile,
             devices: self.node_list.clone(),
             defaults: enum_map! {
                 DeviceType::Source => match &self.default_source {
@@ -210,7 +335,156 @@ impl PipewireManager {
                     DeviceType::Target => targets.clone(),
                 }
             },
+
+            capabilities: Capabilities {
+                session_manager_present: self.session_manager_present,
+            },
+
+            engine: EngineStatus {
+                quantum: self.quantum,
+                quantum_mismatch: self.quantum_mismatch(),
+                bypassed_filters: self.bypassed_filters.iter().copied().collect(),
+                realtime: self.realtime_status.clone(),
+            },
+
+            overlays: self.active_overlays().await,
+            attached_devices: self.attached_device_status(),
+        }
+    }
+
+    fn attached_device_status(&self) -> HashMap<Ulid, Vec<AttachedDeviceStatus>> {
+        let devices = &self.profile.devices;
+
+        let sources = devices.sources.physical_devices.iter();
+        let sources = sources.map(|d| (d.description.id, &d.attached_devices));
+        let targets = devices.targets.physical_devices.iter();
+        let targets = targets.map(|d| (d.description.id, &d.attached_devices));
+        let virtuals = devices.targets.virtual_devices.iter();
+        let virtuals = virtuals.map(|d| (d.description.id, &d.attached_devices));
+
+        sources
+            .chain(targets)
+            .chain(virtuals)
+            .map(|(id, attached)| {
+                let status = attached
+                    .iter()
+                    .map(|descriptor| AttachedDeviceStatus {
+                        descriptor: descriptor.clone(),
+                        connected: self.locate_node(descriptor.clone()).is_some(),
+                    })
+                    .collect();
+                (id, status)
+            })
+            .collect()
+    }
+
+    async fn active_overlays(&self) -> ActiveOverlays {
+        let mut auto_muted_sources: Vec<Ulid> = self.auto_muted_sources.iter().copied().collect();
+        let mut bypassed_filters: Vec<Ulid> = self.bypassed_filters.iter().copied().collect();
+        let mut test_tones: Vec<Ulid> = self.test_tones.keys().copied().collect();
+
+        // Keep these in a stable order, so they only show as changed when they have
+        auto_muted_sources.sort();
+        bypassed_filters.sort();
+        test_tones.sort();
+
+        let mut preview = None;
+        if let Some((target, mix)) = self.mix_preview {
+            preview = Some(MixPreview { target, mix });
+        }
+
+        ActiveOverlays {
+            preview,
+            auto_match_suspended: !self.auto_match,
+            auto_muted_sources,
+            bypassed_filters,
+            test_tones,
+            panic_muted: self.panic_muted,
+        }
+    }
+
+    fn resume_filter(&mut self, id: Ulid) -> Result<()> {
+        if !self.bypassed_filters.remove(&id) {
+            bail!("Filter {} is not Bypassed", id);
+        }
+
+        let message = PipewireMessage::SetFilterBypass(id, false);
+        self.pipewire().send_message(message)
+    }
+
+    fn set_panic_mute(&mut self, muted: bool) -> Result<()> {
+        self.pipewire()
+            .send_message(PipewireMessage::SetPanicMute(muted))?;
+        self.panic_muted = muted;
+        Ok(())
+    }
+
+    fn audio_quantum(&self) -> AudioQuantum {
+        let rate = self.clock_rate.unwrap_or(48000);
+        let requested = self.profile.audio_node_quantum.map(u32::from);
+        let actual = self.quantum;
+
+        let latency_ms = actual
+            .or(requested)
+            .map(|quantum| quantum as f32 * 1000. / rate as f32);
+
+        AudioQuantum {
+            requested,
+            actual,
+            rate,
+            latency_ms,
+        }
+    }
+
+    fn set_realtime_priority(&mut self, priority: Option<u8>) -> Result<()> {
+        if priority.is_some_and(|p| !(1..=99).contains(&p)) {
+            bail!("Realtime Priority must be between 1 and 99");
+        }
+        self.pipewire()
+            .send_message(PipewireMessage::SetRealtimePriority(priority))?;
+        self.realtime_priority = priority;
+        Ok(())
+    }
+
+    fn realtime_status_changed(&mut self, status: pipeweaver_pipewire::RealtimeStatus) -> bool {
+        let error = status
+            .error
+            .map(|e| std::io::Error::from_raw_os_error(e).to_string());
+        let status = RealtimeStatus {
+            requested: status.requested,
+            realtime: status.realtime,
+            priority: status.priority,
+            error,
+        };
+
+        // Every filter reports this, only handle the first
+        if self.realtime_status.as_ref() == Some(&status) {
+            return false;
         }
+
+        match (&status.requested, &status.error) {
+            (Some(requested), Some(error)) => warn!(
+                "Unable to set Filter thread to Realtime Priority {} ({}), check rtkit or RLIMIT_RTPRIO is configured",
+                requested, error
+            ),
+            _ if !status.realtime => warn!(
+                "Filter thread is not running with Realtime scheduling, this may cause crackling under load"
+            ),
+            _ => info!(
+                "Filter thread is running with Realtime Priority {}",
+                status.priority
+            ),
+        }
+
+        self.realtime_status = Some(status);
+        true
+    }
+
+    fn quantum_mismatch(&self) -> Option<QuantumMismatch> {
+        let requested: u32 = self.profile.audio_node_quantum?.into();
+        let actual = self.quantum?;
+
+        (requested != actual).then_some(QuantumMismatch { requested, actual })
     }
 
     pub async fn run(&mut self) {
@@ -228,7 +502,7 @@ impl PipewireManager {
         let receiver = thread::spawn(|| run_receiver_wrapper(recv, send_async));
 
         // Run up the Pipewire Handler
-        let pipewire = PipewireRunner::new(send.clone());
+        let pipewire = PipewireRunner::new(send.clone(), self.naming.clone());
         self.pipewire = match pipewire {
             Ok(pipewire) => Some(pipewire),
             Err(e) => {
@@ -242,6 +516,12 @@ impl PipewireManager {
             }
         };
 
+        if let Some(priority) = self.realtime_priority
+            && let Err(e) = self.set_realtime_priority(Some(priority))
+        {
+            warn!("Unable to request Realtime Priority: {}", e);
+        }
+
         // Hold until we receive a clock value
         let mut loaded_profile = false;
 
@@ -263,12 +543,28 @@ impl PipewireManager {
         let mut meter_receiver = self.meter_receiver.take().unwrap();
         let mut meter_buffer: Vec<(Ulid, u8)> = Vec::with_capacity(64);
 
+        // And the Capture Receiver
+        let mut capture_receiver = self.capture_receiver.take().unwrap();
+        let mut latency_receiver = self.latency_receiver.take().unwrap();
+
         let mut pipewire_exited = false;
 
         loop {
+            let mute_deadline = self.pending_mutes.deadline().unwrap_or_else(Instant::now);
+            let meters_deadline = self.meters_deadline().unwrap_or_else(Instant::now);
+            let fade_deadline = self.routing_fade_deadline().unwrap_or_else(Instant::now);
+            let capture_deadline = self.capture_deadline().unwrap_or_else(Instant::now);
+            let latency_deadline = self.latency_deadline().unwrap_or_else(Instant::now);
             select!(
                 biased;
                 Some(command) = self.command_receiver.recv() => {
+                    // Anything reading or changing the profile has to run after queued mutes
+                    match pending_mute_action(&command) {
+                        PendingMuteAction::Keep => {}
+                        PendingMuteAction::Apply => self.apply_pending_mutes().await,
+                        PendingMuteAction::Discard => self.pending_mutes.discard(),
+                    }
+
                     match command {
                         ManagerMessage::Execute(command, tx) => {
                             let result = self.handle_command(command).await;
@@ -283,12 +579,81 @@ impl PipewireManager {
                             let _ = tx.send(self.get_audio_config().await);
                         }
                         ManagerMessage::SetMetering(enabled) => {
-                            let _ = self.set_metering(enabled).await;
+                            let _ = self.meters_set_requested(enabled).await;
                         }
                         ManagerMessage::SetAudioQuantum(value, callback) => {
                             self.profile.audio_node_quantum = value;
                             let _ = callback.send(());
                         }
+                        ManagerMessage::CleanupOrphans(callback) => {
+                            let _ = callback.send(self.orphans_cleanup().await);
+                        }
+                        ManagerMessage::SetDefaultPalette(palette, callback) => {
+                            self.palette = palette;
+                            let _ = callback.send(());
+                        }
+                        ManagerMessage::SetRealtimePriority(priority, callback) => {
+                            let _ = callback.send(self.set_realtime_priority(priority));
+                        }
+                        ManagerMessage::SetVolumeCurve(value, callback) => {
+                            self.profile.volume_curve = value;
+                            let _ = callback.send(());
+                        }
+                        ManagerMessage::SaveEqPreset(name, id, callback) => {
+                            let _ = callback.send(self.save_eq_preset(name, id).await);
+                        }
+                        ManagerMessage::ApplyEqPreset(name, id, callback) => {
+                            let _ = callback.send(self.apply_eq_preset(name, id).await);
+                        }
+                        ManagerMessage::SnapshotVolumes(callback) => {
+                            let _ = callback.send(self.snapshot_volumes());
+                        }
+                        ManagerMessage::RestoreVolumes(snapshot, callback) => {
+                            let _ = callback.send(self.restore_volumes(snapshot).await);
+                        }
+                        ManagerMessage::RestoreProfile(profile, callback) => {
+                            let _ = callback.send(self.restore_profile(*profile).await);
+                        }
+                        ManagerMessage::PreviewMix(mix, active, callback) => {
+                            let _ = callback.send(self.routing_preview_mix(mix, active).await);
+                        }
+                        ManagerMessage::MeasureLatency(source, target, callback) => {
+                            self.latency_start(source, target, callback).await;
+                        }
+                        ManagerMessage::CaptureSample(node, duration, callback) => {
+                            self.capture_start(node, duration, callback).await;
+                        }
+                        ManagerMessage::ResumeFilter(id, callback) => {
+                            let _ = callback.send(self.resume_filter(id));
+                        }
+                        ManagerMessage::PanicMute(muted, callback) => {
+                            let _ = callback.send(self.set_panic_mute(muted));
+                        }
+                        ManagerMessage::StartTestTone(target, mix, signal, level, callback) => {
+                            let result = self.test_tone_start(target, mix, signal, level).await;
+                            let _ = callback.send(result);
+                        }
+                        ManagerMessage::StopTestTone(target, callback) => {
+                            let _ = callback.send(self.test_tone_stop(target).await);
+                        }
+                        ManagerMessage::ReconcileLinks(callback) => {
+                            let _ = callback.send(self.reconcile_links().await);
+                        }
+                        ManagerMessage::GetAudioQuantum(callback) => {
+                            let _ = callback.send(self.audio_quantum());
+                        }
+                        ManagerMessage::GetMeters(callback) => {
+                            if let Err(e) = self.meters_read(callback).await {
+                                warn!("Unable to Enable Meters: {}", e);
+                            }
+                        }
+                        ManagerMessage::GetDevicePorts(node_id, callback) => {
+                            let _ = callback.send(self.get_device_ports(node_id).await);
+                        }
+                        ManagerMessage::SetAutoMatch(enabled, callback) => {
+                            let sender = self.worker_sender.clone();
+                            let _ = callback.send(self.set_auto_match(enabled, sender).await);
+                        }
                         ManagerMessage::Quit => {
                             info!("[Manager] Stopping");
                             break;
@@ -348,6 +713,12 @@ impl PipewireManager {
                             warn!("This shouldn't happen twice!");
                         }
 
+                        PipewireReceiver::SessionManagerFound => {
+                            debug!("Session Manager Present");
+                            self.session_manager_present = true;
+                            let _ = self.worker_sender.send(TransientChange).await;
+                        }
+
                         PipewireReceiver::DefaultChanged(class, target) => {
                             match class {
                                 MediaClass::Source => {
@@ -589,6 +960,67 @@ impl PipewireManager {
                                 warn!("Unable to reestablish link: {}", e);
                             }
                         }
+                        PipewireReceiver::ManagedNodeDropped(id) => {
+                            warn!("Managed Node Removed: {}, recreating", id);
+
+                            let result = match self.is_aggregate_sink(id) {
+                                true => self.aggregate_load().await,
+                                false => self.node_recreate(id).await,
+                            };
+                            if let Err(e) = result {
+                                warn!("Unable to recreate node: {}", e);
+                            }
+                            let _ = self.worker_sender.send(TransientChange).await;
+                        }
+                        PipewireReceiver::FilterOverrun(id) => {
+                            warn!("Filter {} is taking too long to process, silencing it", id);
+                            self.bypassed_filters.insert(id);
+                            let _ = self.worker_sender.send(TransientChange).await;
+                        }
+                        PipewireReceiver::Xrun(driver, cycle, duration) => {
+                            if self.last_xrun_cycle.replace(cycle) == Some(cycle) {
+                                continue;
+                            }
+
+                            let driver_name = self.device_nodes.get(&driver).and_then(|n| n.name.clone());
+                            warn!("Xrun Detected, Driver: {} ({:?}), Duration: {}ns", driver, driver_name, duration);
+
+                            let timestamp = SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .map(|d| d.as_millis() as u64)
+                                .unwrap_or_default();
+
+                            let duration = duration / 1000;
+                            let severity = xrun_severity(duration, self.audio_quantum().latency_ms);
+                            let event = XrunEvent {
+                                timestamp,
+                                driver_id: driver,
+                                driver_name,
+                                duration,
+                                severity,
+                            };
+                            let _ = self.worker_sender.send(WorkerMessage::Xrun(event)).await;
+                        }
+                        PipewireReceiver::RealtimeStatus(status) => {
+                            if self.realtime_status_changed(status) {
+                                let _ = self.worker_sender.send(TransientChange).await;
+                            }
+                        }
+                        PipewireReceiver::QuantumChanged(quantum) => {
+                            // Every filter reports this, only handle the first
+                            if self.quantum.replace(quantum) == Some(quantum) {
+                                continue;
+                            }
+
+                            debug!("Graph Quantum is now {}", quantum);
+                            if let Some(mismatch) = self.quantum_mismatch() {
+                                warn!(
+                                    "Graph is running at a quantum of {}, but {} was requested",
+                                    mismatch.actual, mismatch.requested
+                                );
+                            }
+                            let _ = self.worker_sender.send(TransientChange).await;
+                        }
                         PipewireReceiver::ApplicationAdded(node) => {
                             if node.media_target.is_some() {
                                 // We already have a target defined, no point waiting for it.
@@ -702,11 +1134,59 @@ impl PipewireManager {
                         }
                     }
                 }
+                _ = sleep_until(mute_deadline), if self.pending_mutes.deadline().is_some() => {
+                    self.apply_pending_mutes().await;
+                    let _ = self.worker_sender.send(WorkerMessage::ProfileChanged).await;
+                }
+                _ = sleep_until(meters_deadline), if self.meters_deadline().is_some() => {
+                    self.meters_tick().await;
+                }
+                _ = sleep_until(fade_deadline), if self.mix_fade.is_some() => {
+                    self.routing_fade_tick().await;
+                }
+                _ = sleep_until(capture_deadline), if !self.captures.is_empty() => {
+                    self.capture_tick().await;
+                }
+                Some((id, samples)) = capture_receiver.recv() => {
+                    self.capture_complete(id, samples).await;
+                }
+                _ = sleep_until(latency_deadline), if !self.latency_probes.is_empty() => {
+                    self.latency_tick().await;
+                }
+                Some((id, recording)) = latency_receiver.recv() => {
+                    self.latency_complete(id, recording).await;
+                }
                 _ = Pin::as_mut(&mut initial_ready_timer), if !initial_ready => {
                     debug!("Activating Pipewire Volume Manager");
                     self.sync_all_pipewire_volumes().await;
                     self.sync_all_pipewire_mutes().await;
                     initial_ready = true;
+
+                    if !self.session_manager_present {
+                        warn!("No Session Manager (eg. WirePlumber) detected, application routing and default devices will not work");
+                    }
+
+                    // Our nodes are built, so any stream still pointing elsewhere (eg. at a node
+                    // from before a Pipewire restart) can be moved back to its mapped target
+                    if let Err(e) = self.reapply_application_targets() {
+                        warn!("Unable to Reapply Application Targets: {}", e);
+                    }
+
+                    // By now all the existing nodes should have been reported
+                    if self.attach_defaults
+                        && let Ok(true) = self.defaults_attach_first_run().await
+                    {
+                        let _ = self.worker_sender.send(WorkerMessage::ProfileChanged).await;
+                    }
+
+                    let orphans = self.orphans_find();
+                    if !orphans.is_empty() {
+                        if self.cleanup_orphans {
+                            let _ = self.orphans_cleanup().await;
+                        } else {
+                            warn!("Found {} orphaned nodes from a previous run, use CleanupOrphans to remove them", orphans.len());
+                        }
+                    }
                 }
                 Some(node_id) = application_ready_rx.recv() => {
                     // An Application has been hanging around for 200ms without receiving a route,
@@ -722,7 +1202,12 @@ impl PipewireManager {
                     }
                 }
                 result = meter_receiver.recv_many(&mut meter_buffer, 64) => {
-                    if result > 0 {
+                    self.meter_levels.extend(meter_buffer.iter().copied());
+
+                    // Nothing is listening, so there's no point building the events
+                    if result > 0 && self.meter_broadcast.receiver_count() == 0 {
+                        meter_buffer.clear();
+                    } else if result > 0 {
                         for (id, percent) in meter_buffer.drain(..result) {
                             let _ = self.meter_broadcast.send(MeterEvent {
                                 id,
@@ -774,6 +1259,22 @@ pub async fn run_pipewire_manager(config: PipewireManagerConfig, stopped: onesho
 
 pub(crate) struct PipewireManagerConfig {
     pub(crate) profile: Profile,
+    pub(crate) cleanup_orphans: bool,
+
+    /// The profile is brand new, so attach the system's default devices to it
+    pub(crate) attach_defaults: bool,
+
+    /// Automatically attach physical devices to their nodes as they appear
+    pub(crate) auto_match: bool,
+
+    /// How the nodes and filters we create are named in Pipewire
+    pub(crate) naming: NamingScheme,
+
+    /// The colours new nodes cycle through
+    pub(crate) palette: Vec<Colour>,
+
+    /// A realtime priority to request for the filter thread
+    pub(crate) realtime_priority: Option<u8>,
 
     pub(crate) command_receiver: mpsc::Receiver<ManagerMessage>,
     pub(crate) worker_sender: Sender<WorkerMessage>,
@@ -782,3 +1283,32 @@ pub(crate) struct PipewireManagerConfig {
 
     pub(crate) ready_sender: Option<oneshot::Sender<()>>,
 }
+
+/// Grades an xrun (in microseconds) against the length of a quantum. If the quantum isn't known
+/// yet there's nothing to compare against, so assume the worst rather than play it down.
+fn xrun_severity(duration: u64, latency_ms: Option<f32>) -> XrunSeverity {
+    match latency_ms {
+        Some(latency) if (duration as f32) < latency * 1000. => XrunSeverity::Minor,
+        _ => XrunSeverity::Major,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xruns_are_graded_against_the_quantum() {
+        // 1024 samples at 48kHz is a little over 21ms
+        let latency = Some(1024. * 1000. / 48000.);
+        assert_eq!(xrun_severity(500, latency), XrunSeverity::Minor);
+        assert_eq!(xrun_severity(21000, latency), XrunSeverity::Minor);
+        assert_eq!(xrun_severity(21334, latency), XrunSeverity::Major);
+        assert_eq!(xrun_severity(100000, latency), XrunSeverity::Major);
+    }
+
+    #[test]
+    fn xruns_without_a_quantum_are_major() {
+        assert_eq!(xrun_severity(10, None), XrunSeverity::Major);
+    }
+}
@@ -1,10 +1,16 @@
 use crate::handler::messaging::DaemonMessage;
 use crate::handler::pipewire::manager::{PipewireManagerConfig, run_pipewire_manager};
 use crate::handler::primary_worker::ManagerMessage::{
-    Execute, GetAudioConfiguration, SetAudioQuantum, SetMetering,
+    ApplyEqPreset, CaptureSample, CleanupOrphans, Execute, GetAudioConfiguration, GetAudioQuantum,
+    GetDevicePorts, GetMeters, MeasureLatency, PanicMute, PreviewMix, ReconcileLinks,
+    RestoreProfile, RestoreVolumes, ResumeFilter, SaveEqPreset, SetAudioQuantum, SetAutoMatch,
+    SetDefaultPalette, SetMetering, SetRealtimePriority, SetVolumeCurve, SnapshotVolumes,
+    StartTestTone, StopTestTone,
 };
+use crate::servers::audit_log::AuditLog;
 use crate::servers::http_server::{MeterEvent, PatchEvent};
-use crate::settings::{check_settings_path, save_settings};
+use crate::servers::meter_pipe::MeterPipe;
+use crate::settings::{check_settings_path, naming_scheme, save_settings};
 use crate::stop::Stop;
 use crate::{APP_DAEMON_NAME, APP_ID};
 use crate::{APP_NAME_ID, BACKGROUND_PARAM};
@@ -15,12 +21,13 @@ use ini::Ini;
 use json_patch::diff;
 use log::{debug, error, info, warn};
 use pipeweaver_ipc::commands::{
-    APICommand, AudioConfiguration, DaemonCommand, DaemonResponse, DaemonStatus, GlobalSettings,
-    PWCommandResponse,
+    APICommand, AudioConfiguration, AudioQuantum, CapturedSample, DaemonCommand, DaemonRequest,
+    DaemonResponse, DaemonStatus, DevicePorts, GlobalSettings, LatencyMeasurement, LinkReport,
+    PWCommandResponse, RoutingCell, RoutingMatrix, TestSignal, VolumeSnapshot, XrunEvent,
 };
 use pipeweaver_profile::Profile;
-use pipeweaver_shared::Quantum;
-use std::collections::HashSet;
+use pipeweaver_shared::{Colour, Mix, OrderGroup, Quantum, VolumeCurve};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::{File, create_dir_all};
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -30,17 +37,34 @@ use tokio::sync::broadcast::Sender;
 use tokio::sync::{RwLock, mpsc, oneshot, watch};
 use tokio::time::sleep;
 use tokio::{select, task, time};
+use ulid::Ulid;
 use which::which;
 
 type Manage = mpsc::Sender<ManagerMessage>;
 
+// The number of xruns we keep around for GetXrunHistory
+const MAX_XRUN_HISTORY: usize = 100;
+
 pub struct PrimaryWorker {
     last_status: Option<DaemonStatus>,
 
+    // Transient changes which happen while nobody is listening are only picked up on request
+    status_stale: bool,
+
     patch_broadcast: Sender<PatchEvent>,
     meter_broadcast: Sender<MeterEvent>,
     manager_alive: watch::Sender<bool>,
 
+    // Metering needs to stay on while either a client or the pipe wants it
+    metering_requested: bool,
+    meter_pipe: Option<MeterPipe>,
+    audit_log: Option<AuditLog>,
+
+    xrun_history: VecDeque<XrunEvent>,
+
+    // Kept here rather than the manager, so it survives the manager being restarted
+    auto_match: bool,
+
     shutdown: Stop,
     settings: Arc<RwLock<GlobalSettings>>,
 }
@@ -55,10 +79,19 @@ impl PrimaryWorker {
     ) -> Self {
         Self {
             last_status: None,
+            status_stale: false,
             patch_broadcast: patch,
             meter_broadcast: meter,
             manager_alive,
 
+            metering_requested: false,
+            meter_pipe: None,
+            audit_log: None,
+
+            xrun_history: VecDeque::with_capacity(MAX_XRUN_HISTORY),
+
+            auto_match: true,
+
             shutdown,
             settings,
         }
@@ -71,6 +104,7 @@ impl PrimaryWorker {
     ) {
         let profile_path = config_path.join(format!("{}-profile.json", APP_NAME_ID));
         let mut first_run = true;
+        let mut restored = false;
 
         'main: loop {
             let _ = self.manager_alive.send(false);
@@ -90,6 +124,7 @@ impl PrimaryWorker {
             }
 
             info!("[PrimaryWorker] Starting Primary Worker");
+            let new_profile = !profile_path.exists();
             let profile = self.load_profile(&profile_path);
 
             // Used to pass messages into the Pipewire Manager
@@ -103,8 +138,15 @@ impl PrimaryWorker {
             let mut profile_tick = time::interval(Duration::from_secs(5));
 
             debug!("[PrimaryWorker] Spawning Pipewire Task..");
+            let naming = naming_scheme(&self.settings.read().await.naming);
             let config = PipewireManagerConfig {
                 profile,
+                cleanup_orphans: self.settings.read().await.auto_cleanup_orphans,
+                attach_defaults: new_profile,
+                auto_match: self.auto_match,
+                naming,
+                palette: self.settings.read().await.default_palette.clone(),
+                realtime_priority: self.settings.read().await.realtime_priority,
 
                 command_receiver,
                 worker_sender,
@@ -122,8 +164,9 @@ impl PrimaryWorker {
 
             debug!("[PrimaryWorker] Pipewire Manager Ready, Loading Initial Status");
 
-            // Load the initial status
-            self.update_status(&command_sender, true).await;
+            // Load the initial status, if we've restored a profile clients will need the diff
+            self.update_status(&command_sender, !restored).await;
+            restored = false;
             let mut profile_changed = false;
 
             // Set the manager as alive
@@ -149,6 +192,15 @@ impl PrimaryWorker {
                                 }
                                 let _ = command_sender.send(ManagerMessage::Quit).await;
                             }
+                            MessageResult::Restore(profile) => {
+                                // Replace the saved profile, and restart the manager to load it
+                                info!("[PrimaryWorker] Restoring Profile, Restarting Pipewire Manager");
+                                let _ = self.save_profile(&profile_path, &profile);
+                                profile_changed = false;
+                                restored = true;
+
+                                let _ = command_sender.send(ManagerMessage::Quit).await;
+                            }
                             MessageResult::None => {}
                         }
                     }
@@ -157,8 +209,13 @@ impl PrimaryWorker {
                         match message {
                             WorkerMessage::TransientChange => {
                                 // A physical device has changed, we need to update the main
-                                // status to include it.
-                                self.update_status(&command_sender, false).await;
+                                // status to include it. If no one is subscribed, there's no one
+                                // to tell, so wait until the status is next asked for.
+                                if self.patch_broadcast.receiver_count() == 0 {
+                                    self.status_stale = true;
+                                } else {
+                                    self.update_status(&command_sender, false).await;
+                                }
                             }
                             WorkerMessage::ProfileChanged => {
                                 // Something's been changed in the Profile
@@ -172,6 +229,15 @@ impl PrimaryWorker {
 
                                 continue 'main;
                             }
+                            WorkerMessage::Xrun(event) => {
+                                if self.xrun_history.len() == MAX_XRUN_HISTORY {
+                                    self.xrun_history.pop_front();
+                                }
+                                self.xrun_history.push_back(event.clone());
+
+                                let data = DaemonResponse::XrunOccurred(event);
+                                let _ = self.patch_broadcast.send(PatchEvent { data });
+                            }
                         }
                     }
 
@@ -210,6 +276,10 @@ impl PrimaryWorker {
         let mut update = false;
         let mut reset = false;
 
+        if self.status_stale {
+            self.update_status(pw_tx, false).await;
+        }
+
         match message {
             DaemonMessage::GetStatus(tx) => {
                 if let Some(status) = &self.last_status {
@@ -221,11 +291,73 @@ impl PrimaryWorker {
             DaemonMessage::RunDaemon(command, tx) => {
                 match command {
                     DaemonCommand::SetMetering(enabled) => {
+                        self.metering_requested = enabled;
+                        let enabled = enabled || self.meter_pipe.is_some();
                         let _ = pw_tx.send(SetMetering(enabled)).await;
                     }
+                    DaemonCommand::SetMeterPipe(path) => {
+                        // Drop any existing pipe before we create a new one
+                        self.meter_pipe = None;
+                        if let Some(path) = path {
+                            match MeterPipe::start(path, &self.meter_broadcast) {
+                                Ok(pipe) => self.meter_pipe = Some(pipe),
+                                Err(e) => {
+                                    let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                                    return MessageResult::None;
+                                }
+                            }
+                        }
+
+                        let enabled = self.metering_requested || self.meter_pipe.is_some();
+                        let _ = pw_tx.send(SetMetering(enabled)).await;
+                    }
+                    DaemonCommand::SetAuditLog(path) => {
+                        // Stop the existing log first, so the new one takes over the sender
+                        self.audit_log = None;
+                        if let Some(path) = path {
+                            match AuditLog::start(path) {
+                                Ok(log) => self.audit_log = Some(log),
+                                Err(e) => {
+                                    let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                                    return MessageResult::None;
+                                }
+                            }
+                        }
+                    }
                     DaemonCommand::SetUseBrowser(enabled) => {
                         self.settings.write().await.use_browser = enabled;
-                        let _ = save_settings(*self.settings.read().await);
+                        let _ = save_settings(self.settings.read().await.clone());
+                    }
+                    DaemonCommand::SetAutoCleanupOrphans(enabled) => {
+                        self.settings.write().await.auto_cleanup_orphans = enabled;
+                        let _ = save_settings(self.settings.read().await.clone());
+                    }
+                    DaemonCommand::SetDefaultPalette(palette) => {
+                        self.settings.write().await.default_palette = palette.clone();
+                        let _ = save_settings(self.settings.read().await.clone());
+
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(SetDefaultPalette(palette, cmd_tx)).await;
+                        let _ = cmd_rx.await;
+                    }
+                    DaemonCommand::SetRealtimePriority(priority) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(SetRealtimePriority(priority, cmd_tx)).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
+
+                        self.settings.write().await.realtime_priority = priority;
+                        let _ = save_settings(self.settings.read().await.clone());
+                    }
+                    DaemonCommand::CleanupOrphans => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(CleanupOrphans(cmd_tx)).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
                     }
                     DaemonCommand::SetAudioQuantum(value) => {
                         let (tx, rx) = oneshot::channel();
@@ -234,6 +366,14 @@ impl PrimaryWorker {
 
                         reset = true;
                     }
+                    DaemonCommand::SetVolumeCurve(value) => {
+                        // Node volumes are built with the curve, so they need to be recreated
+                        let (tx, rx) = oneshot::channel();
+                        let _ = pw_tx.send(SetVolumeCurve(value, tx)).await;
+                        let _ = rx.await;
+
+                        reset = true;
+                    }
                     DaemonCommand::OpenInterface => {
                         let force_browser = self.settings.read().await.use_browser;
 
@@ -273,6 +413,136 @@ impl PrimaryWorker {
                             warn!("Unable to open web interface: {}", e);
                         }
                     }
+                    DaemonCommand::SaveEqPreset(name, id) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(SaveEqPreset(name, id, cmd_tx)).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
+                    }
+                    DaemonCommand::ApplyEqPreset(name, id) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(ApplyEqPreset(name, id, cmd_tx)).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
+                    }
+                    DaemonCommand::SnapshotVolumes => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(SnapshotVolumes(cmd_tx)).await;
+                        let response = match cmd_rx.await {
+                            Ok(snapshot) => DaemonResponse::VolumeSnapshot(snapshot),
+                            Err(e) => DaemonResponse::Err(e.to_string()),
+                        };
+
+                        // Nothing has changed, so no need to update the state
+                        let _ = tx.send(response);
+                        return MessageResult::None;
+                    }
+                    DaemonCommand::RestoreVolumes(snapshot) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(RestoreVolumes(snapshot, cmd_tx)).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
+                    }
+                    DaemonCommand::MeasureLatency(source, target) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(MeasureLatency(source, target, cmd_tx)).await;
+                        let response = match cmd_rx.await {
+                            Ok(Ok(measurement)) => DaemonResponse::Latency(measurement),
+                            Ok(Err(e)) => DaemonResponse::Err(e.to_string()),
+                            Err(e) => DaemonResponse::Err(e.to_string()),
+                        };
+
+                        // The probe is cleaned up afterwards, so nothing has changed
+                        let _ = tx.send(response);
+                        return MessageResult::None;
+                    }
+                    DaemonCommand::CaptureSample(node, duration) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(CaptureSample(node, duration, cmd_tx)).await;
+                        let response = match cmd_rx.await {
+                            Ok(Ok(sample)) => DaemonResponse::Sample(sample),
+                            Ok(Err(e)) => DaemonResponse::Err(e.to_string()),
+                            Err(e) => DaemonResponse::Err(e.to_string()),
+                        };
+
+                        let _ = tx.send(response);
+                        return MessageResult::None;
+                    }
+                    DaemonCommand::ReconcileLinks => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(ReconcileLinks(cmd_tx)).await;
+                        let response = match cmd_rx.await {
+                            Ok(Ok(report)) => DaemonResponse::LinkReport(report),
+                            Ok(Err(e)) => DaemonResponse::Err(e.to_string()),
+                            Err(e) => DaemonResponse::Err(e.to_string()),
+                        };
+
+                        // Links aren't part of the status, so there's nothing to patch
+                        let _ = tx.send(response);
+                        return MessageResult::None;
+                    }
+                    DaemonCommand::ResumeFilter(id) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(ResumeFilter(id, cmd_tx)).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
+                    }
+                    DaemonCommand::PanicMute(muted) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(PanicMute(muted, cmd_tx)).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
+                    }
+                    DaemonCommand::StartTestTone(target, mix, signal, level) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let message = StartTestTone(target, mix, signal, level, cmd_tx);
+                        let _ = pw_tx.send(message).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
+                    }
+                    DaemonCommand::StopTestTone(target) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(StopTestTone(target, cmd_tx)).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
+                    }
+                    DaemonCommand::SetAutoMatch(enabled) => {
+                        self.auto_match = enabled;
+
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(SetAutoMatch(enabled, cmd_tx)).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
+                    }
+                    DaemonCommand::PreviewMix(mix, active) => {
+                        let (cmd_tx, cmd_rx) = oneshot::channel();
+                        let _ = pw_tx.send(PreviewMix(mix, active, cmd_tx)).await;
+                        if let Ok(Err(e)) = cmd_rx.await {
+                            let _ = tx.send(DaemonResponse::Err(e.to_string()));
+                            return MessageResult::None;
+                        }
+
+                        // Previews aren't part of the profile, so there's nothing to save. The
+                        // manager reports the change once the fade has switched the mix.
+                        let _ = tx.send(DaemonResponse::Ok);
+                        return MessageResult::None;
+                    }
                     DaemonCommand::ResetAudio => reset = true,
                     DaemonCommand::SetAutoStart(enabled) => {
                         let _ = set_autostart(enabled).await;
@@ -297,6 +567,39 @@ impl PrimaryWorker {
                     }
                 }
             }
+            DaemonMessage::RunTransaction(requests, tx) => {
+                return self.run_transaction(pw_tx, requests, tx).await;
+            }
+            DaemonMessage::GetXrunHistory(tx) => {
+                let _ = tx.send(self.xrun_history.iter().cloned().collect());
+            }
+            DaemonMessage::GetRoutingMatrix(tx) => {
+                let matrix = self
+                    .last_status
+                    .as_ref()
+                    .map(|s| routing_matrix(&s.audio.profile));
+                let _ = tx.send(matrix.unwrap_or_default());
+            }
+            DaemonMessage::GetDevicePorts(node_id, tx) => {
+                let (cmd_tx, cmd_rx) = oneshot::channel();
+                let _ = pw_tx.send(GetDevicePorts(node_id, cmd_tx)).await;
+                let result = cmd_rx.await.unwrap_or_else(|e| Err(anyhow!(e)));
+                let _ = tx.send(result);
+            }
+            DaemonMessage::GetAudioQuantum(tx) => {
+                let (cmd_tx, cmd_rx) = oneshot::channel();
+                let _ = pw_tx.send(GetAudioQuantum(cmd_tx)).await;
+                let _ = tx.send(cmd_rx.await.unwrap_or_default());
+            }
+            DaemonMessage::GetMeters(tx) => {
+                let (cmd_tx, cmd_rx) = oneshot::channel();
+                let _ = pw_tx.send(GetMeters(cmd_tx)).await;
+                let _ = tx.send(cmd_rx.await.unwrap_or_default());
+            }
+            DaemonMessage::GetActiveOverlays(tx) => {
+                let overlays = self.last_status.as_ref().map(|s| s.audio.overlays.clone());
+                let _ = tx.send(overlays.unwrap_or_default());
+            }
         }
         if reset {
             return MessageResult::Reset;
@@ -307,6 +610,105 @@ impl PrimaryWorker {
         MessageResult::None
     }
 
+    async fn run_transaction(
+        &mut self,
+        pw_tx: &Manage,
+        requests: Vec<DaemonRequest>,
+        tx: oneshot::Sender<Vec<DaemonResponse>>,
+    ) -> MessageResult {
+        // Only changes to the profile can be rolled back, so anything else (settings, one off
+        // actions, or anything which restarts the manager) can't be part of a transaction.
+        // Refuse the whole thing before we touch anything.
+        for request in &requests {
+            let allowed = match request {
+                DaemonRequest::Daemon(command) => matches!(
+                    command,
+                    DaemonCommand::SaveEqPreset(..)
+                        | DaemonCommand::ApplyEqPreset(..)
+                        | DaemonCommand::RestoreVolumes(_)
+                ),
+                DaemonRequest::Pipewire(_) => true,
+                _ => false,
+            };
+            if !allowed {
+                let error = format!("{:?} cannot be used in a Transaction", request);
+                let _ = tx.send(vec![DaemonResponse::Err(error)]);
+                return MessageResult::None;
+            }
+        }
+
+        let Some(snapshot) = self.last_status.as_ref().map(|s| s.audio.profile.clone()) else {
+            let error = "Unable to Snapshot Profile, Transaction not run".to_string();
+            let _ = tx.send(vec![DaemonResponse::Err(error)]);
+            return MessageResult::None;
+        };
+
+        let mut results = vec![];
+        let mut changed = false;
+        let mut failed = false;
+        for request in requests {
+            if failed {
+                results.push(DaemonResponse::Err(
+                    "Not Run, Transaction Failed".to_string(),
+                ));
+                continue;
+            }
+
+            // Run these through the normal handler, but don't report changes until the end
+            let (response, result) = match request {
+                DaemonRequest::Daemon(command) => {
+                    let (cmd_tx, cmd_rx) = oneshot::channel();
+                    let message = DaemonMessage::RunDaemon(command, cmd_tx);
+                    let result = Box::pin(self.handle_message(pw_tx, message)).await;
+                    let response = cmd_rx
+                        .await
+                        .unwrap_or_else(|e| DaemonResponse::Err(e.to_string()));
+                    (response, result)
+                }
+                DaemonRequest::Pipewire(command) => {
+                    let (cmd_tx, cmd_rx) = oneshot::channel();
+                    let message = DaemonMessage::RunPipewire(command, cmd_tx);
+                    let result = Box::pin(self.handle_message(pw_tx, message)).await;
+                    let response = cmd_rx
+                        .await
+                        .unwrap_or_else(|e| PWCommandResponse::Err(e.to_string()));
+                    (DaemonResponse::Pipewire(response), result)
+                }
+                _ => unreachable!("Transaction requests are checked before running"),
+            };
+
+            failed = matches!(
+                response,
+                DaemonResponse::Err(_) | DaemonResponse::Pipewire(PWCommandResponse::Err(_))
+            );
+            if !failed && matches!(result, MessageResult::UpdateState) {
+                changed = true;
+            }
+            results.push(response);
+        }
+        let _ = tx.send(results);
+
+        if failed && changed {
+            // Something before the failure changed the setup, put back the profile we had when
+            // we started. Where only routes, mutes, mixes and volumes changed that's done in
+            // place, otherwise the manager has to be reloaded from it.
+            warn!("[Transaction] Command Failed, Rolling back Profile");
+            let (cmd_tx, cmd_rx) = oneshot::channel();
+            let message = RestoreProfile(Box::new(snapshot.clone()), cmd_tx);
+            let _ = pw_tx.send(message).await;
+            match cmd_rx.await {
+                Ok(Ok(true)) => return MessageResult::UpdateState,
+                Ok(Err(e)) => warn!("[Transaction] Unable to Restore in place: {}", e),
+                _ => {}
+            }
+            return MessageResult::Restore(Box::new(snapshot));
+        }
+        if changed {
+            return MessageResult::UpdateState;
+        }
+        MessageResult::None
+    }
+
     async fn update_status(&mut self, pw_tx: &Manage, initial: bool) {
         let mut status = DaemonStatus::default();
 
@@ -329,20 +731,38 @@ impl PrimaryWorker {
             warn!("Unable to obtain autostart status: {}", e);
             false
         });
-        status.config.global_settings = *self.settings.read().await;
+        status.config.global_settings = self.settings.read().await.clone();
+        status.config.auto_match = self.auto_match;
 
         if self.patch_broadcast.receiver_count() > 0 && !initial {
+            let previous = self.last_status.as_ref().map(|s| &s.audio.overlays);
+            if previous.is_some_and(|overlays| overlays != &status.audio.overlays) {
+                let data = DaemonResponse::OverlaysChanged(status.audio.overlays.clone());
+                let _ = self.patch_broadcast.send(PatchEvent { data });
+            }
+
+            if let Some(previous) = &self.last_status {
+                let previous = routing_matrix(&previous.audio.profile);
+                let matrix = routing_matrix(&status.audio.profile);
+                if previous != matrix {
+                    let data = DaemonResponse::RoutingMatrixChanged(matrix);
+                    let _ = self.patch_broadcast.send(PatchEvent { data });
+                }
+            }
+
             let previous = serde_json::to_value(&self.last_status).unwrap();
             let new = serde_json::to_value(&status).unwrap();
 
             let patch = diff(&previous, &new);
             if !patch.is_empty() {
                 // Something has changed in our config, broadcast it to listeners
-                let _ = self.patch_broadcast.send(PatchEvent { data: patch });
+                let data = DaemonResponse::Patch(patch);
+                let _ = self.patch_broadcast.send(PatchEvent { data });
             }
         }
 
         self.last_status = Some(status);
+        self.status_stale = false;
     }
 
     fn load_profile(&self, path: &PathBuf) -> Profile {
@@ -429,6 +849,51 @@ impl PrimaryWorker {
 }
 
 static UI_NAME: &str = "pipeweaver-app";
+fn routing_matrix(profile: &Profile) -> RoutingMatrix {
+    // Pinned devices are shown first, and Hidden last
+    let groups = [OrderGroup::Pinned, OrderGroup::Default, OrderGroup::Hidden];
+    let sources: Vec<Ulid> = groups
+        .iter()
+        .flat_map(|group| profile.devices.sources.device_order[*group].iter().copied())
+        .collect();
+    let targets: Vec<Ulid> = groups
+        .iter()
+        .flat_map(|group| profile.devices.targets.device_order[*group].iter().copied())
+        .collect();
+
+    let cells = sources
+        .iter()
+        .map(|source| {
+            let routes = profile.routes.get(source);
+            targets
+                .iter()
+                .map(|target| {
+                    let connected = routes.is_some_and(|routes| routes.contains(target));
+                    let channel_pair = profile
+                        .devices
+                        .targets
+                        .virtual_devices
+                        .iter()
+                        .find(|device| device.description.id == *target)
+                        .filter(|device| connected && device.channels.is_some())
+                        .map(|device| *device.channel_routes.get(source).unwrap_or(&0));
+
+                    RoutingCell {
+                        connected,
+                        channel_pair,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    RoutingMatrix {
+        sources,
+        targets,
+        cells,
+    }
+}
+
 pub fn get_ui_app_path() -> Option<PathBuf> {
     // This simply looks for the Pipeweaver UI App alongside the daemon binary and returns it..
     let mut path = None;
@@ -570,6 +1035,7 @@ pub fn get_autostart_file() -> Result<PathBuf> {
 pub enum MessageResult {
     UpdateState,
     Reset,
+    Restore(Box<Profile>),
     None,
 }
 
@@ -579,6 +1045,33 @@ pub enum ManagerMessage {
     GetAudioConfiguration(oneshot::Sender<AudioConfiguration>),
     SetMetering(bool),
     SetAudioQuantum(Option<Quantum>, oneshot::Sender<()>),
+    SetVolumeCurve(VolumeCurve, oneshot::Sender<()>),
+    SetDefaultPalette(Vec<Colour>, oneshot::Sender<()>),
+    SetRealtimePriority(Option<u8>, oneshot::Sender<Result<()>>),
+    CleanupOrphans(oneshot::Sender<Result<usize>>),
+    SaveEqPreset(String, Ulid, oneshot::Sender<Result<()>>),
+    ApplyEqPreset(String, Ulid, oneshot::Sender<Result<()>>),
+    SnapshotVolumes(oneshot::Sender<VolumeSnapshot>),
+    RestoreVolumes(VolumeSnapshot, oneshot::Sender<Result<()>>),
+    RestoreProfile(Box<Profile>, oneshot::Sender<Result<bool>>),
+    PreviewMix(Mix, bool, oneshot::Sender<Result<()>>),
+    SetAutoMatch(bool, oneshot::Sender<Result<()>>),
+    MeasureLatency(Ulid, Ulid, oneshot::Sender<Result<LatencyMeasurement>>),
+    CaptureSample(Ulid, u32, oneshot::Sender<Result<CapturedSample>>),
+    ResumeFilter(Ulid, oneshot::Sender<Result<()>>),
+    PanicMute(bool, oneshot::Sender<Result<()>>),
+    StartTestTone(
+        Ulid,
+        Option<Mix>,
+        TestSignal,
+        u8,
+        oneshot::Sender<Result<()>>,
+    ),
+    StopTestTone(Ulid, oneshot::Sender<Result<()>>),
+    GetDevicePorts(u32, oneshot::Sender<Result<DevicePorts>>),
+    GetAudioQuantum(oneshot::Sender<AudioQuantum>),
+    GetMeters(oneshot::Sender<HashMap<Ulid, u8>>),
+    ReconcileLinks(oneshot::Sender<Result<LinkReport>>),
     Quit,
 }
 
@@ -586,6 +1079,7 @@ pub enum WorkerMessage {
     TransientChange,
     ProfileChanged,
     ManagerStopped,
+    Xrun(XrunEvent),
 }
 
 pub async fn start_primary_worker(
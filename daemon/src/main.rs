@@ -17,8 +17,8 @@ use directories::ProjectDirs;
 use file_rotate::compression::Compression;
 use file_rotate::suffix::AppendCount;
 use file_rotate::{ContentLimit, FileRotate};
-use log::{LevelFilter, error, info};
-use pipeweaver_ipc::commands::{DaemonCommand, HttpSettings};
+use log::{LevelFilter, info};
+use pipeweaver_ipc::commands::DaemonCommand;
 use simplelog::{
     ColorChoice, CombinedLogger, ConfigBuilder, SharedLogger, TermLogger, TerminalMode, WriteLogger,
 };
@@ -115,14 +115,8 @@ async fn main() -> Result<()> {
     let ipc_socket = bind_socket().await;
     if let Err(e) = ipc_socket {
         match e.downcast_ref() {
-            Some(ErrorState::AlreadyRunning) => {
-                info!("Pipeweaver already running, triggering interface.");
-                return Ok(());
-            }
-            _ => {
-                error!("Error Starting Daemon: {}", e);
-                bail!("Error Starting Daemon: {}", e);
-            }
+            Some(ErrorState::AlreadyRunning(_)) => bail!("{}", e),
+            _ => bail!("Error Starting Daemon: {}", e),
         }
     }
 
@@ -136,12 +130,7 @@ async fn main() -> Result<()> {
     ));
 
     // Prepare the HTTP Server
-    let http_settings = HttpSettings {
-        enabled: true,
-        bind_address: "0.0.0.0".to_string(),
-        cors_enabled: false,
-        port: 14565,
-    };
+    let http_settings = global_settings.read().await.http_settings.clone();
 
     let (httpd_tx, httpd_rx) = tokio::sync::oneshot::channel();
     let (meter_tx, meter_rx) = broadcast::channel(32);
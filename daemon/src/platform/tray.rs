@@ -4,6 +4,7 @@ use anyhow::Result;
 use image::GenericImageView;
 
 use crate::handler::packet::{Messenger, handle_packet};
+use crate::servers::audit_log::audit_request;
 use crate::stop::Stop;
 use ksni::menu::StandardItem;
 use ksni::{Category, Icon, MenuItem, Status, ToolTip, Tray, TrayMethods};
@@ -36,6 +37,7 @@ pub async fn spawn_tray(mut shutdown: Stop, sender: Messenger) -> Result<()> {
                     TrayMessages::Activate => {
                         debug!("Activate Triggered");
                         let message = DaemonRequest::Daemon(DaemonCommand::OpenInterface);
+                        audit_request("tray", &message);
                         handle_packet(message, &sender).await?;
                     },
                     TrayMessages::Quit => {
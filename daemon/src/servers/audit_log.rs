@@ -0,0 +1,177 @@
+use crate::APP_NAME_ID;
+use anyhow::{Result, anyhow, bail};
+use directories::ProjectDirs;
+use log::{debug, info, warn};
+use pipeweaver_ipc::commands::DaemonRequest;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+// Once the log passes this size, it's moved to <path>.1 and a fresh one is started
+const MAX_AUDIT_BYTES: u64 = 5 * 1024 * 1024;
+
+// Entries are dropped rather than queued if the writer falls this far behind
+const AUDIT_QUEUE: usize = 256;
+
+// Set while an Audit Log is running, so every connection can record into it
+static AUDIT_SENDER: RwLock<Option<mpsc::Sender<AuditEntry>>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Milliseconds since the Unix Epoch
+    pub timestamp: u128,
+
+    /// Where the request came from (eg. ipc, websocket, http)
+    pub source: String,
+
+    pub request: DaemonRequest,
+}
+
+/// Records a request to the Audit Log, if one is running. This never waits on the writer, so
+/// if it can't keep up the entry is simply dropped.
+pub fn audit_request(source: &str, request: &DaemonRequest) {
+    let Ok(sender) = AUDIT_SENDER.read() else {
+        return;
+    };
+    let Some(sender) = sender.as_ref() else {
+        return;
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+
+    let entry = AuditEntry {
+        timestamp,
+        source: source.to_string(),
+        request: redact(request),
+    };
+
+    if let Err(TrySendError::Full(_)) = sender.try_send(entry) {
+        debug!("[Audit] Writer is behind, dropping entry");
+    }
+}
+
+/// Strips anything secret from a request before it's written. No request currently carries a
+/// credential, authentication happens at the connection rather than in the request body, so
+/// there's nothing to remove yet. Anything added to DaemonRequest which does carry one must be
+/// blanked here, as the log is meant to be attached to bug reports.
+fn redact(request: &DaemonRequest) -> DaemonRequest {
+    request.clone()
+}
+
+/// Audit Logs are only ever read and written in the daemon's own audit directory, clients only
+/// get to pick the file name. Otherwise any client could write (or read back) any file the
+/// daemon has access to.
+pub fn resolve_audit_path(name: &Path) -> Result<PathBuf> {
+    let mut components = name.components();
+    let (Some(Component::Normal(file)), None) = (components.next(), components.next()) else {
+        bail!("Audit Logs must be given as a file name, not a path");
+    };
+
+    let dirs = ProjectDirs::from("io", "github", APP_NAME_ID)
+        .ok_or(anyhow!("Unable to locate project directory"))?;
+    let dir = dirs.data_dir().join("audit");
+    fs::create_dir_all(&dir).map_err(|e| anyhow!("Unable to create {}: {}", dir.display(), e))?;
+    Ok(dir.join(file))
+}
+
+/// Reads the requests back out of an Audit Log, in the order they were recorded
+pub fn read_audit_log(name: &Path) -> Result<Vec<AuditEntry>> {
+    let path = resolve_audit_path(name)?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Unable to read {}: {}", path.display(), e))?;
+
+    let mut entries = vec![];
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(line)
+            .map_err(|e| anyhow!("Invalid Audit entry on line {}: {}", index + 1, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Writes every incoming request to a file as newline delimited JSON, giving a trace which can
+/// be handed back to the daemon with ReplayAudit to reproduce a problem.
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn start(name: PathBuf) -> Result<Self> {
+        let path = resolve_audit_path(&name)?;
+        let file = open_log(&path)?;
+
+        info!("[Audit] Writing Requests to {}", path.display());
+        let (tx, mut rx) = mpsc::channel::<AuditEntry>(AUDIT_QUEUE);
+        let task_path = path.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut file = file;
+            while let Some(entry) = rx.blocking_recv() {
+                let Ok(mut line) = serde_json::to_vec(&entry) else {
+                    continue;
+                };
+                line.push(b'\n');
+
+                if let Err(e) = file.write_all(&line) {
+                    warn!("[Audit] Unable to write to {}: {}", task_path.display(), e);
+                    break;
+                }
+
+                if file.metadata().is_ok_and(|m| m.len() > MAX_AUDIT_BYTES) {
+                    match rotate_log(&task_path) {
+                        Ok(new_file) => file = new_file,
+                        Err(e) => {
+                            warn!("[Audit] Unable to rotate {}: {}", task_path.display(), e);
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        if let Ok(mut sender) = AUDIT_SENDER.write() {
+            *sender = Some(tx);
+        }
+        Ok(Self { path })
+    }
+}
+
+impl Drop for AuditLog {
+    fn drop(&mut self) {
+        // Dropping the sender closes the channel, letting the writer finish what's queued
+        if let Ok(mut sender) = AUDIT_SENDER.write() {
+            *sender = None;
+        }
+        info!(
+            "[Audit] Stopped writing Requests to {}",
+            self.path.display()
+        );
+    }
+}
+
+fn open_log(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow!("Unable to open {}: {}", path.display(), e))
+}
+
+fn rotate_log(path: &Path) -> Result<File> {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+
+    fs::rename(path, PathBuf::from(rotated))?;
+    open_log(path)
+}
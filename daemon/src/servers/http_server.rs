@@ -1,5 +1,6 @@
 use crate::APP_NAME;
-use crate::handler::packet::{Messenger, handle_packet};
+use crate::handler::packet::{ConnectionState, Messenger, handle_packet};
+use crate::servers::audit_log::audit_request;
 use actix_cors::Cors;
 use actix_web::dev::ServerHandle;
 use actix_web::http::header::ContentType;
@@ -10,7 +11,6 @@ use actix_ws::{AggregatedMessage, CloseCode, CloseReason, Session};
 use anyhow::{Result, anyhow};
 use futures_lite::StreamExt;
 use include_dir::{Dir, include_dir};
-use json_patch::Patch;
 use log::{debug, error, info, warn};
 use mime_guess::MimeGuess;
 use pipeweaver_ipc::commands::DaemonCommand::SetMetering;
@@ -19,25 +19,41 @@ use pipeweaver_ipc::commands::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
+use std::future;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use tokio::sync::broadcast::Sender as BroadcastSender;
 use tokio::sync::oneshot::Sender;
 use tokio::sync::{RwLock, watch};
+use tokio::time;
+use tokio::time::{Interval, MissedTickBehavior};
 use ulid::Ulid;
 
 const WEB_CONTENT: Dir = include_dir!("./daemon/web-content/");
 type ClientCounter = Arc<AtomicUsize>;
 
+// The highest rate (in Hz) a meter client can request
+const MAX_METER_RATE: u32 = 120;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MeterEvent {
     pub(crate) id: Ulid,
     pub(crate) percent: u8,
 }
 
+/// Options for the meter websocket, passed as query parameters on connect
+#[derive(Debug, Deserialize)]
+struct MeterOptions {
+    /// If set, meter values are sent at most this many times a second, rather than as they arrive
+    rate: Option<u32>,
+}
+
+/// Sent to every connected client, usually a Patch to the status
 #[derive(Debug, Clone)]
 pub struct PatchEvent {
-    pub data: Patch,
+    pub data: DaemonResponse,
 }
 
 #[derive(Serialize)]
@@ -133,10 +149,12 @@ async fn websocket(
 
     let usb_tx = data.messenger.clone();
     let mut broadcast_rx = data.broadcast_tx.subscribe();
+    let source = connection_source("websocket", &req);
     let mut manager_alive = data.manager_alive.clone();
 
     actix_web::rt::spawn(async move {
         let mut msg_stream = msg_stream.aggregate_continuations();
+        let mut state = ConnectionState::default();
         let close_reason = loop {
             tokio::select! {
                 changed = manager_alive.changed() => {
@@ -148,10 +166,10 @@ async fn websocket(
                     }
                 }
 
-                Ok(patch) = broadcast_rx.recv() => {
+                Ok(event) = broadcast_rx.recv() => {
                     let message = WsResponse(WebsocketResponse {
                         id: u64::MAX,
-                        data: DaemonResponse::Patch(patch.data),
+                        data: event.data,
                     });
                     if let Err(e) = send_message(&message, &mut session).await {
                         break e;
@@ -172,7 +190,12 @@ async fn websocket(
                             match serde_json::from_slice::<WebsocketRequest>(msg.as_ref()) {
                                 Ok(request) => {
                                     let request_id = request.id;
-                                    let result = handle_packet(request.data, &usb_tx).await;
+                                    audit_request(&source, &request.data);
+                                    let result = match state.check_request(&request.data) {
+                                        Ok(()) => handle_packet(request.data, &usb_tx).await,
+                                        Err(e) => Err(e),
+                                    };
+                                    let mut rejected = false;
                                     let response = match result {
                                         Ok(resp) => {
                                             match resp {
@@ -188,18 +211,97 @@ async fn websocket(
                                                         data: DaemonResponse::Err(error),
                                                     })
                                                 }
+                                                DaemonResponse::Hello(hello) => {
+                                                    rejected = !hello.compatible;
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::Hello(hello),
+                                                    })
+                                                }
                                                 DaemonResponse::Status(status) => {
                                                     WsResponse(WebsocketResponse {
                                                         id: request_id,
                                                         data: DaemonResponse::Status(status),
                                                     })
                                                 }
+                                                DaemonResponse::Version(version) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::Version(version),
+                                                    })
+                                                }
+                                                DaemonResponse::VolumeSnapshot(snapshot) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::VolumeSnapshot(snapshot),
+                                                    })
+                                                }
+                                                DaemonResponse::Latency(latency) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::Latency(latency),
+                                                    })
+                                                }
+                                                DaemonResponse::Sample(sample) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::Sample(sample),
+                                                    })
+                                                }
                                                 DaemonResponse::Pipewire(result) => {
                                                     WsResponse(WebsocketResponse {
                                                         id: request_id,
                                                         data: DaemonResponse::Pipewire(result),
                                                     })
                                                 }
+                                                DaemonResponse::Transaction(results) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::Transaction(results),
+                                                    })
+                                                }
+                                                DaemonResponse::XrunHistory(history) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::XrunHistory(history),
+                                                    })
+                                                }
+                                                DaemonResponse::ActiveOverlays(overlays) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::ActiveOverlays(overlays),
+                                                    })
+                                                }
+                                                DaemonResponse::RoutingMatrix(matrix) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::RoutingMatrix(matrix),
+                                                    })
+                                                }
+                                                DaemonResponse::DevicePorts(ports) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::DevicePorts(ports),
+                                                    })
+                                                }
+                                                DaemonResponse::AudioQuantum(quantum) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::AudioQuantum(quantum),
+                                                    })
+                                                }
+                                                DaemonResponse::Meters(meters) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::Meters(meters),
+                                                    })
+                                                }
+                                                DaemonResponse::LinkReport(report) => {
+                                                    WsResponse(WebsocketResponse {
+                                                        id: request_id,
+                                                        data: DaemonResponse::LinkReport(report),
+                                                    })
+                                                }
                                                 _ => {
                                                     // This should never fucking happen
                                                     break Some(CloseReason {
@@ -219,6 +321,12 @@ async fn websocket(
                                     if let Err(e) = send_message(&response, &mut session).await {
                                         break e;
                                     }
+                                    if rejected {
+                                        break Some(CloseReason {
+                                            code: CloseCode::Policy,
+                                            description: Some("Incompatible Protocol Version".to_string()),
+                                        });
+                                    }
                                 }
                                 Err(error) => {
                                     // Ok, we weren't able to deserialise the request into a proper object, we
@@ -289,6 +397,7 @@ async fn websocket(
 #[get("/api/websocket/meter")]
 async fn websocket_meter(
     app_data: Data<RwLock<AppData>>,
+    options: web::Query<MeterOptions>,
     req: HttpRequest,
     body: web::Payload,
 ) -> Result<HttpResponse, actix_web::Error> {
@@ -312,6 +421,16 @@ async fn websocket_meter(
     let mut meter_rx = data.meter_tx.subscribe();
     let mut manager_alive = data.manager_alive.clone();
 
+    // If the client has asked for a specific rate, we hold the latest value for each node and
+    // send them out on a timer, rather than forwarding every event.
+    let mut throttle = options.rate.map(|rate| {
+        let rate = rate.clamp(1, MAX_METER_RATE);
+        let mut interval = time::interval(Duration::from_secs_f64(1.0 / rate as f64));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        interval
+    });
+    let mut pending: HashMap<Ulid, u8> = HashMap::new();
+
     actix_web::rt::spawn(async move {
         // Is this the first client?
         if client_counter.fetch_add(1, Ordering::SeqCst) == 0 {
@@ -333,7 +452,21 @@ async fn websocket_meter(
                 }
 
                 Ok(event) = meter_rx.recv() => {
-                    if let Err(e) = send_message(&event, &mut session).await {
+                    if throttle.is_some() {
+                        pending.insert(event.id, event.percent);
+                    } else if let Err(e) = send_message(&event, &mut session).await {
+                        break e;
+                    }
+                }
+                _ = meter_tick(&mut throttle) => {
+                    let mut result = Ok(());
+                    for (id, percent) in pending.drain() {
+                        result = send_message(&MeterEvent { id, percent }, &mut session).await;
+                        if result.is_err() {
+                            break;
+                        }
+                    }
+                    if let Err(e) = result {
                         break e;
                     }
                 }
@@ -394,8 +527,10 @@ async fn websocket_meter(
 async fn execute_command(
     request: web::Json<DaemonRequest>,
     app_data: Data<RwLock<AppData>>,
+    req: HttpRequest,
 ) -> HttpResponse {
     let data = app_data.read().await;
+    audit_request(&connection_source("http", &req), &request.0);
 
     if !*data.manager_alive.borrow() {
         return HttpResponse::ServiceUnavailable().json(DaemonResponse::Err(
@@ -425,6 +560,16 @@ async fn get_devices(app_data: Data<RwLock<AppData>>) -> HttpResponse {
     HttpResponse::InternalServerError().finish()
 }
 
+/// Waits for the next throttled meter update, or forever if meters aren't being throttled
+async fn meter_tick(throttle: &mut Option<Interval>) {
+    match throttle {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => future::pending().await,
+    }
+}
+
 /// Serialises a serialisable into a JSON mess, and send to websocket
 async fn send_message<T>(value: &T, session: &mut Session) -> Result<(), Option<CloseReason>>
 where
@@ -469,6 +614,14 @@ async fn default(req: HttpRequest) -> HttpResponse {
     }
 }
 
+/// Identifies a connection in the Audit Log
+fn connection_source(kind: &str, req: &HttpRequest) -> String {
+    match req.peer_addr() {
+        Some(address) => format!("{}:{}", kind, address),
+        None => kind.to_string(),
+    }
+}
+
 async fn get_status(app_data: Data<RwLock<AppData>>) -> Result<DaemonStatus> {
     let data = app_data.read().await;
     let request = DaemonRequest::GetStatus;
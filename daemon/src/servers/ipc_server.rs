@@ -1,7 +1,8 @@
-use crate::handler::packet::{Messenger, handle_packet};
+use crate::handler::packet::{ConnectionState, Messenger, handle_packet};
+use crate::servers::audit_log::audit_request;
 use crate::servers::http_server::PatchEvent;
 use crate::{APP_NAME, APP_NAME_ID, Stop};
-use anyhow::{Error, Result};
+use anyhow::{Error, Result, anyhow};
 use directories::BaseDirs;
 use interprocess::local_socket::tokio::prelude::{LocalSocketListener, LocalSocketStream};
 use interprocess::local_socket::traits::tokio::{Listener, Stream};
@@ -9,21 +10,26 @@ use interprocess::local_socket::{GenericFilePath, ListenerOptions, ToFsName};
 use log::{debug, info, warn};
 use pipeweaver_ipc::clients::ipc::ipc_socket::Socket;
 use pipeweaver_ipc::commands::{DaemonCommand, DaemonRequest, DaemonResponse};
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
-use std::{env, fs};
+use std::{env, fs, process};
 use tokio::select;
 use tokio::sync::broadcast::Sender;
 use tokio::sync::watch;
 
 #[derive(Debug)]
 pub enum ErrorState {
-    AlreadyRunning,
+    AlreadyRunning(Option<u32>),
 }
 
 impl std::fmt::Display for ErrorState {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            ErrorState::AlreadyRunning => write!(f, "Pipeweaver Already Running"),
+            ErrorState::AlreadyRunning(Some(pid)) => {
+                write!(f, "{} is already running (pid {})", APP_NAME_ID, pid)
+            }
+            ErrorState::AlreadyRunning(None) => write!(f, "{} is already running", APP_NAME_ID),
         }
     }
 }
@@ -46,6 +52,61 @@ pub fn get_socket_path() -> Result<PathBuf> {
     Ok(socket_path)
 }
 
+fn get_pid_path() -> Result<PathBuf> {
+    let socket_path = get_socket_path()?;
+    Ok(socket_path.with_file_name(format!("{}.pid", APP_NAME_ID)))
+}
+
+/// Returns the PID from the lock file, if the process it refers to is still alive
+fn get_running_pid() -> Option<u32> {
+    let pid_path = get_pid_path().ok()?;
+    let pid = fs::read_to_string(pid_path)
+        .ok()?
+        .trim()
+        .parse::<u32>()
+        .ok()?;
+
+    if pid != process::id() && Path::new(&format!("/proc/{}", pid)).exists() {
+        return Some(pid);
+    }
+    None
+}
+
+fn acquire_pid_lock() -> Result<()> {
+    let pid_path = get_pid_path()?;
+
+    for _ in 0..2 {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&pid_path)
+        {
+            Ok(mut file) => {
+                write!(file, "{}", process::id())?;
+                debug!("Created PID File at {:?}", pid_path);
+                return Ok(());
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                if let Some(pid) = get_running_pid() {
+                    return Err(ErrorState::AlreadyRunning(Some(pid)).into());
+                }
+                debug!("PID File is stale, removing..");
+                fs::remove_file(&pid_path)?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(anyhow!("Unable to create PID File at {:?}", pid_path))
+}
+
+pub fn release_pid_lock() {
+    if let Ok(pid_path) = get_pid_path()
+        && fs::read_to_string(&pid_path).is_ok_and(|pid| pid.trim() == process::id().to_string())
+    {
+        let _ = fs::remove_file(pid_path);
+    }
+}
+
 async fn ipc_tidy() -> Result<()> {
     let socket_path = get_socket_path()?;
     debug!("Using IPC Path: {:?}", socket_path);
@@ -78,13 +139,17 @@ async fn ipc_tidy() -> Result<()> {
     socket.read().await;
 
     // If we get here, there's an active Daemon running!
-    Err(ErrorState::AlreadyRunning.into())
+    Err(ErrorState::AlreadyRunning(get_running_pid()).into())
 }
 
 pub async fn bind_socket() -> Result<LocalSocketListener> {
     let socket_path = get_socket_path()?;
     ipc_tidy().await?;
 
+    // The socket may not exist yet if another daemon is still starting up, so make sure we're
+    // the only instance before binding.
+    acquire_pid_lock()?;
+
     let name = socket_path.to_fs_name::<GenericFilePath>()?;
     let opts = ListenerOptions::new().name(name.clone());
     let listener = opts.create_tokio()?;
@@ -100,7 +165,6 @@ pub async fn spawn_ipc_server(
     manager_alive: watch::Receiver<bool>,
     mut shutdown_signal: Stop,
 ) {
-    let socket_path = format!("/tmp/{}.socket", APP_NAME);
     debug!("Running IPC Server..");
     loop {
         select! {
@@ -123,7 +187,10 @@ pub async fn spawn_ipc_server(
             }
             () = shutdown_signal.recv() => {
                 info!("[IPC] Stopping");
-                let _ = fs::remove_file(socket_path);
+                if let Ok(socket_path) = get_socket_path() {
+                    let _ = fs::remove_file(socket_path);
+                }
+                release_pid_lock();
                 info!("[IPC] Stopped");
                 return;
             }
@@ -138,6 +205,7 @@ async fn handle_connection(
     mut manager_alive: watch::Receiver<bool>,
 ) {
     let mut subscriber = broadcast_tx.subscribe();
+    let mut state = ConnectionState::default();
 
     loop {
         select! {
@@ -151,28 +219,38 @@ async fn handle_connection(
             }
 
             Ok(event) = subscriber.recv() => {
-                let patch = DaemonResponse::Patch(event.data);
-                if let Err(e) = socket.send(patch).await {
+                if let Err(e) = socket.send(event.data).await {
                     warn!("Couldn't send PatchEvent to {:?}: {}", socket.address(), e);
                     return;
                 }
             }
             Some(msg) = socket.read() => {
                 match msg {
-                    Ok(msg) => match handle_packet(msg, &usb_tx).await {
-                        Ok(response) => {
-                            if let Err(e) = socket.send(response).await {
-                                warn!("Couldn't reply to {:?}: {}", socket.address(), e);
-                                return;
+                    Ok(msg) => {
+                        audit_request("ipc", &msg);
+                        let result = match state.check_request(&msg) {
+                            Ok(()) => handle_packet(msg, &usb_tx).await,
+                            Err(e) => Err(e),
+                        };
+                        match result {
+                            Ok(response) => {
+                                let rejected = ConnectionState::is_rejected(&response);
+                                if let Err(e) = socket.send(response).await {
+                                    warn!("Couldn't reply to {:?}: {}", socket.address(), e);
+                                    return;
+                                }
+                                if rejected {
+                                    return;
+                                }
                             }
-                        }
-                        Err(e) => {
-                            if let Err(e) = socket.send(DaemonResponse::Err(e.to_string())).await {
-                                warn!("Couldn't reply to {:?}: {}", socket.address(), e);
-                                return;
+                            Err(e) => {
+                                if let Err(e) = socket.send(DaemonResponse::Err(e.to_string())).await {
+                                    warn!("Couldn't reply to {:?}: {}", socket.address(), e);
+                                    return;
+                                }
                             }
                         }
-                    },
+                    }
                     Err(e) => {
                         warn!("Invalid message from {:?}: {}", socket.address(), e);
                         if let Err(e) = socket.send(DaemonResponse::Err(e.to_string())).await {
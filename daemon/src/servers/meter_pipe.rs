@@ -0,0 +1,129 @@
+use crate::servers::http_server::MeterEvent;
+use crate::servers::ipc_server::get_socket_path;
+use anyhow::{Result, anyhow, bail};
+use log::{debug, info, warn};
+use std::ffi::CString;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::path::{Component, Path, PathBuf};
+use tokio::sync::broadcast::Sender;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// Writes the meter stream to a named pipe as newline delimited JSON, for consumers that
+/// don't want to deal with a WebSocket. The pipe is never allowed to block, if the reader
+/// isn't keeping up, samples are simply dropped.
+pub struct MeterPipe {
+    path: PathBuf,
+    created: bool,
+    handle: JoinHandle<()>,
+}
+
+impl MeterPipe {
+    pub fn start(name: PathBuf, meter_tx: &Sender<MeterEvent>) -> Result<Self> {
+        let path = resolve_pipe_path(&name)?;
+        let created = match fs::metadata(&path) {
+            Ok(meta) => {
+                if !meta.file_type().is_fifo() {
+                    bail!("{} exists and is not a named pipe", path.display());
+                }
+                false
+            }
+            Err(_) => {
+                let c_path = CString::new(path.as_os_str().as_bytes())?;
+                if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+                    let error = std::io::Error::last_os_error();
+                    bail!("Unable to create pipe {}: {}", path.display(), error);
+                }
+                true
+            }
+        };
+
+        info!("[MeterPipe] Writing Meter events to {}", path.display());
+        let mut meter_rx = meter_tx.subscribe();
+        let task_path = path.clone();
+        let handle = tokio::spawn(async move {
+            let mut pipe: Option<File> = None;
+            loop {
+                let event = match meter_rx.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+
+                // Opening a pipe for writing fails until there's a reader on the other side
+                if pipe.is_none() {
+                    pipe = open_pipe(&task_path);
+                }
+                let Some(file) = pipe.as_mut() else {
+                    continue;
+                };
+
+                let Ok(mut line) = serde_json::to_vec(&event) else {
+                    continue;
+                };
+                line.push(b'\n');
+
+                // Lines are well under PIPE_BUF, so writes are atomic, we either write the
+                // whole line or get WouldBlock and drop it.
+                match file.write(&line) {
+                    Ok(_) => {}
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                    Err(e) => {
+                        debug!("[MeterPipe] Reader Disconnected: {}", e);
+                        pipe = None;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            path,
+            created,
+            handle,
+        })
+    }
+}
+
+impl Drop for MeterPipe {
+    fn drop(&mut self) {
+        self.handle.abort();
+
+        // Only tidy up the pipe if we were the ones who made it
+        if self.created
+            && let Err(e) = fs::remove_file(&self.path)
+        {
+            warn!(
+                "[MeterPipe] Unable to remove {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Pipes are only ever created next to the daemon's socket in the runtime directory, clients
+/// only get to pick the name, so they can't have us create a pipe anywhere else.
+fn resolve_pipe_path(name: &Path) -> Result<PathBuf> {
+    let mut components = name.components();
+    let (Some(Component::Normal(file)), None) = (components.next(), components.next()) else {
+        bail!("Meter Pipes must be given as a file name, not a path");
+    };
+
+    let socket = get_socket_path()?;
+    let dir = socket
+        .parent()
+        .ok_or(anyhow!("Unable to locate runtime directory"))?;
+    Ok(dir.join(file))
+}
+
+fn open_pipe(path: &Path) -> Option<File> {
+    OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+        .ok()
+}
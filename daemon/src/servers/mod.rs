@@ -1,2 +1,4 @@
+pub(crate) mod audit_log;
 pub(crate) mod http_server;
 pub(crate) mod ipc_server;
+pub(crate) mod meter_pipe;
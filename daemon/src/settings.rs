@@ -1,31 +1,57 @@
-use crate::APP_NAME_ID;
+use crate::{APP_ID, APP_NAME, APP_NAME_ID};
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
-use log::{info, warn};
-use pipeweaver_ipc::commands::GlobalSettings;
+use log::{error, info, warn};
+use pipeweaver_ipc::commands::{GlobalSettings, NamingSettings};
+use pipeweaver_pipewire::NamingScheme;
 use std::fs;
 use std::fs::{File, create_dir_all};
 use std::path::{Path, PathBuf};
 
 pub fn load_settings() -> GlobalSettings {
-    match File::open(get_settings_file()) {
-        Ok(reader) => {
-            let settings = serde_json::from_reader(reader);
-            settings.unwrap_or_else(|e| {
-                warn!(
-                    "[Settings] Found, but unable to Load ({}), sending default",
+    let file_path = get_settings_file();
+    if !file_path.exists() {
+        info!("[Settings] Not Found, creating defaults");
+        return create_default_settings();
+    }
+
+    let settings: Result<GlobalSettings> = File::open(&file_path)
+        .context("Unable to open file")
+        .and_then(|reader| serde_json::from_reader(reader).context("Unable to parse file"));
+
+    match settings {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("[Settings] Found, but unable to Load ({:#})", e);
+
+            // Keep hold of the broken file so nothing the user had set is lost, if we can't even
+            // do that, leave it alone and run on the defaults rather than overwriting it.
+            let backup = file_path.with_extension("json.bak");
+            if let Err(e) = fs::rename(&file_path, &backup) {
+                error!(
+                    "[Settings] Unable to back up settings, using defaults: {}",
                     e
                 );
-                GlobalSettings::default()
-            })
-        }
-        Err(_) => {
-            warn!("[Settings] Not Found, sending default");
-            GlobalSettings::default()
+                return GlobalSettings::default();
+            }
+
+            warn!(
+                "[Settings] Backed up to {:?}, regenerating defaults",
+                backup
+            );
+            create_default_settings()
         }
     }
 }
 
+fn create_default_settings() -> GlobalSettings {
+    let settings = GlobalSettings::default();
+    if let Err(e) = save_settings(settings.clone()) {
+        error!("[Settings] Unable to save default settings: {}", e);
+    }
+    settings
+}
+
 pub fn save_settings(settings: GlobalSettings) -> Result<()> {
     info!("[Settings] Saving");
     let file_path = get_settings_file();
@@ -43,6 +69,24 @@ pub fn save_settings(settings: GlobalSettings) -> Result<()> {
     Ok(())
 }
 
+/// Builds the naming scheme for created nodes, using our own names for anything not overridden
+pub fn naming_scheme(settings: &NamingSettings) -> NamingScheme {
+    let value = |value: &Option<String>, default: &str| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or(default)
+            .to_string()
+    };
+
+    NamingScheme {
+        app_id: value(&settings.app_id, APP_ID),
+        app_name: value(&settings.app_name, APP_NAME),
+        group_prefix: value(&settings.group_prefix, APP_NAME_ID),
+    }
+}
+
 fn get_settings_file() -> PathBuf {
     // We'll never get here if the project dir can't be found, that'll bail in main
     let dirs =
@@ -1,24 +1,69 @@
 use enum_map::EnumMap;
 use json_patch::Patch;
-use pipeweaver_profile::Profile;
+use pipeweaver_profile::{EqBand, PhysicalDeviceDescriptor, Profile, Volumes};
 use pipeweaver_shared::{
-    AppDefinition, AppTarget, Colour, DeviceType, Mix, MuteState, MuteTarget, NodeType, OrderGroup,
-    PortDirection, Quantum,
+    AppDefinition, AppTarget, ChannelMode, Colour, DeviceType, Mix, MuteState, MuteTarget,
+    NodeType, OrderGroup, PortDirection, Quantum, VolumeCurve,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use ulid::Ulid;
 
+/// This should be bumped whenever the command types change in a way that breaks compatibility
+/// between the daemon and its clients.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The oldest client protocol version the daemon is still able to talk to.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DaemonRequest {
     /// Simple ping, will get an Ok / Error response
     Ping,
 
+    /// Should be the first message on a connection, the daemon replies with its own protocol
+    /// version, and will close the connection if the client's version isn't supported
+    Hello {
+        client_protocol_version: u32,
+    },
+
     /// This fetches the full status for all devices
     GetStatus,
 
+    /// Fetches the daemon version, build hash and protocol version
+    GetVersion,
+
+    /// Fetches the most recent xruns, oldest first
+    GetXrunHistory,
+
+    /// Fetches the temporary states currently layered over the profile
+    GetActiveOverlays,
+
+    /// Fetches the connection state between every source and every target
+    GetRoutingMatrix,
+
+    /// Fetches every port (including monitors) on a physical device, by Pipewire node id
+    GetDevicePorts(u32),
+
+    /// Fetches the requested and running audio quantum, and the latency it results in. The
+    /// quantum is changed with DaemonCommand::SetAudioQuantum
+    GetAudioQuantum,
+
+    /// Fetches a single reading from every meter, keyed by node. If metering is off the meters
+    /// are briefly switched on to take it
+    GetMeters,
+
+    /// Development aid, re-issues every request recorded in an Audit Log, in order. Like
+    /// SetAuditLog, this is a file name in the daemon's audit directory
+    ReplayAudit(PathBuf),
+
     Daemon(DaemonCommand),
     Pipewire(APICommand),
+
+    /// Runs a set of Daemon / Pipewire commands as a unit, if any of them fail, the profile
+    /// is rolled back to how it was before the transaction started
+    Transaction(Vec<DaemonRequest>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,8 +78,102 @@ pub enum DaemonResponse {
     Ok,
     Err(String),
     Patch(Patch),
+    Hello(HelloResponse),
     Status(DaemonStatus),
+    Version(VersionInfo),
+    VolumeSnapshot(VolumeSnapshot),
+    Latency(LatencyMeasurement),
+    Sample(CapturedSample),
     Pipewire(PWCommandResponse),
+    Transaction(Vec<DaemonResponse>),
+    XrunHistory(Vec<XrunEvent>),
+    ActiveOverlays(ActiveOverlays),
+    RoutingMatrix(RoutingMatrix),
+    DevicePorts(DevicePorts),
+    AudioQuantum(AudioQuantum),
+    Meters(HashMap<Ulid, u8>),
+    LinkReport(LinkReport),
+
+    /// Broadcast to all clients when an xrun is detected
+    XrunOccurred(XrunEvent),
+
+    /// Broadcast to all clients when an overlay is applied or removed
+    OverlaysChanged(ActiveOverlays),
+
+    /// Broadcast to all clients when a route is added or removed
+    RoutingMatrixChanged(RoutingMatrix),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelloResponse {
+    pub protocol_version: u32,
+    pub compatible: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyMeasurement {
+    pub samples: u64,
+    pub milliseconds: f32,
+}
+
+/// Every source against every target, both in the order they're displayed (Pinned, Default, then
+/// Hidden). `cells[source][target]` is the route from `sources[source]` to `targets[target]`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingMatrix {
+    pub sources: Vec<Ulid>,
+    pub targets: Vec<Ulid>,
+    pub cells: Vec<Vec<RoutingCell>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingCell {
+    pub connected: bool,
+
+    /// For connected multichannel targets, the channel pair the source lands on
+    pub channel_pair: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedSample {
+    pub rate: u32,
+    pub channels: u16,
+    pub milliseconds: u32,
+
+    /// A 16bit PCM WAV file, base64 encoded
+    pub wav: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub version: String,
+    pub git_hash: String,
+    pub protocol_version: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct XrunEvent {
+    /// Milliseconds since the Unix Epoch
+    pub timestamp: u64,
+
+    /// The Pipewire node which was driving the graph, and its name if it's a known device
+    pub driver_id: u32,
+    pub driver_name: Option<String>,
+
+    /// The estimated length of the xrun, in microseconds
+    pub duration: u64,
+
+    /// How noticeable the xrun is likely to have been
+    pub severity: XrunSeverity,
+}
+
+/// How badly an xrun will have been heard, based on its length against the graph's quantum
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum XrunSeverity {
+    /// Shorter than a single quantum, likely a brief click
+    Minor,
+
+    /// A full quantum or longer, so at least one buffer of audio was lost
+    Major,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,15 +186,89 @@ pub struct WebsocketResponse {
 pub enum DaemonCommand {
     SetAutoStart(bool),
     SetAudioQuantum(Option<Quantum>),
+    SetVolumeCurve(VolumeCurve),
     SetMetering(bool),
+    // Writes meter events to a named pipe in the daemon's runtime directory, given by name
+    SetMeterPipe(Option<PathBuf>),
+
+    // Records every request the daemon receives to a file in the daemon's audit directory,
+    // given by name. None stops recording
+    SetAuditLog(Option<PathBuf>),
     SetUseBrowser(bool),
+    SetAutoCleanupOrphans(bool),
+
+    // The colours new nodes without a known name are given, an empty list restores the defaults
+    SetDefaultPalette(Vec<Colour>),
+
+    // Requests a realtime priority (1-99) for the filter processing thread, None leaves the
+    // scheduling to Pipewire. Whether it was obtained is reported in the EngineStatus
+    SetRealtimePriority(Option<u8>),
+
+    // Temporarily stop physical devices being automatically attached to their nodes as they
+    // appear, re-enabling will attach any devices which were missed
+    SetAutoMatch(bool),
+    CleanupOrphans,
     OpenInterface,
     ResetAudio,
+
+    // EQ Preset Management (Preset Name, Node)
+    SaveEqPreset(String, Ulid),
+    ApplyEqPreset(String, Ulid),
+
+    // Volume Snapshots, Restore skips any nodes which no longer exist
+    SnapshotVolumes,
+    RestoreVolumes(VolumeSnapshot),
+
+    // While held (true), the Monitor Target listens to the provided Mix, released (false)
+    // returns it to the Mix it was using before
+    PreviewMix(Mix, bool),
+
+    // Plays a probe into the Source, and times how long it takes to arrive at the Target
+    MeasureLatency(Ulid, Ulid),
+
+    // Records a short sample (in milliseconds) from a node, for checking a signal is present
+    CaptureSample(Ulid, u32),
+
+    // Re-enables a filter which was bypassed for overrunning
+    ResumeFilter(Ulid),
+
+    // Plays a Test Signal at a level (0-100) into a Target, if a Mix is provided the signal is
+    // only heard while the Target is listening to that Mix
+    StartTestTone(Ulid, Option<Mix>, TestSignal, u8),
+    StopTestTone(Ulid),
+
+    // Instantly silences everything passing through the daemon without changing any settings,
+    // for emergencies, releasing (false) restores the audio
+    PanicMute(bool),
+
+    // Compares the links for every route against what Pipewire actually has, creating any
+    // that are missing and removing any that no longer match a route
+    ReconcileLinks,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TestSignal {
+    /// A sine wave at the provided frequency (in Hz)
+    Sine(u16),
+    PinkNoise,
+    WhiteNoise,
+}
+
+/// A point in time copy of all source and target volumes, clients should treat this as opaque
+/// and simply hand it back to the daemon to restore.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeSnapshot {
+    pub sources: HashMap<Ulid, Volumes>,
+    pub targets: HashMap<Ulid, u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum APICommand {
-    CreateNode(NodeType, String),
+    // (Type, Name, Route a new Source to the Monitor Target)
+    CreateNode(NodeType, String, bool),
+
+    // Creates a Virtual Source fed by a Target's output (Target, Name)
+    CreateTargetTapSource(Ulid, String),
     RenameNode(Ulid, String),
     RenameNodeByName(String, String),
 
@@ -65,10 +278,18 @@ pub enum APICommand {
     RemoveNode(Ulid),
     RemoveNodeByName(String),
 
+    // Returns a node's volumes and mute states to default, keeping routing and devices
+    ResetNode(Ulid),
+    ResetNodeByName(String),
+
     SetSourceVolume(Ulid, Mix, u8),
     SetTargetVolume(Ulid, u8),
     SetVolumeByName(String, Option<Mix>, u8),
 
+    // Sets every Source (on a Mix) or Target to the same volume
+    SetAllSourceVolumes(Mix, u8),
+    SetAllTargetVolumes(u8),
+
     SetSourceVolumeLinked(Ulid, bool),
     SetSourceVolumeLinkedByName(String, bool),
 
@@ -80,6 +301,12 @@ pub enum APICommand {
     SetRouteByTargetName(Ulid, String, bool),
     SetRouteByNames(String, String, bool),
 
+    // Multichannel Targets (Target, Channel Count) and (Source, Target, Channel Pair)
+    SetTargetChannels(Ulid, u8),
+    SetTargetChannelsByName(String, u8),
+    SetRouteChannelPair(Ulid, Ulid, u8),
+    SetRouteChannelPairByNames(String, String, u8),
+
     ToggleRoute(Ulid, Ulid),
     ToggleRouteBySourceName(String, Ulid),
     ToggleRouteByTargetName(Ulid, String),
@@ -103,9 +330,16 @@ pub enum APICommand {
     ClearMuteTargetNodes(Ulid, MuteTarget),
     ClearMuteTargetNodesByName(String, MuteTarget),
 
+    SetMuteTargetLabel(Ulid, MuteTarget, String),
+    SetMuteTargetLabelByName(String, MuteTarget, String),
+
     SetTargetMuteState(Ulid, MuteState),
     SetTargetMuteStatesByName(String, MuteState),
 
+    // Automatic Muting (Target, Mix, Source)
+    AddAutoMuteRule(Ulid, Mix, Ulid),
+    DelAutoMuteRule(Ulid, Mix, Ulid),
+
     // Attach or Detach physical nodes
     AttachPhysicalNode(Ulid, u32),
     AttachPhysicalNodeByName(String, u32),
@@ -113,11 +347,40 @@ pub enum APICommand {
     RemovePhysicalNode(Ulid, usize),
     RemovePhysicalNodeByName(String, usize),
 
+    // Override the channel layout of an attached physical node (None to Auto Detect)
+    SetPhysicalNodeChannelMode(Ulid, usize, Option<ChannelMode>),
+    SetPhysicalNodeChannelModeByName(String, usize, Option<ChannelMode>),
+
+    // Splits a stereo device into two mono sources, returns the ID of the second source
+    SplitPhysicalNode(Ulid, usize),
+    SplitPhysicalNodeByName(String, usize),
+
     // Used for Application Routing
     SetApplicationRoute(AppDefinition, Ulid),
     SetApplicationRouteByName(AppDefinition, String),
     ClearApplicationRoute(AppDefinition),
 
+    // A single sink which moves applications to their mapped source (Name, Fallback Source)
+    CreateAggregateSink(String, Option<Ulid>),
+    SetAggregateSinkFallback(Option<Ulid>),
+    RemoveAggregateSink,
+
+    // The target new sources are routed to when they're created with auto monitoring
+    SetMonitorTarget(Option<Ulid>),
+
+    // Armed sources are routed to the Recorder Target, and nothing else is
+    SetRecorderTarget(Option<Ulid>),
+    SetRecordArm(Ulid, bool),
+    SetRecordArmByName(String, bool),
+
+    // Replaces the bands of a Source's EQ
+    SetEqBands(Ulid, Vec<EqBand>),
+
+    // Disconnect sources from their A / B mixes while they have no routes
+    SetIdleUnroutedSources(bool),
+
+    // Moves a single running application stream (by Pipewire node id) to a node, without
+    // creating a rule for it. The stream returns to rule based routing when it next appears.
     SetTransientApplicationRoute(u32, Ulid),
     SetTransientApplicationRouteByName(u32, String),
     ClearTransientApplicationRoute(u32),
@@ -135,6 +398,10 @@ pub enum APICommand {
     SetOrder(Ulid, u8),
     SetOrderByName(String, u8),
 
+    // As above, but ignored if the node is no longer in the provided group
+    SetOrderInGroup(Ulid, OrderGroup, u8),
+    SetOrderInGroupByName(String, OrderGroup, u8),
+
     // Node Map Handling
     // NodeId, Name, Left Channel, Right Channel
     CreatePhysicalNodePortMap(u32, String, String, String),
@@ -178,16 +445,151 @@ pub struct AudioConfiguration {
     pub defaults_id: EnumMap<DeviceType, Option<Ulid>>,
 
     pub applications: EnumMap<DeviceType, HashMap<String, HashMap<String, Vec<Application>>>>,
+
+    #[serde(default)]
+    pub capabilities: Capabilities,
+
+    #[serde(default)]
+    pub engine: EngineStatus,
+
+    #[serde(default)]
+    pub overlays: ActiveOverlays,
+
+    /// The attached devices of each node, in profile order, and whether they're present
+    #[serde(default)]
+    pub attached_devices: HashMap<Ulid, Vec<AttachedDeviceStatus>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachedDeviceStatus {
+    pub descriptor: PhysicalDeviceDescriptor,
+
+    /// Whether the device is currently available in Pipewire
+    pub connected: bool,
+}
+
+/// Temporary states which change how things sound without being stored in the profile
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveOverlays {
+    /// Set while a Mix Preview is being held on the Monitor Target
+    pub preview: Option<MixPreview>,
+
+    /// Physical devices aren't being automatically attached as they appear
+    pub auto_match_suspended: bool,
+
+    /// Sources currently muted by an Auto Mute Rule
+    pub auto_muted_sources: Vec<Ulid>,
+
+    /// Filters bypassed by the overrun watchdog
+    pub bypassed_filters: Vec<Ulid>,
+
+    /// Targets currently having a Test Tone played into them
+    #[serde(default)]
+    pub test_tones: Vec<Ulid>,
+
+    /// All audio is being silenced by a Panic Mute
+    #[serde(default)]
+    pub panic_muted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MixPreview {
+    pub target: Ulid,
+    pub mix: Mix,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Whether a Session Manager (eg. WirePlumber) is running, without one application
+    /// routing and default device handling won't function.
+    pub session_manager_present: bool,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct EngineStatus {
+    /// The quantum the graph is currently running at, if it's been reported yet
+    pub quantum: Option<u32>,
+
+    /// Set when the graph is running at a different quantum to the one forced by the profile
+    pub quantum_mismatch: Option<QuantumMismatch>,
+
+    /// Filters which were bypassed after repeatedly overrunning their processing time, these
+    /// output silence until resumed
+    #[serde(default)]
+    pub bypassed_filters: Vec<Ulid>,
+
+    /// How the filter processing thread is scheduled, once the filters have started running
+    #[serde(default)]
+    pub realtime: Option<RealtimeStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealtimeStatus {
+    /// The priority requested in the settings, None if it was left to Pipewire
+    pub requested: Option<u8>,
+
+    /// Whether the thread is running with a realtime policy, and at what priority
+    pub realtime: bool,
+    pub priority: i32,
+
+    /// Why the requested priority couldn't be applied (eg. rtkit or RLIMIT_RTPRIO not set up)
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct QuantumMismatch {
+    pub requested: u32,
+    pub actual: u32,
+}
+
+/// What ReconcileLinks found to be wrong with the route links, and has now fixed
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LinkReport {
+    /// Routes which had no link at all
+    pub created: Vec<RouteLink>,
+
+    /// Routes whose link Pipewire never fully connected, these have been rebuilt
+    pub repaired: Vec<RouteLink>,
+
+    /// Links from a Source's Mix which don't belong to any route
+    pub removed: Vec<RouteLink>,
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RouteLink {
+    pub source: Ulid,
+    pub target: Ulid,
+    pub mix: Mix,
+}
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct AudioQuantum {
+    /// The quantum forced by the profile, None leaves the choice to Pipewire
+    pub requested: Option<u32>,
+
+    /// The quantum the graph is currently running at, if it's been reported yet
+    pub actual: Option<u32>,
+
+    /// The sample rate of the graph
+    pub rate: u32,
+
+    /// How long a single quantum lasts at the current rate, based on the running quantum if
+    /// it's known, otherwise the requested one
+    pub latency_ms: Option<f32>,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct DaemonConfig {
     pub global_settings: GlobalSettings,
-    pub http_settings: HttpSettings,
     pub auto_start: bool,
+
+    /// Whether physical devices are being automatically attached as they appear
+    #[serde(default)]
+    pub auto_match: bool,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct HttpSettings {
     pub enabled: bool,
     pub bind_address: String,
@@ -195,11 +597,52 @@ pub struct HttpSettings {
     pub port: u16,
 }
 
-#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            bind_address: "0.0.0.0".to_string(),
+            cors_enabled: false,
+            port: 14565,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct GlobalSettings {
     #[serde(default)]
     pub use_browser: bool,
+
+    /// Remove nodes left behind by a previous run when the daemon starts
+    #[serde(default)]
+    pub auto_cleanup_orphans: bool,
+
+    /// Overrides for how created nodes are named, applied when the daemon starts
+    #[serde(default)]
+    pub naming: NamingSettings,
+
+    /// Where the HTTP server listens, applied when the daemon starts
+    #[serde(default)]
+    pub http_settings: HttpSettings,
+
+    /// The colours new nodes cycle through, empty for the built-in palette
+    #[serde(default)]
+    pub default_palette: Vec<Colour>,
+
+    /// A realtime (SCHED_FIFO) priority to request for the filter processing thread, None
+    /// leaves it to Pipewire
+    #[serde(default)]
+    pub realtime_priority: Option<u8>,
+}
+
+/// Any value left unset falls back to the daemon's built-in name
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamingSettings {
+    pub app_id: Option<String>,
+    pub app_name: Option<String>,
+    pub group_prefix: Option<String>,
 }
 
 /// The API generally doesn't need to care about all the general minutia of how a Pipewire
@@ -227,6 +670,17 @@ pub struct PhysicalDevicePort {
     pub channel: String,
 }
 
+pub type DevicePorts = EnumMap<PortDirection, Vec<DevicePort>>;
+
+/// The full details of a port on a physical device, for mapping arbitrary channels
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DevicePort {
+    pub port_id: u32,
+    pub name: String,
+    pub channel: String,
+    pub is_monitor: bool,
+}
+
 /// This will be extended over time, for now we'll just include the node id and the name.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Application {
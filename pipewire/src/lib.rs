@@ -1,6 +1,7 @@
 pub extern crate oneshot;
 mod default_device;
 mod manager;
+mod realtime;
 mod registry;
 mod store;
 
@@ -9,6 +10,7 @@ use anyhow::{Result, anyhow, bail};
 use enum_map::{Enum, EnumMap};
 use log::{info, trace, warn};
 use oneshot::TryRecvError;
+use pipeweaver_shared::VolumeCurve;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::mpsc;
@@ -28,7 +30,8 @@ type Receiver = mpsc::Receiver<PipewireInternalMessage>;
 pub enum PipewireMessage {
     CreateDeviceNode(NodeProperties),
     CreateFilterNode(FilterProperties),
-    CreateDeviceLink(LinkType, LinkType, oneshot::Sender<()>),
+    /// Source, Destination, Passive, Ready Sender
+    CreateDeviceLink(LinkType, LinkType, bool, oneshot::Sender<()>),
 
     RemoveDeviceNode(Ulid),
     RemoveFilterNode(Ulid),
@@ -37,6 +40,22 @@ pub enum PipewireMessage {
     GetFilterParameters(Ulid, oneshot::Sender<Result<Vec<FilterProperty>>>),
     SetFilterValue(Ulid, u32, FilterValue, oneshot::Sender<Result<String>>),
 
+    /// Fetches every port on an unmanaged device node, including monitors
+    GetDevicePorts(u32, oneshot::Sender<Result<DevicePortMap>>),
+
+    /// Fetches every link we've created which Pipewire has finished setting up
+    GetManagedLinks(oneshot::Sender<Result<Vec<ManagedLink>>>),
+
+    /// Bypasses (or resumes) a filter, resuming also resets its overrun watchdog
+    SetFilterBypass(Ulid, bool),
+
+    /// Silences the output of every filter, without touching their settings
+    SetPanicMute(bool),
+
+    /// Requests a SCHED_FIFO priority for the thread running the filters, None leaves it to
+    /// Pipewire. The outcome is reported back with PipewireReceiver::RealtimeStatus
+    SetRealtimePriority(Option<u8>),
+
     SetNodeVolume(Ulid, u8),
     SetNodeMute(Ulid, bool),
 
@@ -52,6 +71,7 @@ pub enum PipewireMessage {
     SetDefaultDevice(MediaClass, NodeTarget),
 
     DestroyUnmanagedLinks(u32),
+    DestroyUnmanagedNode(u32),
 
     Quit(bool),
 }
@@ -62,6 +82,7 @@ pub enum PipewireInternalMessage {
     CreateDeviceLink(
         LinkType,
         LinkType,
+        bool,
         oneshot::Sender<()>,
         oneshot::Sender<Result<()>>,
     ),
@@ -72,6 +93,11 @@ pub enum PipewireInternalMessage {
 
     GetFilterParameters(Ulid, oneshot::Sender<Result<Vec<FilterProperty>>>),
     SetFilterValue(Ulid, u32, FilterValue, oneshot::Sender<Result<String>>),
+    SetFilterBypass(Ulid, bool, oneshot::Sender<Result<()>>),
+    SetPanicMute(bool, oneshot::Sender<Result<()>>),
+    SetRealtimePriority(Option<u8>, oneshot::Sender<Result<()>>),
+    GetDevicePorts(u32, oneshot::Sender<Result<DevicePortMap>>),
+    GetManagedLinks(oneshot::Sender<Result<Vec<ManagedLink>>>),
 
     SetNodeVolume(Ulid, u8, oneshot::Sender<Result<()>>),
     SetNodeMute(Ulid, bool, oneshot::Sender<Result<()>>),
@@ -87,6 +113,7 @@ pub enum PipewireInternalMessage {
     SetDefaultDevice(MediaClass, NodeTarget, oneshot::Sender<Result<()>>),
 
     DestroyUnmanagedLinks(u32, oneshot::Sender<Result<()>>),
+    DestroyUnmanagedNode(u32, oneshot::Sender<Result<()>>),
     Quit(bool, oneshot::Sender<Result<()>>),
 }
 
@@ -96,6 +123,7 @@ pub enum PipewireReceiver {
     Exited,
 
     AnnouncedClock(Option<u32>),
+    SessionManagerFound,
 
     DefaultChanged(MediaClass, NodeTarget),
 
@@ -116,14 +144,49 @@ pub enum PipewireReceiver {
     NodeMuteChanged(Ulid, bool),
 
     ManagedLinkDropped(LinkType, LinkType),
+    ManagedNodeDropped(Ulid),
+
+    /// The graph missed a deadline (Driver Node, Graph Cycle, Duration in Nanoseconds)
+    Xrun(u32, u32, u64),
+
+    /// The quantum the graph is actually running at has changed (Quantum in Samples)
+    QuantumChanged(u32),
+
+    /// A filter repeatedly took too long to process, and has been bypassed
+    FilterOverrun(Ulid),
+
+    /// The scheduling of the thread running the filters, sent when they first run and after
+    /// every change to the requested priority
+    RealtimeStatus(RealtimeStatus),
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RealtimeStatus {
+    /// The priority we asked for, None if the scheduling was left to Pipewire
+    pub requested: Option<u8>,
+
+    /// Whether the thread is running with a realtime policy (SCHED_FIFO / SCHED_RR)
+    pub realtime: bool,
+    pub priority: i32,
+
+    /// The OS error if the request was refused (eg. EPERM without rtkit or RLIMIT_RTPRIO)
+    pub error: Option<i32>,
+}
+
+/// How nodes and filters we create identify themselves to Pipewire and graph tools
+#[derive(Debug, Clone)]
 pub struct NamingScheme {
     pub app_id: String,
     pub app_name: String,
     pub group_prefix: String,
 }
 
+impl NamingScheme {
+    pub fn node_group(&self) -> String {
+        format!("{}-nodes", self.group_prefix)
+    }
+}
+
 // We'll use Options on the thread handles, so we can take them during drop
 pub struct PipewireRunner {
     pipewire_thread: Option<JoinHandle<()>>,
@@ -132,7 +195,7 @@ pub struct PipewireRunner {
 }
 
 impl PipewireRunner {
-    pub fn new(callback_tx: mpsc::Sender<PipewireReceiver>) -> Result<Self> {
+    pub fn new(callback_tx: mpsc::Sender<PipewireReceiver>, naming: NamingScheme) -> Result<Self> {
         // First, we need our pipewire messaging queue, so establish that here
         let (pw_tx, pw_rx) = pipewire::channel::channel();
         let (tx, rx) = mpsc::channel();
@@ -141,7 +204,8 @@ impl PipewireRunner {
         let (start_tx, start_rx) = oneshot::channel();
 
         // Next, spawn up the pipewire mainloop in a separate thread
-        let pipewire_handle = thread::spawn(|| run_pw_main_loop(pw_rx, start_tx, callback_tx));
+        let pipewire_handle =
+            thread::spawn(|| run_pw_main_loop(pw_rx, start_tx, callback_tx, naming));
 
         // Await a response from that thread to indicate we're ready to handle messages
         loop {
@@ -178,7 +242,10 @@ impl PipewireRunner {
         // Check if this is a message that handles its own response channel
         let uses_own_channel = matches!(
             message,
-            PipewireMessage::GetFilterParameters(..) | PipewireMessage::SetFilterValue(..)
+            PipewireMessage::GetFilterParameters(..)
+                | PipewireMessage::SetFilterValue(..)
+                | PipewireMessage::GetDevicePorts(..)
+                | PipewireMessage::GetManagedLinks(..)
         );
         let (tx, rx) = oneshot::channel();
 
@@ -189,8 +256,8 @@ impl PipewireRunner {
             PipewireMessage::CreateFilterNode(f) => {
                 PipewireInternalMessage::CreateFilterNode(f, tx)
             }
-            PipewireMessage::CreateDeviceLink(lt, lt2, cb) => {
-                PipewireInternalMessage::CreateDeviceLink(lt, lt2, cb, tx)
+            PipewireMessage::CreateDeviceLink(lt, lt2, passive, cb) => {
+                PipewireInternalMessage::CreateDeviceLink(lt, lt2, passive, cb, tx)
             }
             PipewireMessage::RemoveDeviceNode(id) => {
                 PipewireInternalMessage::RemoveDeviceNode(id, tx)
@@ -204,12 +271,28 @@ impl PipewireRunner {
             PipewireMessage::DestroyUnmanagedLinks(id) => {
                 PipewireInternalMessage::DestroyUnmanagedLinks(id, tx)
             }
+            PipewireMessage::DestroyUnmanagedNode(id) => {
+                PipewireInternalMessage::DestroyUnmanagedNode(id, tx)
+            }
             PipewireMessage::GetFilterParameters(id, tx) => {
                 PipewireInternalMessage::GetFilterParameters(id, tx)
             }
             PipewireMessage::SetFilterValue(id, prop, value, tx) => {
                 PipewireInternalMessage::SetFilterValue(id, prop, value, tx)
             }
+            PipewireMessage::GetDevicePorts(id, tx) => {
+                PipewireInternalMessage::GetDevicePorts(id, tx)
+            }
+            PipewireMessage::GetManagedLinks(tx) => PipewireInternalMessage::GetManagedLinks(tx),
+            PipewireMessage::SetFilterBypass(id, bypass) => {
+                PipewireInternalMessage::SetFilterBypass(id, bypass, tx)
+            }
+            PipewireMessage::SetPanicMute(muted) => {
+                PipewireInternalMessage::SetPanicMute(muted, tx)
+            }
+            PipewireMessage::SetRealtimePriority(priority) => {
+                PipewireInternalMessage::SetRealtimePriority(priority, tx)
+            }
             PipewireMessage::SetNodeVolume(id, volume) => {
                 PipewireInternalMessage::SetNodeVolume(id, volume, tx)
             }
@@ -325,13 +408,14 @@ pub struct NodeProperties {
     pub initial_volume: u8,
 
     // App specific variables..
-    pub app_id: String,
-    pub app_name: String,
+    pub naming: NamingScheme,
 
     // Node Configuration
     pub linger: bool,
     pub class: MediaClass,
     pub managed_volume: bool,
+    pub volume_curve: VolumeCurve,
+    pub channels: u8,
 
     // Latency Configuration
     pub buffer: Option<u32>,
@@ -348,8 +432,7 @@ pub struct FilterProperties {
     pub filter_nick: String,
     pub filter_description: String,
 
-    pub app_id: String,
-    pub app_name: String,
+    pub naming: NamingScheme,
 
     pub class: MediaClass,
     pub linger: bool,
@@ -364,8 +447,7 @@ impl Debug for FilterProperties {
             .field("filter_name", &self.filter_name)
             .field("filter_nick", &self.filter_nick)
             .field("filter_description", &self.filter_description)
-            .field("app_id", &self.app_id)
-            .field("app_name", &self.app_name)
+            .field("naming", &self.naming)
             .field("class", &self.class)
             .field("linger", &self.linger)
             .finish()
@@ -388,14 +470,25 @@ pub enum Direction {
 #[derive(Debug, Clone, PartialEq)]
 pub enum LinkType {
     Node(Ulid),
+
+    /// A specific Left / Right channel pair on a multichannel Node
+    NodeChannelPair(Ulid, u8),
     Filter(Ulid),
     UnmanagedNode(u32, Option<LinkPorts>),
 }
 
+/// A link we've created, and whether Pipewire still has every port of it connected
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManagedLink {
+    pub source: LinkType,
+    pub destination: LinkType,
+    pub connected: bool,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct LinkPorts {
-    left: String,
-    right: String,
+    pub left: String,
+    pub right: String,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -411,6 +504,10 @@ pub trait FilterHandler: Send + 'static {
     fn set_property(&mut self, id: u32, value: FilterValue) -> Result<String>;
 
     fn process_samples(&mut self, inputs: Vec<&mut [f32]>, outputs: Vec<&mut [f32]>);
+
+    /// Called from the audio thread before processing whenever the graph's sample rate
+    /// changes, so must not allocate. Only needed by filters which depend on the rate.
+    fn set_rate(&mut self, _rate: u32) {}
 }
 
 // We need these because while *WE* know what values are coming in and out, rust doesn't
@@ -450,6 +547,9 @@ pub struct DeviceNode {
     pub nickname: Option<String>,
     pub description: Option<String>,
 
+    /// The node.group, our own nodes are created in NamingScheme::node_group
+    pub group: Option<String>,
+
     pub volume: u8,
     pub muted: bool,
 
@@ -462,6 +562,17 @@ pub struct NodePort {
     pub channel: String,
 }
 
+pub type DevicePortMap = EnumMap<Direction, Vec<DeviceNodePort>>;
+
+/// The full details of a port on a device node, as reported by Pipewire
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceNodePort {
+    pub port_id: u32,
+    pub name: String,
+    pub channel: String,
+    pub is_monitor: bool,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ApplicationNode {
     pub node_id: u32,
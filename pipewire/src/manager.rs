@@ -1,12 +1,14 @@
+use crate::realtime::RealtimeRequest;
 use crate::registry::PipewireRegistry;
 use crate::store::{
     FilterStore, LinkStore, LinkStoreMap, NodeStore, NodeStoreState, PortLocation, Store,
+    channel_volume,
 };
 use crate::{
-    Direction, FilterHandler, FilterProperties, FilterProperty, FilterValue, LinkType,
-    NodeProperties, NodeTarget, PipewireInternalMessage, PipewireReceiver,
+    DevicePortMap, Direction, FilterHandler, FilterProperties, FilterProperty, FilterValue,
+    LinkType, ManagedLink, NodeProperties, NodeTarget, PipewireInternalMessage, PipewireReceiver,
 };
-use crate::{MediaClass, PWReceiver};
+use crate::{MediaClass, NamingScheme, PWReceiver};
 use anyhow::Result;
 use anyhow::{anyhow, bail};
 use log::{debug, error, info};
@@ -26,9 +28,9 @@ use pipewire::proxy::ProxyT;
 use pipewire::registry::Registry;
 use pipewire::spa::pod::builder::Builder;
 use pipewire::spa::pod::deserialize::PodDeserializer;
-use pipewire::spa::pod::{Pod, Property, Value, ValueArray, object};
+use pipewire::spa::pod::{Pod, Value, ValueArray};
 use pipewire::spa::sys::{
-    SPA_AUDIO_CHANNEL_FL, SPA_AUDIO_CHANNEL_FR, SPA_FORMAT_AUDIO_position,
+    SPA_AUDIO_CHANNEL_AUX0, SPA_AUDIO_CHANNEL_FL, SPA_AUDIO_CHANNEL_FR, SPA_FORMAT_AUDIO_position,
     SPA_PARAM_PORT_CONFIG_format, SPA_PARAM_PortConfig, SPA_PARAM_Props, SPA_PROP_channelVolumes,
     SPA_PROP_mute, SPA_TYPE_OBJECT_ParamProcessLatency, spa_process_latency_build,
     spa_process_latency_info,
@@ -38,22 +40,37 @@ use enum_map::{EnumMap, enum_map};
 use oneshot::Sender;
 use parking_lot::RwLock;
 use pipewire::spa::param::ParamType;
-use pipewire::spa::pod::serialize::PodSerializer;
 use pipewire::spa::utils;
 
 use pipewire::main_loop::MainLoop;
 use pipewire::{context, main_loop};
 use std::cell::{Cell, RefCell};
-use std::io::Cursor;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::time::Duration;
 use strum::IntoEnumIterator;
 use ulid::Ulid;
 
+// If a filter uses more than this fraction of the period for OVERRUN_LIMIT cycles in a row, it's
+// bypassed to stop it from dragging the rest of the graph down with it. This is measured in CPU
+// time for the thread, so a busy host preempting us doesn't count against the filter.
+const OVERRUN_FRACTION: f64 = 0.5;
+const OVERRUN_LIMIT: u32 = 32;
+
+// How long a Panic Mute takes to fade a filter's output in or out, short enough to feel instant
+// while still avoiding a click
+const PANIC_RAMP_MS: f32 = 10.;
+
 pub(crate) struct FilterData {
     pub callback: Box<dyn FilterHandler>,
+
+    // Set by the overrun watchdog, while bypassed the filter outputs silence. Passing the input
+    // through instead would undo whatever the filter was doing, eg. a muted volume filter.
+    pub bypassed: bool,
+    pub overruns: u32,
 }
 
 struct PipewireManager {
@@ -63,6 +80,15 @@ struct PipewireManager {
     store: Rc<RefCell<Store>>,
     mainloop: Rc<MainLoop>,
 
+    // Shared with every filter's process callback, while set all filters output silence
+    panic_mute: Arc<AtomicBool>,
+
+    // Shared with every filter's process callback, which apply it to the thread they run on
+    realtime: Arc<RealtimeRequest>,
+
+    // Used to brand the links we create
+    naming: NamingScheme,
+
     _core_listener: Option<Listener>,
 }
 
@@ -72,6 +98,7 @@ impl PipewireManager {
         mainloop: Rc<MainLoop>,
         registry: Registry,
         callback_tx: mpsc::Sender<PipewireReceiver>,
+        naming: NamingScheme,
     ) -> Self {
         let store = Rc::new(RefCell::new(Store::new(callback_tx.clone())));
         let registry = PipewireRegistry::new(registry, store.clone(), Rc::new(core.clone()));
@@ -82,6 +109,9 @@ impl PipewireManager {
             store,
 
             mainloop,
+            panic_mute: Arc::new(AtomicBool::new(false)),
+            realtime: Arc::new(RealtimeRequest::new()),
+            naming,
             _core_listener: None,
         }
     }
@@ -180,13 +210,13 @@ impl PipewireManager {
             *NODE_VIRTUAL => "true",
             *PORT_MONITOR => "false",
 
-            *APP_ICON_NAME => &*properties.app_id,
-            *MEDIA_ICON_NAME => &*properties.app_id,
-            *DEVICE_ICON_NAME => &*properties.app_id,
+            *APP_ICON_NAME => &*properties.naming.app_id,
+            *MEDIA_ICON_NAME => &*properties.naming.app_id,
+            *DEVICE_ICON_NAME => &*properties.naming.app_id,
 
-            *NODE_GROUP => "pipeweaver-nodes",
+            *NODE_GROUP => properties.naming.node_group(),
 
-            //*APP_NAME => properties.app_name,
+            //*APP_NAME => properties.naming.app_name,
             *OBJECT_LINGER => match properties.linger {
                 true => "true",
                 false => "false"
@@ -197,7 +227,7 @@ impl PipewireManager {
                 MediaClass::Sink => "Audio/Sink",
             },
 
-            *AUDIO_CHANNELS => "2",
+            *AUDIO_CHANNELS => properties.channels.to_string(),
 
             // Force the RATE to match the system rate
             *NODE_FORCE_RATE => properties.rate.to_string(),
@@ -215,7 +245,7 @@ impl PipewireManager {
             *NODE_DRIVER => "false",
 
             // https://gitlab.freedesktop.org/pipewire/pipewire/-/wikis/Virtual-Devices
-            "audio.position" => "FL,FR",
+            "audio.position" => node_positions(properties.channels),
 
             // If upstream is managing the volumes via a filter, we don't want Pipewire interfering
             "monitor.channel-volumes" => match properties.managed_volume {
@@ -234,25 +264,23 @@ impl PipewireManager {
             properties.node_id, properties.node_name
         );
 
+        // Do everything which can fail before touching Pipewire, so a failure here leaves
+        // things exactly as they were.
+        let bytes = self.store.borrow_mut().managed_node_prepare(
+            properties.node_id,
+            properties.volume_curve,
+            properties.initial_volume,
+            properties.channels,
+        )?;
+        let volume_pod = Pod::from_bytes(&bytes).ok_or(anyhow!("Invalid Initial Volume"))?;
+
         // Properties built, create the node.
         let proxy = self
             .core
             .create_object::<pipewire::node::Node>("adapter", node_properties)
             .map_err(|e| anyhow!("Unable to Create Node {}", e))?;
 
-        // Set the Initial volume
-        let volume = (properties.initial_volume as f32 / 100.0).powi(3);
-        let pod = Value::Object(object! {
-            utils::SpaTypes::ObjectParamProps,
-            ParamType::Props,
-            Property::new(SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(vec![volume, volume]))),
-        });
-
-        let (cursor, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &pod)?;
-        let bytes = cursor.into_inner();
-        if let Some(bytes) = Pod::from_bytes(&bytes) {
-            proxy.set_param(ParamType::Props, 0, bytes);
-        }
+        proxy.set_param(ParamType::Props, 0, volume_pod);
 
         debug!("[{}] Registering Proxy Listener", properties.node_id);
         let proxy_id = properties.node_id;
@@ -277,6 +305,7 @@ impl PipewireManager {
         let listener_id = properties.node_id;
         let listener_info_store = Rc::downgrade(&self.store);
         let listener_param_store = Rc::downgrade(&self.store);
+        let listener_channels = properties.channels as u32;
         let listener = proxy
             .add_listener_local()
             .info(move |info| {
@@ -285,7 +314,9 @@ impl PipewireManager {
                     || info.change_mask().contains(NodeChangeMask::OUTPUT_PORTS)
                 {
                     // Now check whether our port count matches what's expected
-                    if info.n_input_ports() == 2 && info.n_output_ports() == 2 {
+                    if info.n_input_ports() == listener_channels
+                        && info.n_output_ports() == listener_channels
+                    {
                         debug!(
                             "[{}] Ports have appeared, requesting configuration",
                             listener_id
@@ -336,17 +367,18 @@ impl PipewireManager {
                                         let mut store = listener_param_store.borrow_mut();
                                         for (index, value) in array.iter().enumerate() {
                                             let index = index as u32;
-                                            if value.0 == SPA_AUDIO_CHANNEL_FL {
+                                            let channel = match value.0 {
+                                                SPA_AUDIO_CHANNEL_FL => Some(0),
+                                                SPA_AUDIO_CHANNEL_FR => Some(1),
+                                                aux if aux >= SPA_AUDIO_CHANNEL_AUX0 => {
+                                                    Some((aux - SPA_AUDIO_CHANNEL_AUX0) as usize)
+                                                }
+                                                _ => None,
+                                            };
+                                            if let Some(channel) = channel {
                                                 store.managed_node_add_port(
                                                     listener_id,
-                                                    PortLocation::Left,
-                                                    index,
-                                                );
-                                            }
-                                            if value.0 == SPA_AUDIO_CHANNEL_FR {
-                                                store.managed_node_add_port(
-                                                    listener_id,
-                                                    PortLocation::Right,
+                                                    channel,
                                                     index,
                                                 );
                                             }
@@ -360,23 +392,15 @@ impl PipewireManager {
                                 .iter()
                                 .find(|p| p.key == SPA_PROP_channelVolumes);
 
-                            // Get the Left / Right value
+                            // Get the Left / Right value, and map it back through the curve we used to set it
                             if let Some(prop) = prop
                                 && let Value::ValueArray(ValueArray::Float(value)) = &prop.value
+                                && let Some(volume) = channel_volume(volume_curve, value)
+                                && let Some(listener_param_store) = listener_param_store.upgrade()
                             {
-                                // OK, so KDE and pwvucontrol use the highest value for their reference
-                                let max = value
-                                    .iter()
-                                    .copied()
-                                    .max_by(|a, b| a.partial_cmp(b).unwrap())
-                                    .unwrap();
-
-                                let volume = (max.cbrt() * 100.0).round() as u8;
-                                if let Some(listener_param_store) = listener_param_store.upgrade() {
-                                    listener_param_store
-                                        .borrow_mut()
-                                        .on_volume_change(listener_id, volume);
-                                }
+                                listener_param_store
+                                    .borrow_mut()
+                                    .on_volume_change(listener_id, volume);
                             }
 
                             let prop = object.properties.iter().find(|p| p.key == SPA_PROP_mute);
@@ -409,15 +433,20 @@ impl PipewireManager {
             object_serial: None,
             id: properties.node_id,
             props: node_properties.clone(),
-            proxy,
-            _listener: listener,
-            _proxy_listener: proxy_listener,
+            proxy: Some(proxy),
+            _listener: Some(listener),
+            _proxy_listener: Some(proxy_listener),
 
-            port_map: Default::default(),
+            port_map: vec![None; properties.channels as usize],
             ports_ready: false,
+            volume_curve,
+            pending: Default::default(),
 
             node_state: NodeStoreState::Creating,
             ready_sender: Some(properties.ready_sender),
+
+            #[cfg(test)]
+            sent_props: Default::default(),
         };
 
         self.store.borrow_mut().managed_node_add(store);
@@ -434,13 +463,13 @@ impl PipewireManager {
         // For now, we assume a mono implementation... We should separately support both varying
         // input and output counts and have upstream handle it
         let properties = properties!(
-            *APP_ID => &*props.app_id,
+            *APP_ID => &*props.naming.app_id,
             *NODE_NAME => &*props.filter_name,
             *NODE_NICK => &*props.filter_nick,
             *NODE_DESCRIPTION => &*props.filter_description,
             *NODE_ALWAYS_PROCESS => "true",
 
-            *NODE_GROUP => "pipeweaver-nodes",
+            *NODE_GROUP => props.naming.node_group(),
 
             *MEDIA_TYPE => "Audio",
             *MEDIA_CATEGORY => "Filter",
@@ -461,11 +490,14 @@ impl PipewireManager {
         let input_ports = Rc::new(RefCell::new(vec![]));
         let output_ports = Rc::new(RefCell::new(vec![]));
 
-        let mut input_port_map = EnumMap::default();
-        let mut output_port_map = EnumMap::default();
+        // A filter may only have ports on one side, a Source only takes input (eg. a meter)
+        // and a Sink only produces output (eg. a tone generator)
+        let mut input_port_map = None;
+        let mut output_port_map = None;
 
         if props.class == MediaClass::Source || props.class == MediaClass::Duplex {
             debug!("[{}] Registering Input Ports", props.filter_id);
+            let mut port_map = EnumMap::default();
             for (index, port) in PortLocation::iter().enumerate() {
                 input_ports.borrow_mut().push(
                     filter
@@ -481,8 +513,9 @@ impl PipewireManager {
                         )
                         .map_err(|e| anyhow!("Filter Input Creation Failed: {}", e))?,
                 );
-                input_port_map[port] = index as u32;
+                port_map[port] = index as u32;
             }
+            input_port_map = Some(port_map);
         }
 
         #[allow(clippy::collapsible_if)]
@@ -490,6 +523,7 @@ impl PipewireManager {
         if props.class == MediaClass::Sink || props.class == MediaClass::Duplex {
             debug!("[{}] Registering Output Ports", props.filter_id);
 
+            let mut port_map = EnumMap::default();
             for (index, port) in PortLocation::iter().enumerate() {
                 output_ports.borrow_mut().push(
                     filter
@@ -505,8 +539,9 @@ impl PipewireManager {
                         )
                         .map_err(|e| anyhow!("Filter Input Creation Failed: {:?}", e))?,
                 );
-                output_port_map[port] = index as u32;
+                port_map[port] = index as u32;
             }
+            output_port_map = Some(port_map);
         }
         //}
 
@@ -514,6 +549,8 @@ impl PipewireManager {
         // its settings on-the-fly
         let data = Rc::new(RwLock::new(FilterData {
             callback: props.callback,
+            bypassed: false,
+            overruns: 0,
         }));
         let data_inner = data.clone();
 
@@ -523,9 +560,41 @@ impl PipewireManager {
         let listener_state_store = Rc::downgrade(&self.store);
         let listener_core = self.core.clone();
         let listener_id = props.filter_id;
+
+        // The driver's clock keeps a running total of time lost to xruns, so if it goes up
+        // between cycles, we've had one. Every filter in the graph will see the same clock, so
+        // the cycle is included to allow duplicates to be dropped upstream.
+        let xrun_sender = self.store.borrow().callback_sender();
+        let overrun_sender = xrun_sender.clone();
+        let last_xrun = Cell::new(None);
+
+        // Pipewire is free to ignore a forced quantum (eg. if another node needs a smaller one),
+        // so report whatever the clock is actually running at whenever it changes.
+        let last_quantum = Cell::new(None);
+
+        // The gain applied by a Panic Mute, ramped towards 0 or 1 to avoid clicks
+        let panic_mute = self.panic_mute.clone();
+        let panic_gain = Cell::new(panic_mute_gain(&panic_mute));
+
+        // The generation of the realtime request this filter last applied
+        let realtime = self.realtime.clone();
+        let realtime_applied = Cell::new(None);
+
+        // The sample rate last handed to the filter
+        let last_rate = Cell::new(None);
+
         let listener = filter
             .add_local_listener_with_user_data(data_inner)
-            .state_changed(move |filter, _data, old, _new| {
+            .state_changed(move |filter, _data, old, new| {
+                if let FilterState::Error(error) = new {
+                    if let Some(listener_state_store) = listener_state_store.upgrade() {
+                        listener_state_store
+                            .borrow_mut()
+                            .managed_filter_error(listener_id, error);
+                    }
+                    return;
+                }
+
                 if old == FilterState::Connecting {
                     debug!("[{}] Filter Connected", listener_id);
                     if let Some(listener_state_store) = listener_state_store.upgrade() {
@@ -539,19 +608,40 @@ impl PipewireManager {
             })
             .process(move |filter, data, position| {
                 let samples = position.clock.duration as u32;
+
+                if last_quantum.replace(Some(samples)) != Some(samples) {
+                    let _ = xrun_sender.send(PipewireReceiver::QuantumChanged(samples));
+                }
+
+                let generation = realtime.generation();
+                if realtime_applied.replace(Some(generation)) != Some(generation) {
+                    let status = realtime.apply();
+                    let _ = xrun_sender.send(PipewireReceiver::RealtimeStatus(status));
+                }
+
+                let xrun = position.clock.xrun;
+                if let Some(last) = last_xrun.replace(Some(xrun))
+                    && xrun > last
+                {
+                    let clock = &position.clock;
+                    let message = PipewireReceiver::Xrun(clock.id, clock.cycle, xrun - last);
+                    let _ = xrun_sender.send(message);
+                }
                 //debug!("Rate: {:?}", position.clock.rate.denom);
 
                 let mut input_list = vec![];
                 let mut output_list = vec![];
 
+                // A port without a buffer is handed over empty, so the lists still line up with
+                // the ports. Either list may be empty if the filter only has ports on one side.
                 for input in listener_input_ports.borrow().iter() {
                     let in_buffer = filter.get_dsp_buffer::<f32>(input, samples);
-                    input_list.push(in_buffer.unwrap());
+                    input_list.push(in_buffer.unwrap_or_default());
                 }
 
                 for output in listener_output_ports.borrow().iter() {
                     let out_buffer = filter.get_dsp_buffer::<f32>(output, samples);
-                    output_list.push(out_buffer.unwrap());
+                    output_list.push(out_buffer.unwrap_or_default());
                 }
 
                 // Check for inputs, output only filters don't need this
@@ -566,9 +656,43 @@ impl PipewireManager {
                     }
                 }
 
-                data.write()
-                    .callback
-                    .process_samples(input_list, output_list);
+                // Work out where the Panic Mute should be by the end of this period
+                let rate = position.clock.rate;
+                let panic_target = panic_mute_gain(&panic_mute);
+                let panic_step =
+                    (rate.num as f32 * 1000.) / (rate.denom.max(1) as f32 * PANIC_RAMP_MS);
+
+                let mut data = data.write();
+                if rate.denom > 0 && last_rate.replace(Some(rate.denom)) != Some(rate.denom) {
+                    data.callback.set_rate(rate.denom);
+                }
+
+                if data.bypassed {
+                    for out_buf in output_list.iter_mut() {
+                        out_buf.fill(0.0);
+                    }
+                    return;
+                }
+
+                // The filter still runs while muted, so its state is current when released
+                let outputs = output_list.iter_mut().map(|buf| &mut **buf).collect();
+                let start = thread_cpu_time();
+                data.callback.process_samples(input_list, outputs);
+                let elapsed = thread_cpu_time() - start;
+                apply_panic_ramp(&mut output_list, &panic_gain, panic_target, panic_step);
+
+                // The length of this period in seconds, derived from the driver's clock
+                let period = (samples as f64 * rate.num as f64) / rate.denom.max(1) as f64;
+
+                if elapsed > period * OVERRUN_FRACTION {
+                    data.overruns += 1;
+                    if data.overruns >= OVERRUN_LIMIT {
+                        data.bypassed = true;
+                        let _ = overrun_sender.send(PipewireReceiver::FilterOverrun(listener_id));
+                    }
+                } else {
+                    data.overruns = 0;
+                }
             })
             .register()
             .map_err(|e| anyhow!("Unable to Register Filter: {:?}", e))?;
@@ -628,6 +752,28 @@ impl PipewireManager {
         self.store.borrow().managed_filter_get_parameters(id)
     }
 
+    pub fn get_device_ports(&mut self, id: u32) -> Result<DevicePortMap> {
+        self.store.borrow().unmanaged_device_node_get_ports(id)
+    }
+
+    pub fn get_managed_links(&mut self) -> Result<Vec<ManagedLink>> {
+        Ok(self.store.borrow().managed_link_list())
+    }
+
+    pub fn set_panic_mute(&mut self, muted: bool) {
+        self.panic_mute.store(muted, Ordering::Relaxed);
+    }
+
+    pub fn set_realtime_priority(&mut self, priority: Option<u8>) {
+        self.realtime.set_priority(priority);
+    }
+
+    pub fn set_filter_bypass(&mut self, id: Ulid, bypass: bool) -> Result<()> {
+        self.store
+            .borrow_mut()
+            .managed_filter_set_bypass(id, bypass)
+    }
+
     pub fn set_filter_value(&mut self, id: Ulid, key: u32, value: FilterValue) -> Result<String> {
         // We need to grab the filter from the store, and pass the value set..
         self.store
@@ -639,10 +785,11 @@ impl PipewireManager {
         &mut self,
         source: LinkType,
         dest: LinkType,
+        passive: bool,
         sender: Sender<()>,
     ) -> Result<()> {
         // Fetch the details of the links that need creating
-        let mut group = self.prepare_links(source, dest, sender)?;
+        let mut group = self.prepare_links(source, dest, passive, sender)?;
 
         // Create a Parent ID for this link set
         let parent_id = Ulid::new();
@@ -665,6 +812,7 @@ impl PipewireManager {
         &mut self,
         source: LinkType,
         dest: LinkType,
+        passive: bool,
         sender: Sender<()>,
     ) -> Result<LinkStore> {
         // First, check if a managed link already exists and remove it
@@ -726,6 +874,9 @@ impl PipewireManager {
 
                 source_port: (src_id, src_index),
                 destination_port: (tgt_id, tgt_index),
+
+                passive,
+                name: link_name(&self.naming, &source, &dest, port),
             };
 
             port_map[port] = Some(store);
@@ -759,6 +910,21 @@ impl PipewireManager {
         Ok(())
     }
 
+    pub fn remove_unmanaged_node(&mut self, id: u32) -> Result<()> {
+        let mut store = self.store.borrow_mut();
+
+        // Never allow one of our own nodes to be destroyed this way
+        if store.is_managed_node(id) {
+            bail!("Node {} is managed, refusing to destroy", id);
+        }
+        if store.unmanaged_device_node_get(id).is_none() {
+            bail!("Unable to Locate Node {}", id);
+        }
+
+        self.registry.destroy_global(id);
+        Ok(())
+    }
+
     fn get_port(
         &mut self,
         link: &LinkType,
@@ -768,23 +934,34 @@ impl PipewireManager {
         // Ok, simple enough, pull out the relevant type, and get the port at location
         let mut store = self.store.borrow_mut();
         match link {
-            LinkType::Node(id) => {
+            LinkType::Node(id) | LinkType::NodeChannelPair(id, _) => {
                 let Some(node) = store.managed_node_get(*id) else {
                     bail!("Unable to Locate Node");
                 };
 
+                // Channels are laid out in Left / Right pairs, a plain Node uses the first
+                let pair = match link {
+                    LinkType::NodeChannelPair(_, pair) => *pair as usize,
+                    _ => 0,
+                };
+                let channel = pair * 2 + location as usize;
+
                 let id = node.pw_id.unwrap();
-                let port = node.port_map[location].unwrap();
+                let Some(Some(port)) = node.port_map.get(channel) else {
+                    bail!("Node has no Port for Channel {}", channel);
+                };
 
-                Ok((id, port))
+                Ok((id, *port))
             }
             LinkType::Filter(id) => {
                 let filter = store.managed_filter_get(*id).unwrap();
 
                 let id = filter.pw_id.unwrap();
-                let port = filter.port_map[direction][location];
+                let Some(port_map) = filter.port_map[direction] else {
+                    bail!("Filter has no {:?} Ports", direction);
+                };
 
-                Ok((id, port))
+                Ok((id, port_map[location]))
             }
             LinkType::UnmanagedNode(id, port_map) => {
                 let node = store
@@ -847,7 +1024,9 @@ impl PipewireManager {
                     *LINK_INPUT_NODE => dest_node.to_string(),
                     *LINK_INPUT_PORT => dest_port.to_string(),
                     *OBJECT_LINGER => "false",
-                    *NODE_PASSIVE => "false",
+                    *NODE_PASSIVE => map.passive.to_string(),
+                    "link.name" => map.name.as_str(),
+                    "object.label" => map.name.as_str(),
                 },
             )
             .map_err(|e| anyhow!("Failed to create link: {}", e))?;
@@ -1002,10 +1181,94 @@ impl Drop for PipewireManager {
     }
 }
 
+/// The gain a filter's output should settle at, given the current Panic Mute state
+fn panic_mute_gain(panic_mute: &AtomicBool) -> f32 {
+    if panic_mute.load(Ordering::Relaxed) {
+        0.
+    } else {
+        1.
+    }
+}
+
+/// CPU time used by the calling thread in seconds, unlike wall time this doesn't advance while
+/// the thread is preempted
+fn thread_cpu_time() -> f64 {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+
+    // SAFETY: The pointer is to a local which outlives the call
+    unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) };
+    time.tv_sec as f64 + time.tv_nsec as f64 / 1_000_000_000.
+}
+
+/// Moves the Panic Mute gain towards its target a sample at a time, and applies it to the outputs
+fn apply_panic_ramp(outputs: &mut [&mut [f32]], gain: &Cell<f32>, target: f32, step: f32) {
+    let start = gain.get();
+    if start == 1. && target == 1. {
+        return;
+    }
+
+    let mut end = start;
+    for output in outputs.iter_mut() {
+        let mut current = start;
+        for sample in output.iter_mut() {
+            current = if current < target {
+                (current + step).min(target)
+            } else {
+                (current - step).max(target)
+            };
+            *sample *= current;
+        }
+        end = current;
+    }
+    gain.set(end);
+}
+
+/// Builds the audio.position list for a node, the first pair is always FL / FR so that
+/// multichannel nodes still behave sensibly when treated as stereo.
+fn node_positions(channels: u8) -> String {
+    let mut positions = vec![String::from("FL"), String::from("FR")];
+    for channel in 2..channels {
+        positions.push(format!("AUX{}", channel));
+    }
+    positions.join(",")
+}
+
+/// Names a managed link after its endpoints, so our wiring can be identified in tools such as
+/// pw-dump or qpwgraph. This is purely informational, managed links are tracked by their ID.
+fn link_name(
+    naming: &NamingScheme,
+    source: &LinkType,
+    destination: &LinkType,
+    port: PortLocation,
+) -> String {
+    let describe = |link: &LinkType| match link {
+        LinkType::Node(id) => format!("node-{}", id),
+        LinkType::NodeChannelPair(id, pair) => format!("node-{}-{}", id, pair),
+        LinkType::Filter(id) => format!("filter-{}", id),
+        LinkType::UnmanagedNode(id, _) => format!("device-{}", id),
+    };
+
+    let port = match port {
+        PortLocation::Left => "left",
+        PortLocation::Right => "right",
+    };
+    format!(
+        "{}:{}->{}:{}",
+        naming.group_prefix,
+        describe(source),
+        describe(destination),
+        port
+    )
+}
+
 pub fn run_pw_main_loop(
     pw_rx: PWReceiver,
     start_tx: oneshot::Sender<anyhow::Result<()>>,
     callback_tx: mpsc::Sender<PipewireReceiver>,
+    naming: NamingScheme,
 ) {
     debug!("Initialising Pipewire..");
 
@@ -1075,9 +1338,17 @@ pub fn run_pw_main_loop(
         mainloop.clone(),
         registry,
         callback_tx.clone(),
+        naming,
     )));
     PipewireManager::create_core_listener(&manager);
 
+    // Let the store check nodes once their ports have finished arriving
+    let store = manager.borrow().store.clone();
+    let settle_store = Rc::downgrade(&store);
+    store
+        .borrow_mut()
+        .set_settle_loop(settle_store, mainloop.clone());
+
     let receiver_clone = mainloop.clone();
     let _receiver = pw_rx.attach(mainloop.loop_(), {
         move |message| match message {
@@ -1092,12 +1363,19 @@ pub fn run_pw_main_loop(
             PipewireInternalMessage::CreateFilterNode(props, result) => {
                 let _ = result.send(manager.borrow_mut().create_filter(props));
             }
-            PipewireInternalMessage::CreateDeviceLink(source, destination, sender, result) => {
-                let _ = result.send(
-                    manager
-                        .borrow_mut()
-                        .create_link(source, destination, sender),
-                );
+            PipewireInternalMessage::CreateDeviceLink(
+                source,
+                destination,
+                passive,
+                sender,
+                result,
+            ) => {
+                let _ = result.send(manager.borrow_mut().create_link(
+                    source,
+                    destination,
+                    passive,
+                    sender,
+                ));
             }
 
             PipewireInternalMessage::RemoveDeviceNode(id, result) => {
@@ -1115,6 +1393,10 @@ pub fn run_pw_main_loop(
                 let _ = result.send(manager.borrow_mut().remove_all_unmanaged_links(id));
             }
 
+            PipewireInternalMessage::DestroyUnmanagedNode(id, result) => {
+                let _ = result.send(manager.borrow_mut().remove_unmanaged_node(id));
+            }
+
             PipewireInternalMessage::GetFilterParameters(id, result) => {
                 let _ = result.send(manager.borrow_mut().get_filter_values(id));
             }
@@ -1123,6 +1405,28 @@ pub fn run_pw_main_loop(
                 let _ = result.send(manager.borrow_mut().set_filter_value(id, key, value));
             }
 
+            PipewireInternalMessage::SetFilterBypass(id, bypass, result) => {
+                let _ = result.send(manager.borrow_mut().set_filter_bypass(id, bypass));
+            }
+
+            PipewireInternalMessage::SetPanicMute(muted, result) => {
+                manager.borrow_mut().set_panic_mute(muted);
+                let _ = result.send(Ok(()));
+            }
+
+            PipewireInternalMessage::SetRealtimePriority(priority, result) => {
+                manager.borrow_mut().set_realtime_priority(priority);
+                let _ = result.send(Ok(()));
+            }
+
+            PipewireInternalMessage::GetDevicePorts(id, result) => {
+                let _ = result.send(manager.borrow_mut().get_device_ports(id));
+            }
+
+            PipewireInternalMessage::GetManagedLinks(result) => {
+                let _ = result.send(manager.borrow_mut().get_managed_links());
+            }
+
             PipewireInternalMessage::SetNodeVolume(id, volume, result) => {
                 let _ = result.send(manager.borrow_mut().set_node_volume(id, volume));
             }
@@ -1167,3 +1471,48 @@ pub fn run_pw_main_loop(
 
     info!("[PIPEWIRE] Main Loop Terminated");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naming(prefix: &str) -> NamingScheme {
+        NamingScheme {
+            app_id: "io.github.pipeweaver".into(),
+            app_name: "Pipeweaver".into(),
+            group_prefix: prefix.into(),
+        }
+    }
+
+    #[test]
+    fn link_names_use_the_configured_prefix() {
+        let source = Ulid::new();
+        let target = Ulid::new();
+        let name = link_name(
+            &naming("studio"),
+            &LinkType::Filter(source),
+            &LinkType::Node(target),
+            PortLocation::Right,
+        );
+        assert_eq!(
+            name,
+            format!("studio:filter-{}->node-{}:right", source, target)
+        );
+        assert!(!name.contains("pipeweaver"));
+    }
+
+    #[test]
+    fn link_names_describe_both_endpoints() {
+        let source = Ulid::new();
+        let name = link_name(
+            &naming("pipeweaver"),
+            &LinkType::NodeChannelPair(source, 2),
+            &LinkType::UnmanagedNode(42, None),
+            PortLocation::Left,
+        );
+        assert_eq!(
+            name,
+            format!("pipeweaver:node-{}-2->device-42:left", source)
+        );
+    }
+}
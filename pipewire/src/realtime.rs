@@ -0,0 +1,108 @@
+use crate::RealtimeStatus;
+use std::sync::atomic::{AtomicI32, AtomicU8, AtomicU32, Ordering};
+
+// Marks the original scheduling as not having been saved yet
+const UNKNOWN: i32 = -1;
+
+/// The scheduling requested for the thread running the filters. Pipewire owns that thread, so
+/// the request is only stored here, and applied by the filters from inside their process
+/// callbacks the next time they run.
+pub(crate) struct RealtimeRequest {
+    // 0 leaves the scheduling to Pipewire
+    priority: AtomicU8,
+
+    // Bumped on every change, so each callback knows to (re)apply it
+    generation: AtomicU32,
+
+    // What the thread was running with before we changed it, so it can be put back
+    original_policy: AtomicI32,
+    original_priority: AtomicI32,
+}
+
+impl RealtimeRequest {
+    pub(crate) fn new() -> Self {
+        Self {
+            priority: AtomicU8::new(0),
+            generation: AtomicU32::new(0),
+            original_policy: AtomicI32::new(UNKNOWN),
+            original_priority: AtomicI32::new(UNKNOWN),
+        }
+    }
+
+    pub(crate) fn set_priority(&self, priority: Option<u8>) {
+        self.priority
+            .store(priority.unwrap_or_default(), Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn generation(&self) -> u32 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Applies the request to the calling thread, and reports what it ended up with. This
+    /// makes a couple of syscalls, so should only be called when the generation changes.
+    pub(crate) fn apply(&self) -> RealtimeStatus {
+        let requested = match self.priority.load(Ordering::Relaxed) {
+            0 => None,
+            priority => Some(priority),
+        };
+
+        // SAFETY: These only operate on the calling thread, with params we own
+        unsafe {
+            let thread = libc::pthread_self();
+            let (policy, priority) = get_scheduling(thread);
+
+            let error = match requested {
+                Some(requested) => {
+                    if self.original_policy.load(Ordering::Relaxed) == UNKNOWN {
+                        self.original_policy.store(policy, Ordering::Relaxed);
+                        self.original_priority.store(priority, Ordering::Relaxed);
+                    }
+
+                    let max = libc::sched_get_priority_max(libc::SCHED_FIFO);
+                    let param = libc::sched_param {
+                        sched_priority: (requested as i32).min(max),
+                    };
+                    let policy = libc::SCHED_FIFO | libc::SCHED_RESET_ON_FORK;
+                    match libc::pthread_setschedparam(thread, policy, &param) {
+                        0 => None,
+                        error => Some(error),
+                    }
+                }
+                None => {
+                    // Hand the thread back how Pipewire set it up
+                    let policy = self.original_policy.swap(UNKNOWN, Ordering::Relaxed);
+                    let priority = self.original_priority.swap(UNKNOWN, Ordering::Relaxed);
+                    if policy == UNKNOWN {
+                        None
+                    } else {
+                        let param = libc::sched_param {
+                            sched_priority: priority,
+                        };
+                        match libc::pthread_setschedparam(thread, policy, &param) {
+                            0 => None,
+                            error => Some(error),
+                        }
+                    }
+                }
+            };
+
+            let (policy, priority) = get_scheduling(thread);
+            RealtimeStatus {
+                requested,
+                realtime: policy == libc::SCHED_FIFO || policy == libc::SCHED_RR,
+                priority,
+                error,
+            }
+        }
+    }
+}
+
+unsafe fn get_scheduling(thread: libc::pthread_t) -> (i32, i32) {
+    let mut policy = 0;
+    let mut param = libc::sched_param { sched_priority: 0 };
+
+    // SAFETY: Both pointers are to locals which outlive the call
+    unsafe { libc::pthread_getschedparam(thread, &mut policy, &mut param) };
+    (policy & !libc::SCHED_RESET_ON_FORK, param.sched_priority)
+}
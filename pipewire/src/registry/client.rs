@@ -75,6 +75,29 @@ impl RegistryClient {
     }
 }
 
+#[cfg(test)]
+impl RegistryClient {
+    pub(crate) fn test(application_name: &str, application_binary: &str) -> Self {
+        Self {
+            object_serial: 0,
+            module_id: 0,
+            protocol: "protocol-native".into(),
+            process_id: 0,
+            user_id: 0,
+            group_id: 0,
+            access: "unrestricted".into(),
+
+            application_name: application_name.into(),
+            application_binary: Some(application_binary.into()),
+
+            _proxy: None,
+            _listener: None,
+
+            nodes: vec![],
+        }
+    }
+}
+
 impl TryFrom<&DictRef> for RegistryClient {
     type Error = anyhow::Error;
 
@@ -6,7 +6,8 @@ use enum_map::EnumMap;
 use log::debug;
 use pipewire::core::Core;
 use pipewire::keys::{
-    DEVICE_ID, MEDIA_CLASS, NODE_DESCRIPTION, NODE_NAME, NODE_NICK, OBJECT_PATH, OBJECT_SERIAL,
+    DEVICE_ID, MEDIA_CLASS, NODE_DESCRIPTION, NODE_GROUP, NODE_NAME, NODE_NICK, OBJECT_PATH,
+    OBJECT_SERIAL,
 };
 use pipewire::node::{Node, NodeListener};
 use pipewire::registry::{GlobalObject, Registry};
@@ -88,6 +89,7 @@ pub(crate) struct RegistryDeviceNode {
     pub nickname: Option<String>,
     pub description: Option<String>,
     pub name: Option<String>,
+    pub group: Option<String>,
 
     pub(crate) _proxy: Option<Node>,
     pub(crate) _listener: Option<NodeListener>,
@@ -112,6 +114,7 @@ impl TryFrom<&DictRef> for RegistryDeviceNode {
         let nickname = value.get(*NODE_NICK).map(|s| s.to_string());
         let description = value.get(*NODE_DESCRIPTION).map(|s| s.to_string());
         let name = value.get(*NODE_NAME).map(|s| s.to_string());
+        let group = value.get(*NODE_GROUP).map(|s| s.to_string());
         let media_class = value.get(*MEDIA_CLASS).map(|s| s.to_string());
 
         // We need to match the media type here, it's only a device if it's a Sink or Source
@@ -142,6 +145,7 @@ impl TryFrom<&DictRef> for RegistryDeviceNode {
             nickname,
             description,
             name,
+            group,
 
             _proxy: None,
             _listener: None,
@@ -163,6 +167,7 @@ impl Debug for RegistryDeviceNode {
             .field("nickname", &self.nickname)
             .field("description", &self.description)
             .field("name", &self.name)
+            .field("group", &self.group)
             .finish()
     }
 }
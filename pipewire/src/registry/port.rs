@@ -46,9 +46,8 @@ pub fn handle_port(id: u32, global: &GlobalObject<&DictRef>, store: &mut Store)
                 store.unmanaged_node_port_add(node_id, direction, port);
                 return;
             }
-            if let Some(node) = store.unmanaged_client_node_get(node_id) {
-                node.add_port(port_id, direction, port);
-                store.unmanaged_client_node_check(node_id);
+            if store.unmanaged_client_node_get(node_id).is_some() {
+                store.unmanaged_client_node_port_add(node_id, port_id, direction, port);
             }
         }
     }
@@ -9,8 +9,8 @@ use crate::registry::link::RegistryLink;
 use crate::registry::metadata::MetadataStore;
 use crate::registry::port::RegistryPort;
 use crate::{
-    ApplicationNode, DeviceNode, Direction, FilterProperty, FilterValue, LinkType, MediaClass,
-    NodePort, NodeTarget, PipewireReceiver,
+    ApplicationNode, DeviceNode, DeviceNodePort, DevicePortMap, Direction, FilterProperty,
+    FilterValue, LinkType, ManagedLink, MediaClass, NodePort, NodeTarget, PipewireReceiver,
 };
 use anyhow::Result;
 use anyhow::{anyhow, bail};
@@ -18,9 +18,12 @@ use enum_map::{Enum, EnumMap};
 use log::{debug, error, info, trace, warn};
 use oneshot::Sender;
 use parking_lot::RwLock;
+use pipeweaver_shared::VolumeCurve;
 use pipewire::filter::{Filter, FilterListener, FilterPort};
 use pipewire::keys::MEDIA_CLASS;
 use pipewire::link::{Link, LinkListener};
+use pipewire::loop_::TimerSource;
+use pipewire::main_loop::MainLoop;
 use pipewire::node::{Node, NodeListener, NodeState};
 use pipewire::properties::Properties;
 use pipewire::proxy::ProxyListener;
@@ -34,13 +37,17 @@ use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::io::Cursor;
 use std::mem::discriminant;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::str::FromStr;
 use std::sync::mpsc;
+use std::time::{Duration, Instant};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use ulid::Ulid;
 
+// How long a node's ports need to be quiet before we check whether it's usable
+pub(crate) const PORT_SETTLE_TIME: Duration = Duration::from_millis(100);
+
 pub(crate) enum TargetType {
     Node(Option<u32>),
     Serial(Option<u32>),
@@ -82,10 +89,23 @@ pub struct Store {
     pub(crate) pending_device_syncs: HashMap<i32, u32>,
     pub(crate) pending_filter_syncs: HashMap<i32, Ulid>,
 
+    // Device and client nodes whose ports are still changing, and when they last changed
+    port_settle: HashMap<u32, Instant>,
+
+    // A single timer re-armed while nodes are settling, and whether a check is pending. The
+    // timer borrows the loop, so it's declared first to make sure it's dropped before it.
+    settle_timer: Option<TimerSource<'static>>,
+    settle_loop: Option<Rc<MainLoop>>,
+    settle_scheduled: bool,
+
     callback_tx: mpsc::Sender<PipewireReceiver>,
 }
 
 impl Store {
+    pub fn callback_sender(&self) -> mpsc::Sender<PipewireReceiver> {
+        self.callback_tx.clone()
+    }
+
     pub fn new(callback_tx: mpsc::Sender<PipewireReceiver>) -> Self {
         Self {
             session_proxy: None,
@@ -112,6 +132,11 @@ impl Store {
             pending_device_syncs: HashMap::new(),
             pending_filter_syncs: HashMap::new(),
 
+            port_settle: HashMap::new(),
+            settle_timer: None,
+            settle_loop: None,
+            settle_scheduled: false,
+
             usable_client_nodes: vec![],
 
             callback_tx,
@@ -126,6 +151,7 @@ impl Store {
         }
         info!("Session Proxy Found");
         self.session_proxy = Some(session);
+        let _ = self.callback_tx.send(PipewireReceiver::SessionManagerFound);
     }
 
     pub fn set_settings_proxy(&mut self, settings: MetadataStore) {
@@ -337,6 +363,27 @@ impl Store {
             .any(|node| node.pw_id == Some(id))
     }
 
+    /// Checks a node can be created under this id, and builds its initial volume. If a previous
+    /// attempt at the node failed and was never removed it's dropped, so it can't get in the
+    /// way of this one, but only once nothing else can fail.
+    pub fn managed_node_prepare(
+        &mut self,
+        id: Ulid,
+        curve: VolumeCurve,
+        volume: u8,
+        channels: u8,
+    ) -> Result<Vec<u8>> {
+        let existing = self.managed_nodes.get(&id).map(|n| &n.node_state);
+        let replace = node_replaceable(id, existing)?;
+        let bytes = channel_volume_pod(curve, volume, channels)?;
+
+        if replace {
+            warn!("[{}] Replacing failed Node in the Store", id);
+            self.managed_node_remove(id);
+        }
+        Ok(bytes)
+    }
+
     pub fn managed_node_add(&mut self, node: NodeStore) {
         debug!("[{}] Device Added to Store, waiting for data", &node.id);
         self.managed_nodes.insert(node.id, node);
@@ -399,6 +446,9 @@ impl Store {
 
         if let NodeStoreState::Error(error) = &state {
             error!("Node {} entered error state: {}", id, error);
+
+            // If we're still waiting on this node, it's never going to become ready
+            abort_ready(&mut node.ready_sender);
         }
 
         node.node_state = state;
@@ -407,18 +457,19 @@ impl Store {
 
     pub fn managed_node_request_ports(&self, id: Ulid) {
         let node = self.managed_nodes.get(&id).expect("Broke");
-        node.proxy
-            .enum_params(0, Some(ParamType::PortConfig), 0, u32::MAX);
+        if let Some(proxy) = &node.proxy {
+            proxy.enum_params(0, Some(ParamType::PortConfig), 0, u32::MAX);
+        }
     }
 
-    pub fn managed_node_add_port(&mut self, id: Ulid, location: PortLocation, port_id: u32) {
+    pub fn managed_node_add_port(&mut self, id: Ulid, channel: usize, port_id: u32) {
         let node = self.managed_nodes.get_mut(&id).expect("Broke");
-        node.port_map[location] = Some(port_id);
+        if let Some(port) = node.port_map.get_mut(channel) {
+            *port = Some(port_id);
+        }
 
-        for location in PortLocation::iter() {
-            if node.port_map[location].is_none() {
-                return;
-            }
+        if node.port_map.iter().any(|port| port.is_none()) {
+            return;
         }
 
         // If we get here, all our ports have been set, trigger the ready event
@@ -437,14 +488,17 @@ impl Store {
             .get_mut(&id)
             .expect("Attempted to lookup non-existing node!");
 
-        if node.ports_ready
-            && node.pw_id.is_some()
-            && !matches!(
-                node.node_state,
-                NodeStoreState::Creating | NodeStoreState::Error(_)
-            )
+        if node_is_ready(node.ports_ready, node.pw_id, &node.node_state)
             && let Some(sender) = node.ready_sender.take()
         {
+            // Now we're ready, apply anything that came in while we were setting up
+            let mut pending = std::mem::take(&mut node.pending);
+            pending.flush(
+                id,
+                |volume| node.send_volume(volume),
+                |muted| node.send_mute(muted),
+            );
+
             debug!("[{}] Device Ready, sending callback", &id);
             if let Some(sender) = sender {
                 let _ = sender.send(());
@@ -463,22 +517,16 @@ impl Store {
     pub fn set_volume(&mut self, id: Ulid, volume: u8) -> Result<()> {
         let node = self
             .managed_nodes
-            .get(&id)
+            .get_mut(&id)
             .ok_or(anyhow!("Failed to find node"))?;
 
-        let volume = (volume as f32 / 100.0).powi(3);
-        let pod = Value::Object(object! {
-            utils::SpaTypes::ObjectParamProps,
-            ParamType::Props,
-            Property::new(SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(vec![volume, volume]))),
-        });
-
-        let (cursor, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &pod).unwrap();
-        let bytes = cursor.into_inner();
-        if let Some(bytes) = Pod::from_bytes(&bytes) {
-            node.proxy.set_param(ParamType::Props, 0, bytes);
+        // The proxy may not accept params until the node is ready, so hold on to this
+        if !node.is_ready() {
+            debug!("[{}] Node not Ready, Queuing Volume {}", id, volume);
+            node.pending.volume = Some(volume);
+            return Ok(());
         }
-        Ok(())
+        node.send_volume(volume)
     }
 
     pub fn set_application_volume(&mut self, id: u32, volume: u8) -> Result<()> {
@@ -513,20 +561,15 @@ impl Store {
     pub fn set_mute(&mut self, id: Ulid, muted: bool) -> Result<()> {
         let node = self
             .managed_nodes
-            .get(&id)
+            .get_mut(&id)
             .ok_or(anyhow!("Failed to find node"))?;
 
-        let pod = Value::Object(object! {
-            utils::SpaTypes::ObjectParamProps,
-            ParamType::Props,
-            Property::new(SPA_PROP_mute, Value::Bool(muted)),
-        });
-        let (cursor, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &pod)?;
-        let bytes = cursor.into_inner();
-        if let Some(bytes) = Pod::from_bytes(&bytes) {
-            node.proxy.set_param(ParamType::Props, 0, bytes);
+        if !node.is_ready() {
+            debug!("[{}] Node not Ready, Queuing Mute {}", id, muted);
+            node.pending.mute = Some(muted);
+            return Ok(());
         }
-        Ok(())
+        node.send_mute(muted)
     }
     pub fn set_application_muted(&mut self, id: u32, muted: bool) -> Result<()> {
         let node = self
@@ -568,6 +611,15 @@ impl Store {
         }
     }
 
+    pub fn managed_filter_error(&mut self, id: Ulid, error: &str) {
+        error!("Filter {} entered error state: {}", id, error);
+
+        // As with nodes, a filter which has failed is never going to become ready
+        if let Some(filter) = self.managed_filters.get_mut(&id) {
+            abort_ready(&mut filter.ready_sender);
+        }
+    }
+
     pub fn managed_filter_add(&mut self, filter: FilterStore) {
         debug!("[{}] Filter Added to Store", &filter.id);
         self.managed_filters.insert(filter.id, filter);
@@ -603,6 +655,18 @@ impl Store {
         filter.data.write().callback.set_property(key, value)
     }
 
+    pub fn managed_filter_set_bypass(&mut self, id: Ulid, bypass: bool) -> Result<()> {
+        let filter = self
+            .managed_filters
+            .get(&id)
+            .ok_or(anyhow!("Filter Not Found"))?;
+
+        let mut data = filter.data.write();
+        data.bypassed = bypass;
+        data.overruns = 0;
+        Ok(())
+    }
+
     pub fn managed_filter_get_parameters(&self, id: Ulid) -> Result<Vec<FilterProperty>> {
         // Find the filter
         let filter = self
@@ -629,6 +693,21 @@ impl Store {
             .copied()
     }
 
+    pub fn managed_link_list(&self) -> Vec<ManagedLink> {
+        self.managed_links
+            .values()
+            .map(|link| ManagedLink {
+                source: link.source.clone(),
+                destination: link.destination.clone(),
+                connected: PortLocation::iter().all(|port| {
+                    link.links[port]
+                        .as_ref()
+                        .is_some_and(|port| port.pw_id.is_some())
+                }),
+            })
+            .collect()
+    }
+
     pub fn managed_link_add(&mut self, id: Ulid, group: LinkStore) {
         self.managed_links.insert(id, group);
     }
@@ -963,6 +1042,29 @@ impl Store {
         self.unmanaged_device_nodes.get_mut(&id)
     }
 
+    pub fn unmanaged_device_node_get_ports(&self, id: u32) -> Result<DevicePortMap> {
+        let node = self
+            .unmanaged_device_nodes
+            .get(&id)
+            .ok_or(anyhow!("Device Node {} not Found", id))?;
+
+        let mut ports: DevicePortMap = Default::default();
+        for direction in Direction::iter() {
+            for port in node.ports[direction].values() {
+                ports[direction].push(DeviceNodePort {
+                    port_id: port.global_id,
+                    name: port.name.clone(),
+                    channel: port.channel.clone(),
+                    is_monitor: port.is_monitor,
+                });
+            }
+
+            // The port map is unordered, keep things stable for clients
+            ports[direction].sort_by_key(|port| port.port_id);
+        }
+        Ok(ports)
+    }
+
     pub fn unmanaged_device_node_remove(&mut self, id: u32) {
         // Need to flag upstream if the node has gone away
         if self.unmanaged_device_nodes.contains_key(&id) {
@@ -970,6 +1072,7 @@ impl Store {
         }
 
         self.unmanaged_device_nodes.remove(&id);
+        self.port_settle.remove(&id);
         for client in self.unmanaged_devices.values_mut() {
             client.nodes.retain(|n| n != &id);
         }
@@ -1017,7 +1120,7 @@ impl Store {
         node.port_count[Direction::In] = Some(in_count);
         node.port_count[Direction::Out] = Some(out_count);
 
-        self.unmanaged_node_reconcile(id);
+        self.unmanaged_node_port_changed(id);
     }
 
     pub fn unmanaged_node_port_removed(&mut self, node_id: u32, dir: Direction, port: u32) {
@@ -1025,7 +1128,7 @@ impl Store {
             node.ports[dir].remove(&port);
         }
 
-        self.unmanaged_node_reconcile(node_id);
+        self.unmanaged_node_port_changed(node_id);
     }
 
     pub fn unmanaged_node_port_add(&mut self, node_id: u32, dir: Direction, port: RegistryPort) {
@@ -1033,7 +1136,80 @@ impl Store {
             node.add_port(dir, port);
         }
 
-        self.unmanaged_node_reconcile(node_id);
+        self.unmanaged_node_port_changed(node_id);
+    }
+
+    fn unmanaged_node_port_changed(&mut self, id: u32) {
+        // Nodes with a lot of ports announce them one at a time, so rather than re-checking
+        // the node on every change (and potentially flapping it up and down), wait until its
+        // ports have been quiet for a moment, then check it once.
+        if self.unmanaged_device_nodes.contains_key(&id)
+            || self.unmanaged_client_nodes.contains_key(&id)
+        {
+            self.port_settle.insert(id, Instant::now());
+            self.unmanaged_node_schedule_settle(PORT_SETTLE_TIME);
+        }
+    }
+
+    /// Lets the store schedule its own settle checks on the main loop, without this ports are
+    /// only checked when unmanaged_node_settle is called.
+    pub fn set_settle_loop(&mut self, store: Weak<RefCell<Store>>, mainloop: Rc<MainLoop>) {
+        let timer = mainloop.loop_().add_timer(move |_| {
+            if let Some(store) = store.upgrade() {
+                store.borrow_mut().unmanaged_node_settle(Instant::now());
+            }
+        });
+
+        // SAFETY: The timer only borrows the loop, which we hold a strong reference to for as
+        // long as the timer exists, settle_timer is declared first so is dropped before it.
+        let timer = unsafe { std::mem::transmute::<TimerSource<'_>, TimerSource<'static>>(timer) };
+
+        self.settle_timer = Some(timer);
+        self.settle_loop = Some(mainloop);
+    }
+
+    /// Reconciles any nodes whose ports have settled by `now`, and schedules another check if
+    /// any are still settling.
+    pub fn unmanaged_node_settle(&mut self, now: Instant) {
+        self.settle_scheduled = false;
+
+        let settled: Vec<u32> = self
+            .port_settle
+            .iter()
+            .filter(|(_, changed)| now.saturating_duration_since(**changed) >= PORT_SETTLE_TIME)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in settled {
+            self.port_settle.remove(&id);
+            if self.unmanaged_device_nodes.contains_key(&id) {
+                self.unmanaged_node_reconcile(id);
+            } else {
+                self.unmanaged_client_node_check(id);
+            }
+        }
+
+        // Wait for whichever of the remaining nodes will settle first
+        let next = self.port_settle.values().min().copied();
+        if let Some(changed) = next {
+            let elapsed = now.saturating_duration_since(changed);
+            self.unmanaged_node_schedule_settle(PORT_SETTLE_TIME.saturating_sub(elapsed));
+        }
+    }
+
+    fn unmanaged_node_schedule_settle(&mut self, delay: Duration) {
+        // A pending check will schedule the next one itself if anything is still settling
+        if self.settle_scheduled {
+            return;
+        }
+        let Some(timer) = &self.settle_timer else {
+            return;
+        };
+
+        // A zero delay would disarm the timer rather than fire it straight away
+        let delay = delay.max(Duration::from_millis(1));
+        timer.update_timer(Some(delay), None);
+        self.settle_scheduled = true;
     }
 
     fn unmanaged_node_reconcile(&mut self, id: u32) {
@@ -1109,6 +1285,11 @@ impl Store {
             return;
         }
 
+        if self.port_settle.contains_key(&id) {
+            // Ports are still arriving, this node will be checked once they've settled
+            return;
+        }
+
         // Check if we have port count expectations for both directions
         let has_port_count_info =
             node.port_count[Direction::In].is_some() && node.port_count[Direction::Out].is_some();
@@ -1212,6 +1393,7 @@ impl Store {
             name: node.name.clone(),
             nickname: node.nickname.clone(),
             description: node.description.clone(),
+            group: node.group.clone(),
 
             volume: node.volume,
             muted: node.muted,
@@ -1376,6 +1558,20 @@ impl Store {
         self.unmanaged_client_nodes.get_mut(&id)
     }
 
+    pub fn unmanaged_client_node_port_add(
+        &mut self,
+        node_id: u32,
+        port_id: u32,
+        dir: Direction,
+        port: RegistryPort,
+    ) {
+        if let Some(node) = self.unmanaged_client_nodes.get_mut(&node_id) {
+            node.add_port(port_id, dir, port);
+        }
+
+        self.unmanaged_node_port_changed(node_id);
+    }
+
     pub fn unmanaged_client_node_set_volume(&mut self, id: u32, volume: u8) {
         if let Some(node) = self.unmanaged_client_node_get(id)
             && node.volume != volume
@@ -1520,6 +1716,7 @@ impl Store {
         }
 
         self.unmanaged_client_nodes.remove(&id);
+        self.port_settle.remove(&id);
         for client in self.unmanaged_clients.values_mut() {
             client.nodes.retain(|n| n != &id);
         }
@@ -1539,6 +1736,11 @@ impl Store {
             return;
         }
 
+        if self.port_settle.contains_key(&id) {
+            // Ports are still arriving, this node will be checked once they've settled
+            return;
+        }
+
         if let Some(node) = self.unmanaged_client_nodes.get(&id)
             && let Some(media_type) = self.is_usable_unmanaged_client_node(id)
             && let Some(parent) = self.unmanaged_clients.get(&node.parent_id)
@@ -1663,6 +1865,17 @@ impl Store {
             return self.unmanaged_link_remove(id);
         }
 
+        // Something (often a session manager) may have destroyed one of our nodes, forget about
+        // it and report back, so it can be rebuilt.
+        if let Some(node) = self.managed_node_find_by_node_id(id) {
+            warn!("[{}] Managed Node removed by Pipewire", node);
+            self.managed_node_remove(node);
+            let _ = self
+                .callback_tx
+                .send(PipewireReceiver::ManagedNodeDropped(node));
+            return;
+        }
+
         // Something may be trying to mess with a managed link, if so, completely drop our links
         // and report back to whatever is calling us that it's happened, so they can action it.
         if let Some(id) = self.is_managed_link(id) {
@@ -1731,17 +1944,117 @@ pub(crate) struct NodeStore {
     pub(crate) id: Ulid,
     pub(crate) props: Properties,
 
-    pub(crate) proxy: Node,
-    pub(crate) _proxy_listener: ProxyListener,
-    pub(crate) _listener: NodeListener,
+    pub(crate) proxy: Option<Node>,
+    pub(crate) _proxy_listener: Option<ProxyListener>,
+    pub(crate) _listener: Option<NodeListener>,
 
     // Nodes will always have inputs and outputs which directly link together, so we
-    // don't need to track each side, we just need the ID and Location
-    pub(crate) port_map: EnumMap<PortLocation, Option<u32>>,
+    // don't need to track each side, we just need the ID for each channel
+    pub(crate) port_map: Vec<Option<u32>>,
     pub(crate) ports_ready: bool,
+
+    pub(crate) volume_curve: VolumeCurve,
     pub(crate) node_state: NodeStoreState,
 
+    // Volume / Mute changes which arrived before the node was ready
+    pub(crate) pending: PendingParams,
+
     pub(crate) ready_sender: Option<Option<Sender<()>>>,
+
+    // Props sent to the node, tests don't have a proxy to send them to
+    #[cfg(test)]
+    pub(crate) sent_props: RefCell<Vec<Vec<u8>>>,
+}
+
+impl NodeStore {
+    // The ready sender is taken once the node has been flagged as ready
+    fn is_ready(&self) -> bool {
+        self.ready_sender.is_none()
+    }
+
+    fn send_volume(&self, volume: u8) -> Result<()> {
+        let channels = self.port_map.len() as u8;
+        let bytes = channel_volume_pod(self.volume_curve, volume, channels)?;
+        self.send_props(&bytes);
+        Ok(())
+    }
+
+    fn send_mute(&self, muted: bool) -> Result<()> {
+        let pod = Value::Object(object! {
+            utils::SpaTypes::ObjectParamProps,
+            ParamType::Props,
+            Property::new(SPA_PROP_mute, Value::Bool(muted)),
+        });
+        let (cursor, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &pod)?;
+        self.send_props(&cursor.into_inner());
+        Ok(())
+    }
+
+    fn send_props(&self, bytes: &[u8]) {
+        #[cfg(test)]
+        self.sent_props.borrow_mut().push(bytes.to_vec());
+
+        if let Some(proxy) = &self.proxy
+            && let Some(pod) = Pod::from_bytes(bytes)
+        {
+            proxy.set_param(ParamType::Props, 0, pod);
+        }
+    }
+}
+
+/// Checks whether a node can be created under an id which may already be in the store,
+/// returning true if an existing node needs removing first. Only a node which has gone into
+/// an error state can be replaced, anything else is still alive and in use.
+fn node_replaceable(id: Ulid, existing: Option<&NodeStoreState>) -> Result<bool> {
+    match existing {
+        None => Ok(false),
+        Some(NodeStoreState::Error(_)) => Ok(true),
+        Some(state) => bail!("[{}] Node already exists ({:?})", id, state),
+    }
+}
+
+/// Drops a ready sender which hasn't fired yet, so whoever's waiting on it is told creation
+/// was aborted, rather than hanging.
+fn abort_ready(ready_sender: &mut Option<Option<Sender<()>>>) {
+    if let Some(sender) = ready_sender.as_mut() {
+        sender.take();
+    }
+}
+
+/// A node is ready once its ports are configured, it has a Pipewire id, and it's settled
+/// into a working state.
+fn node_is_ready(ports_ready: bool, pw_id: Option<u32>, state: &NodeStoreState) -> bool {
+    ports_ready
+        && pw_id.is_some()
+        && !matches!(state, NodeStoreState::Creating | NodeStoreState::Error(_))
+}
+
+/// Volume / Mute changes which arrived before a node was ready to accept them, only the most
+/// recent of each is kept.
+#[derive(Debug, Default)]
+pub(crate) struct PendingParams {
+    volume: Option<u8>,
+    mute: Option<bool>,
+}
+
+impl PendingParams {
+    fn flush(
+        &mut self,
+        id: Ulid,
+        send_volume: impl FnOnce(u8) -> Result<()>,
+        send_mute: impl FnOnce(bool) -> Result<()>,
+    ) {
+        if let Some(volume) = self.volume.take()
+            && let Err(e) = send_volume(volume)
+        {
+            warn!("[{}] Unable to apply Queued Volume: {}", &id, e);
+        }
+        if let Some(muted) = self.mute.take()
+            && let Err(e) = send_mute(muted)
+        {
+            warn!("[{}] Unable to apply Queued Mute: {}", &id, e);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1774,8 +2087,9 @@ pub struct FilterStore {
     /// The Ulid Identifier
     pub(crate) id: Ulid,
 
-    // This maintains a general port map of location -> index
-    pub(crate) port_map: EnumMap<Direction, EnumMap<PortLocation, u32>>,
+    // This maintains a general port map of location -> index, None if there are no ports in
+    // that direction (eg. a generator has no inputs)
+    pub(crate) port_map: EnumMap<Direction, Option<EnumMap<PortLocation, u32>>>,
 
     /// Details of the ports assigned to this filter
     pub(crate) _input_ports: Rc<RefCell<Vec<FilterPort>>>,
@@ -1816,6 +2130,12 @@ pub struct LinkStoreMap {
     /// Internal Port Index Mapping
     pub(crate) source_port: (u32, u32),
     pub(crate) destination_port: (u32, u32),
+
+    /// Passive links won't keep their endpoints awake, allowing hardware to idle
+    pub(crate) passive: bool,
+
+    /// A descriptive name for the link, so it can be identified in external tools
+    pub(crate) name: String,
 }
 
 #[derive(Debug, Enum, EnumIter, Copy, Clone, PartialEq)]
@@ -1856,3 +2176,420 @@ pub struct PendingLinkSync {
     pub group: LinkStore,
     pub bound_ids: HashMap<Ulid, u32>, // link_id -> pw_id collected during sync wait
 }
+
+/// Builds the Props pod which sets every channel of a node to the same volume
+pub(crate) fn channel_volume_pod(curve: VolumeCurve, volume: u8, channels: u8) -> Result<Vec<u8>> {
+    if channels == 0 {
+        bail!("Unable to set the Volume of a Node with no channels");
+    }
+
+    let volume = curve.to_linear(volume);
+    let pod = Value::Object(object! {
+        utils::SpaTypes::ObjectParamProps,
+        ParamType::Props,
+        Property::new(SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(vec![volume; channels as usize]))),
+    });
+
+    let (cursor, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &pod)?;
+    Ok(cursor.into_inner())
+}
+
+/// Maps the channel volumes reported by Pipewire back to a volume. KDE and pwvucontrol use
+/// the highest channel as their reference, so we do the same.
+pub(crate) fn channel_volume(curve: VolumeCurve, volumes: &[f32]) -> Option<u8> {
+    let max = volumes
+        .iter()
+        .copied()
+        .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))?;
+    Some(curve.from_linear(max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enum_map::enum_map;
+    use pipewire::spa::pod::deserialize::PodDeserializer;
+
+    fn store() -> (Store, mpsc::Receiver<PipewireReceiver>) {
+        let (tx, rx) = mpsc::channel();
+        (Store::new(tx), rx)
+    }
+
+    fn port(id: u32, channel: &str, is_monitor: bool) -> RegistryPort {
+        RegistryPort::new(id, &format!("port_{}", channel), channel, is_monitor)
+    }
+
+    fn device_node() -> RegistryDeviceNode {
+        RegistryDeviceNode {
+            object_serial: 1,
+            parent_id: None,
+            object_path: None,
+
+            media_class: Some("Audio/Sink".into()),
+            is_usable: false,
+            clock_ready: true,
+            is_synced: true,
+
+            volume: 100,
+            muted: false,
+
+            nickname: None,
+            description: None,
+            name: Some("test_sink".into()),
+            group: None,
+
+            _proxy: None,
+            _listener: None,
+
+            port_count: enum_map! {
+                Direction::In => Some(2),
+                Direction::Out => Some(2),
+            },
+            ports: Default::default(),
+            sent_upstream: false,
+        }
+    }
+
+    fn client_node(parent_id: u32) -> RegistryClientNode {
+        RegistryClientNode {
+            object_serial: 2,
+            parent_id,
+
+            metadata: None,
+
+            application_name: "Test".into(),
+            node_name: "test_stream".into(),
+
+            volume: 100,
+            media_title: None,
+
+            n_input_ports: 0,
+            n_output_ports: 0,
+            is_running: Some(true),
+            is_muted: false,
+
+            media_target: None,
+
+            proxy: None,
+            _listener: None,
+
+            ports: Default::default(),
+        }
+    }
+
+    fn count(rx: &mpsc::Receiver<PipewireReceiver>, check: fn(&PipewireReceiver) -> bool) -> usize {
+        rx.try_iter().filter(|message| check(message)).count()
+    }
+
+    #[test]
+    fn staggered_device_ports_are_checked_once_settled() {
+        let (mut store, rx) = store();
+        store.unmanaged_device_node_add(10, device_node());
+
+        let ports = [
+            (Direction::In, port(11, "FL", false)),
+            (Direction::In, port(12, "FR", false)),
+            (Direction::Out, port(13, "FL", true)),
+            (Direction::Out, port(14, "FR", true)),
+        ];
+        for (direction, port) in ports {
+            let arrived = Instant::now();
+            store.unmanaged_node_port_add(10, direction, port);
+
+            // Every arrival restarts the wait, so nothing is checked while they're trickling in
+            store.unmanaged_node_settle(arrived + PORT_SETTLE_TIME / 2);
+            assert_eq!(
+                count(&rx, |m| matches!(m, PipewireReceiver::DeviceAdded(_))),
+                0
+            );
+        }
+
+        store.unmanaged_node_settle(Instant::now() + PORT_SETTLE_TIME);
+        assert_eq!(
+            count(&rx, |m| matches!(m, PipewireReceiver::DeviceAdded(_))),
+            1
+        );
+        assert!(store.port_settle.is_empty());
+    }
+
+    #[test]
+    fn device_port_flaps_are_not_sent_upstream() {
+        let (mut store, rx) = store();
+        store.unmanaged_device_node_add(10, device_node());
+        store.unmanaged_node_port_add(10, Direction::In, port(11, "FL", false));
+        store.unmanaged_node_port_add(10, Direction::In, port(12, "FR", false));
+        store.unmanaged_node_port_add(10, Direction::Out, port(13, "FL", true));
+        store.unmanaged_node_port_add(10, Direction::Out, port(14, "FR", true));
+        store.unmanaged_node_settle(Instant::now() + PORT_SETTLE_TIME);
+        let _ = rx.try_iter().count();
+
+        // A port dropping and coming straight back shouldn't remove the device
+        store.unmanaged_node_port_removed(10, Direction::In, 12);
+        store.unmanaged_node_port_add(10, Direction::In, port(12, "FR", false));
+        store.unmanaged_node_settle(Instant::now() + PORT_SETTLE_TIME);
+        assert_eq!(
+            count(&rx, |m| matches!(m, PipewireReceiver::DeviceRemoved(_))),
+            0
+        );
+    }
+
+    #[test]
+    fn staggered_client_ports_are_checked_once_settled() {
+        let (mut store, rx) = store();
+        store.unmanaged_client_add(20, RegistryClient::test("Test", "test"));
+        store.unmanaged_client_node_add(21, client_node(20));
+
+        // Announcing now would see a mono stream, wait for the second channel
+        let arrived = Instant::now();
+        store.unmanaged_client_node_port_add(21, 1, Direction::Out, port(22, "FL", false));
+        store.unmanaged_node_settle(arrived + PORT_SETTLE_TIME / 2);
+        assert_eq!(
+            count(&rx, |m| matches!(m, PipewireReceiver::ApplicationAdded(_))),
+            0
+        );
+
+        store.unmanaged_client_node_port_add(21, 2, Direction::Out, port(23, "FR", false));
+        store.unmanaged_node_settle(Instant::now() + PORT_SETTLE_TIME);
+        assert_eq!(
+            count(&rx, |m| matches!(m, PipewireReceiver::ApplicationAdded(_))),
+            1
+        );
+        assert!(store.port_settle.is_empty());
+    }
+
+    fn pod_volumes(bytes: &[u8]) -> Vec<f32> {
+        let (_, value) = PodDeserializer::deserialize_any_from(bytes).unwrap();
+        let Value::Object(object) = value else {
+            panic!("Volume isn't an Object");
+        };
+        let prop = object
+            .properties
+            .into_iter()
+            .find(|p| p.key == SPA_PROP_channelVolumes)
+            .unwrap();
+        let Value::ValueArray(ValueArray::Float(volumes)) = prop.value else {
+            panic!("Channel Volumes aren't Floats");
+        };
+        volumes
+    }
+
+    #[test]
+    fn channel_volumes_round_trip() {
+        for curve in [VolumeCurve::Cubic, VolumeCurve::Linear] {
+            for volume in 0..=100 {
+                let bytes = channel_volume_pod(curve, volume, 2).unwrap();
+                let volumes = pod_volumes(&bytes);
+                assert_eq!(volumes.len(), 2);
+                assert_eq!(channel_volume(curve, &volumes), Some(volume));
+            }
+        }
+    }
+
+    #[test]
+    fn initial_volume_sets_every_channel() {
+        let bytes = channel_volume_pod(VolumeCurve::Linear, 50, 4).unwrap();
+        assert_eq!(pod_volumes(&bytes), vec![0.5; 4]);
+    }
+
+    #[test]
+    fn channel_volume_follows_the_loudest_channel() {
+        let curve = VolumeCurve::Cubic;
+        let volumes = [curve.to_linear(20), curve.to_linear(80)];
+        assert_eq!(channel_volume(curve, &volumes), Some(80));
+        assert_eq!(channel_volume(curve, &[]), None);
+    }
+
+    #[test]
+    fn nodes_are_not_ready_until_created() {
+        assert!(!node_is_ready(false, Some(1), &NodeStoreState::Idle));
+        assert!(!node_is_ready(true, None, &NodeStoreState::Idle));
+        assert!(!node_is_ready(true, Some(1), &NodeStoreState::Creating));
+        assert!(!node_is_ready(
+            true,
+            Some(1),
+            &NodeStoreState::Error("Failed".into())
+        ));
+        assert!(node_is_ready(true, Some(1), &NodeStoreState::Suspending));
+        assert!(node_is_ready(true, Some(1), &NodeStoreState::Running));
+    }
+
+    fn managed_node(id: Ulid, ready_sender: Sender<()>) -> NodeStore {
+        NodeStore {
+            pw_id: None,
+            object_serial: None,
+            id,
+            props: Properties::new(),
+            proxy: None,
+            _proxy_listener: None,
+            _listener: None,
+
+            port_map: vec![None; 2],
+            ports_ready: false,
+            volume_curve: VolumeCurve::Cubic,
+            node_state: NodeStoreState::Creating,
+            pending: Default::default(),
+            ready_sender: Some(Some(ready_sender)),
+            sent_props: Default::default(),
+        }
+    }
+
+    fn sent_props(store: &Store, id: Ulid) -> Vec<Vec<u8>> {
+        store
+            .managed_node_get(id)
+            .unwrap()
+            .sent_props
+            .borrow()
+            .clone()
+    }
+
+    fn pod_mute(bytes: &[u8]) -> bool {
+        let (_, value) = PodDeserializer::deserialize_any_from(bytes).unwrap();
+        let Value::Object(object) = value else {
+            panic!("Mute isn't an Object");
+        };
+        let prop = object
+            .properties
+            .into_iter()
+            .find(|p| p.key == SPA_PROP_mute);
+        let Some(Property {
+            value: Value::Bool(muted),
+            ..
+        }) = prop
+        else {
+            panic!("Mute isn't a Bool");
+        };
+        muted
+    }
+
+    #[test]
+    fn queued_volume_is_applied_once_ready() {
+        let (mut store, _rx) = store();
+        let (sender, receiver) = oneshot::channel();
+        let id = Ulid::new();
+        store.managed_node_add(managed_node(id, sender));
+
+        // Pipewire hasn't finished creating the node, so these have to wait
+        store.set_volume(id, 60).unwrap();
+        store.set_mute(id, true).unwrap();
+        store.managed_node_set_pw_id(id, 30);
+        store.managed_node_add_port(id, 0, 31);
+        store.managed_node_add_port(id, 1, 32);
+        assert!(sent_props(&store, id).is_empty());
+        assert!(receiver.try_recv().is_err());
+
+        // Once it's ready what was queued is sent, before anyone is told it's ready
+        store.managed_node_state_changed(id, NodeStoreState::Idle);
+        let sent = sent_props(&store, id);
+        assert_eq!(sent.len(), 2);
+        let expected = VolumeCurve::Cubic.to_linear(60);
+        assert_eq!(pod_volumes(&sent[0]), vec![expected, expected]);
+        assert!(pod_mute(&sent[1]));
+        assert!(receiver.try_recv().is_ok());
+
+        // Further checks don't send it again, and new changes go straight out
+        store.managed_node_state_changed(id, NodeStoreState::Running);
+        store.managed_node_check_ready(id);
+        assert_eq!(sent_props(&store, id).len(), 2);
+
+        store.set_mute(id, false).unwrap();
+        let sent = sent_props(&store, id);
+        assert_eq!(sent.len(), 3);
+        assert!(!pod_mute(&sent[2]));
+    }
+
+    #[test]
+    fn live_nodes_are_not_replaced() {
+        let id = Ulid::new();
+        assert!(!node_replaceable(id, None).unwrap());
+        assert!(node_replaceable(id, Some(&NodeStoreState::Creating)).is_err());
+        assert!(node_replaceable(id, Some(&NodeStoreState::Running)).is_err());
+
+        // Preparing over a live node fails, and leaves it alone
+        let (mut store, _rx) = store();
+        let (sender, _receiver) = oneshot::channel();
+        store.managed_node_add(managed_node(id, sender));
+        store.managed_node_state_changed(id, NodeStoreState::Running);
+        assert!(
+            store
+                .managed_node_prepare(id, VolumeCurve::Cubic, 100, 2)
+                .is_err()
+        );
+        let node = store.managed_node_get(id).unwrap();
+        assert!(matches!(node.node_state, NodeStoreState::Running));
+    }
+
+    #[test]
+    fn failed_nodes_are_replaced() {
+        let id = Ulid::new();
+        let (mut store, _rx) = store();
+        let (sender, _receiver) = oneshot::channel();
+        store.managed_node_add(managed_node(id, sender));
+        store.managed_node_state_changed(id, NodeStoreState::Error("Device Busy".into()));
+
+        // A retry which can't be built fails before the failed node is removed
+        assert!(
+            store
+                .managed_node_prepare(id, VolumeCurve::Cubic, 100, 0)
+                .is_err()
+        );
+        let node = store.managed_node_get(id).unwrap();
+        assert!(matches!(node.node_state, NodeStoreState::Error(_)));
+
+        // One which can replaces it, leaving nothing behind from the old attempt
+        let bytes = store
+            .managed_node_prepare(id, VolumeCurve::Linear, 50, 2)
+            .unwrap();
+        assert_eq!(pod_volumes(&bytes), vec![0.5; 2]);
+        assert!(store.managed_node_get(id).is_none());
+
+        let (sender, receiver) = oneshot::channel();
+        store.managed_node_add(managed_node(id, sender));
+        store.managed_node_set_pw_id(id, 40);
+        store.managed_node_ports_ready(id);
+        store.managed_node_state_changed(id, NodeStoreState::Idle);
+        assert!(receiver.try_recv().is_ok());
+    }
+
+    #[test]
+    fn failed_volume_still_flushes_mute() {
+        let mut pending = PendingParams {
+            volume: Some(40),
+            mute: Some(false),
+        };
+
+        let mut muted = None;
+        pending.flush(
+            Ulid::new(),
+            |_| bail!("Proxy Gone"),
+            |value| {
+                muted = Some(value);
+                Ok(())
+            },
+        );
+        assert_eq!(muted, Some(false));
+        assert!(pending.volume.is_none());
+    }
+
+    #[test]
+    fn aborting_drops_a_waiting_sender() {
+        let (sender, receiver) = oneshot::channel();
+        let mut ready_sender = Some(Some(sender));
+        abort_ready(&mut ready_sender);
+
+        // Still flagged as not ready, but the waiter is told it's never going to be
+        assert!(matches!(ready_sender, Some(None)));
+        assert!(receiver.recv().is_err());
+    }
+
+    #[test]
+    fn aborting_a_ready_object_does_nothing() {
+        let mut ready_sender = None;
+        abort_ready(&mut ready_sender);
+        assert!(ready_sender.is_none());
+
+        // Aborting twice (eg. repeated error states) is harmless
+        let mut ready_sender = Some(None);
+        abort_ready(&mut ready_sender);
+        assert!(matches!(ready_sender, Some(None)));
+    }
+}
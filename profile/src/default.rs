@@ -4,7 +4,7 @@ use crate::{
     VirtualTargetDevice, Volumes,
 };
 use enum_map::enum_map;
-use pipeweaver_shared::{Colour, DeviceType, OrderGroup};
+use pipeweaver_shared::{Colour, DeviceType, OrderGroup, VolumeCurve};
 use std::collections::{HashMap, HashSet};
 use ulid::Ulid;
 
@@ -28,10 +28,12 @@ impl Profile {
                                 green: 24,
                                 blue: 71,
                             },
+                            eq: None,
                         },
                         mute_states: MuteStates {
                             mute_state: HashSet::new(),
                             mute_targets: Default::default(),
+                            ..Default::default()
                         },
                         volumes: Volumes {
                             volume: enum_map! {
@@ -43,6 +45,7 @@ impl Profile {
                         sync_with_devices: false,
                         attached_devices: vec![],
                         attached_port_maps: vec![],
+                        record_armed: false,
                     }],
                     virtual_devices: vec![
                         VirtualSourceDevice {
@@ -54,10 +57,12 @@ impl Profile {
                                     green: 98,
                                     blue: 30,
                                 },
+                                eq: None,
                             },
                             mute_states: MuteStates {
                                 mute_state: HashSet::new(),
                                 mute_targets: Default::default(),
+                                ..Default::default()
                             },
                             volumes: Volumes {
                                 volume: enum_map! {
@@ -66,6 +71,8 @@ impl Profile {
                                 },
                                 volumes_linked: Some(1.),
                             },
+                            tap_target: None,
+                            record_armed: false,
                         },
                         VirtualSourceDevice {
                             description: DeviceDescription {
@@ -76,10 +83,12 @@ impl Profile {
                                     green: 139,
                                     blue: 93,
                                 },
+                                eq: None,
                             },
                             mute_states: MuteStates {
                                 mute_state: HashSet::new(),
                                 mute_targets: Default::default(),
+                                ..Default::default()
                             },
                             volumes: Volumes {
                                 volume: enum_map! {
@@ -88,6 +97,8 @@ impl Profile {
                                 },
                                 volumes_linked: Some(1.),
                             },
+                            tap_target: None,
+                            record_armed: false,
                         },
                     ],
                     device_order: enum_map! {
@@ -105,6 +116,7 @@ impl Profile {
                             id: headphones_id,
                             name: "Headphones".to_string(),
                             colour: Default::default(),
+                            eq: None,
                         },
                         mute_state: MuteState::Unmuted,
                         volume: 100,
@@ -122,6 +134,7 @@ impl Profile {
                                 green: 37,
                                 blue: 69,
                             },
+                            eq: None,
                         },
                         mute_state: MuteState::Unmuted,
                         volume: 100,
@@ -129,6 +142,9 @@ impl Profile {
 
                         attached_devices: Default::default(),
                         attached_port_maps: vec![],
+
+                        channels: None,
+                        channel_routes: Default::default(),
                     }],
 
                     device_order: enum_map! {
@@ -151,6 +167,11 @@ impl Profile {
             .collect(),
 
             audio_node_quantum: None,
+            volume_curve: VolumeCurve::default(),
+            aggregate_sink: None,
+            monitor_target: None,
+            recorder_target: None,
+            idle_unrouted_sources: false,
             application_mapping: enum_map! {
                 DeviceType::Source => {
                     HashMap::from([
@@ -163,6 +184,8 @@ impl Profile {
                     Default::default()
                 }
             },
+            eq_presets: HashMap::new(),
+            auto_mute_rules: vec![],
         }
     }
 }
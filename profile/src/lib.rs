@@ -1,7 +1,9 @@
 mod default;
 
 use enum_map::{EnumMap, enum_map};
-use pipeweaver_shared::{Colour, DeviceType, Mix, MuteState, MuteTarget, OrderGroup, Quantum};
+use pipeweaver_shared::{
+    ChannelMode, Colour, DeviceType, Mix, MuteState, MuteTarget, OrderGroup, Quantum, VolumeCurve,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use ulid::Ulid;
@@ -17,8 +19,36 @@ pub struct Profile {
     #[serde(default)]
     pub audio_node_quantum: Option<Quantum>,
 
+    /// How volumes are mapped when talking to Pipewire
+    #[serde(default)]
+    pub volume_curve: VolumeCurve,
+
     #[serde(default)]
     pub application_mapping: EnumMap<DeviceType, HashMap<String, HashMap<String, Ulid>>>,
+
+    /// Named EQ curves which can be applied to any node
+    #[serde(default)]
+    pub eq_presets: HashMap<String, EqSettings>,
+
+    /// Rules which mute a source while a target is on a specific mix
+    #[serde(default)]
+    pub auto_mute_rules: Vec<AutoMuteRule>,
+
+    /// A single system-wide sink which dispatches applications to sources
+    #[serde(default)]
+    pub aggregate_sink: Option<AggregateSink>,
+
+    /// The target new sources are routed to when created with auto monitoring
+    #[serde(default)]
+    pub monitor_target: Option<Ulid>,
+
+    /// The target sources are routed to while they're armed for recording
+    #[serde(default)]
+    pub recorder_target: Option<Ulid>,
+
+    /// Disconnect sources from their mixes while they're not routed anywhere
+    #[serde(default)]
+    pub idle_unrouted_sources: bool,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +94,9 @@ pub struct DeviceDescription {
     pub name: String,
 
     pub colour: Colour,
+
+    #[serde(default)]
+    pub eq: Option<EqSettings>,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -71,18 +104,50 @@ pub struct VirtualSourceDevice {
     pub description: DeviceDescription,
     pub mute_states: MuteStates,
     pub volumes: Volumes,
+
+    /// The Target this Source monitors, None for a regular Virtual Source
+    #[serde(default)]
+    pub tap_target: Option<Ulid>,
+
+    /// Whether this Source is routed to the Recorder Target
+    #[serde(default)]
+    pub record_armed: bool,
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MuteStates {
     pub mute_state: HashSet<MuteTarget>,
     pub mute_targets: EnumMap<MuteTarget, HashSet<Ulid>>,
+
+    /// User facing names for each of the Mute Targets (eg. 'Stream Mute')
+    #[serde(default = "default_mute_target_labels")]
+    pub mute_target_labels: EnumMap<MuteTarget, String>,
+}
+
+impl Default for MuteStates {
+    fn default() -> Self {
+        Self {
+            mute_state: Default::default(),
+            mute_targets: Default::default(),
+            mute_target_labels: default_mute_target_labels(),
+        }
+    }
+}
+
+fn default_mute_target_labels() -> EnumMap<MuteTarget, String> {
+    enum_map! {
+        MuteTarget::TargetA => String::from("Target A"),
+        MuteTarget::TargetB => String::from("Target B"),
+    }
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub struct PhysicalDeviceDescriptor {
     pub name: Option<String>,
     pub description: Option<String>,
+
+    #[serde(default)]
+    pub channel_mode: Option<ChannelMode>,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -98,6 +163,10 @@ pub struct PhysicalSourceDevice {
 
     #[serde(default)]
     pub attached_port_maps: Vec<Ulid>,
+
+    /// Whether this Source is routed to the Recorder Target
+    #[serde(default)]
+    pub record_armed: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -112,6 +181,14 @@ pub struct VirtualTargetDevice {
 
     #[serde(default)]
     pub attached_port_maps: Vec<Ulid>,
+
+    /// The number of channels this node presents, None is standard Stereo
+    #[serde(default)]
+    pub channels: Option<u8>,
+
+    /// Which channel pair a source lands on, sources not listed use the first pair
+    #[serde(default)]
+    pub channel_routes: HashMap<Ulid, u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -159,6 +236,9 @@ impl Default for VirtualTargetDevice {
 
             attached_devices: Default::default(),
             attached_port_maps: Default::default(),
+
+            channels: None,
+            channel_routes: Default::default(),
         }
     }
 }
@@ -181,6 +261,58 @@ impl Default for Volumes {
     }
 }
 
+/// While `trigger_target` is set to `trigger_mix`, `affected_source` will be muted. This mute
+/// is transient, it's not stored in the source's mute state and is lifted when the mix changes.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoMuteRule {
+    pub trigger_target: Ulid,
+    pub trigger_mix: Mix,
+    pub affected_source: Ulid,
+}
+
+/// A sink which isn't connected to anything, applications which arrive on it are moved to the
+/// source defined for them in the `application_mapping`, or to the `fallback` if they have no
+/// mapping. With no fallback, unmapped applications remain on the aggregate and are silent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregateSink {
+    pub id: Ulid,
+    pub name: String,
+    pub fallback: Option<Ulid>,
+}
+
+/// An EQ curve, either attached to a node or stored as a preset
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EqSettings {
+    pub enabled: bool,
+    pub bands: Vec<EqBand>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EqBand {
+    /// Centre frequency in Hz
+    pub frequency: f32,
+
+    /// Gain in dB
+    pub gain: f32,
+
+    /// Bandwidth of the band
+    pub q: f32,
+
+    /// The shape of the band, gain is ignored by the pass filters
+    #[serde(default)]
+    pub band_type: EqBandType,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EqBandType {
+    #[default]
+    Peak,
+    LowShelf,
+    HighShelf,
+    HighPass,
+    LowPass,
+}
+
 /// This aids in allowing port mapping to occur for devices which aren't stereo to allow us
 /// to connect them to the tree based on some user configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
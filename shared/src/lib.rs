@@ -95,6 +95,63 @@ pub enum OrderGroup {
     Hidden,
 }
 
+/// Forces how the channels of an attached physical device are fed into a node, ignoring
+/// the port count heuristic used during device discovery.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "strum", derive(Display, EnumIter))]
+#[cfg_attr(feature = "enum-map", derive(Enum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum ChannelMode {
+    /// The first two channels are Left and Right
+    Stereo,
+
+    /// The first two channels are averaged, and sent to both sides
+    MonoSum,
+
+    /// Only the first channel is used, and sent to both sides
+    #[cfg_attr(feature = "serde", serde(alias = "DualMono"))]
+    MonoFirst,
+
+    /// Only the second channel is used, and sent to both sides
+    #[cfg_attr(feature = "serde", serde(alias = "DualMonoSecond"))]
+    MonoSecond,
+}
+
+/// How a 0-100 volume is mapped to the linear channel volume Pipewire works with, this
+/// needs to match other volume controllers for external changes to be reported correctly.
+#[derive(Default, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "strum", derive(Display, EnumIter))]
+#[cfg_attr(feature = "enum-map", derive(Enum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum VolumeCurve {
+    /// The volume is cubed, as done by KDE, pwvucontrol and wpctl
+    #[default]
+    Cubic,
+
+    /// The volume is passed through as-is
+    Linear,
+}
+
+impl VolumeCurve {
+    pub fn to_linear(&self, volume: u8) -> f32 {
+        let volume = volume as f32 / 100.0;
+        match self {
+            VolumeCurve::Cubic => volume.powi(3),
+            VolumeCurve::Linear => volume,
+        }
+    }
+
+    pub fn from_linear(&self, value: f32) -> u8 {
+        let volume = match self {
+            VolumeCurve::Cubic => value.cbrt(),
+            VolumeCurve::Linear => value,
+        };
+        (volume * 100.0).round() as u8
+    }
+}
+
 #[derive(Default, Debug, Copy, Clone, Hash, Eq, PartialEq)]
 #[cfg_attr(feature = "strum", derive(Display, EnumIter))]
 #[cfg_attr(feature = "enum-map", derive(Enum))]
@@ -271,3 +328,25 @@ fn parse_nibble(b: u8) -> Result<u8, InvalidColour> {
     // Expand nibble: e.g. A → AA
     Ok((value << 4) | value)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_curves_round_trip() {
+        for curve in [VolumeCurve::Cubic, VolumeCurve::Linear] {
+            for volume in 0..=100 {
+                assert_eq!(curve.from_linear(curve.to_linear(volume)), volume);
+            }
+        }
+    }
+
+    #[test]
+    fn cubic_curve_matches_other_controllers() {
+        // wpctl reports a 50% slider as a 0.125 channel volume
+        assert_eq!(VolumeCurve::Cubic.to_linear(50), 0.125);
+        assert_eq!(VolumeCurve::Cubic.from_linear(0.125), 50);
+        assert_eq!(VolumeCurve::Linear.to_linear(50), 0.5);
+    }
+}
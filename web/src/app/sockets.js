@@ -1,5 +1,8 @@
 import {store} from '@/app/store.js'
 
+// This needs to match PROTOCOL_VERSION in the ipc crate
+const PROTOCOL_VERSION = 1
+
 export class Websocket {
   #connection_promise = []
   #disconnect_callback = undefined
@@ -18,11 +21,19 @@ export class Websocket {
       let message_id = json.id
       let message_data = json.data
 
-      if (message_data['Status'] !== undefined) {
+      if (message_data['Hello'] !== undefined) {
+        self.#fulfill_promise(message_id, message_data['Hello'], message_data['Hello'].compatible)
+      } else if (message_data['Status'] !== undefined) {
         self.#fulfill_promise(message_id, message_data, true)
       } else if (message_data['Patch'] !== undefined) {
         // Nothing ever requests patch data, so we can ignore this.
         store.patchData(message_data)
+      } else if (message_data['XrunOccurred'] !== undefined) {
+        // Broadcast when the audio graph drops out, kept in the store for the UI to read
+        store.pushXrun(message_data['XrunOccurred'])
+      } else if (message_data['OverlaysChanged'] !== undefined ||
+        message_data['RoutingMatrixChanged'] !== undefined) {
+        // Both of these are derived from the status, so the accompanying patch keeps the store in sync
       } else if (message_data === 'Ok' || message_data['Pipewire'] !== undefined) {
         if (message_data === 'Ok') {
           self.#fulfill_promise(message_id, message_data, true);
@@ -89,6 +100,10 @@ export class Websocket {
     this.#disconnect_callback = func
   }
 
+  hello() {
+    return this.#sendRequest({Hello: {client_protocol_version: PROTOCOL_VERSION}})
+  }
+
   get_status() {
     return this.#sendRequest('GetStatus')
   }
@@ -220,14 +235,15 @@ export function runWebsocket() {
         setTimeout(runWebsocket, 1000)
       })
 
-      websocket.get_status()
+      websocket.hello()
+        .then(() => websocket.get_status())
         .then((data) => {
           store.socketConnected(data)
           if (window_visible) {
             websocket_meter.connect();
           }
         })
-      // No .catch() here — if get_status() rejects, it's because the socket
+      // No .catch() here — if hello() or get_status() rejects, it's because the socket
       // closed, which already triggers on_disconnect above
     })
     .catch(() => {
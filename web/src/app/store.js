@@ -1,6 +1,8 @@
 import {reactive} from 'vue'
 import {applyOperation} from 'fast-json-patch'
 
+const MAX_XRUNS = 100
+
 export const store = reactive({
   is_connected: false,
   active: true,
@@ -19,6 +21,9 @@ export const store = reactive({
       applications: {},
     }
   },
+  // Recent xruns, newest last, capped to match the daemon's history
+  xruns: [],
+
   a11y: {
     notifications: {
       enabled: true,
@@ -139,6 +144,13 @@ export const store = reactive({
     return !this.active
   },
 
+  pushXrun(xrun) {
+    if (this.xruns.length === MAX_XRUNS) {
+      this.xruns.shift()
+    }
+    this.xruns.push(xrun)
+  },
+
   getAccessibilityNotification(type) {
     if (this.a11y.notifications.enabled) {
       return this.a11y.notifications[type]